@@ -1,15 +1,30 @@
-use windows_experiments::{Keyboard, Mouse};
+use windows_experiments::{Gamepad, HidDevice, Keyboard, Mouse};
 
 fn main() {
-    let keyboards = windows_experiments::get_devices::<Keyboard>();
-    let mice = windows_experiments::get_devices::<Mouse>();
+    let keyboards = windows_experiments::get_devices::<Keyboard>().unwrap();
+    let mice = windows_experiments::get_devices::<Mouse>().unwrap();
+    let gamepads = windows_experiments::get_devices::<Gamepad>().unwrap();
+    let hid_devices = windows_experiments::get_devices::<HidDevice>().unwrap();
 
     println!("Mice: ");
     for mouse in mice {
-        println!("{}", mouse.product_name);
+        println!("{}: {:#?}", mouse.id, mouse.info);
     }
     println!("Keyboards: ");
     for keyboard in keyboards {
-        println!("{}", keyboard.product_name);
+        println!("{}: {:#?}", keyboard.id, keyboard.info);
+    }
+    println!("Gamepads: ");
+    for gamepad in gamepads {
+        println!(
+            "{} ({}): {:#?}",
+            gamepad.id,
+            gamepad.layout().name,
+            gamepad.info
+        );
+    }
+    println!("Other HID devices: ");
+    for device in hid_devices {
+        println!("{}: {:#?}", device.id, device.info);
     }
 }
@@ -1,7 +1,14 @@
+use windows_experiments::ChannelConfig;
+
 fn main() {
-    let mut devices = windows_experiments::Devices::new();
+    let devices = windows_experiments::Devices::new();
 
-    devices.add_all_devices();
+    devices.add_all_devices().unwrap();
 
-    devices.start_listening(None);
+    let (listener, receiver) = devices.start_listening(ChannelConfig::default()).unwrap();
+    for (id, timestamp, event) in receiver.iter() {
+        println!("{:?} {}: {:?}", timestamp.0, id, event);
+    }
+    println!("dropped {} events", listener.dropped_events());
+    listener.join().unwrap();
 }
@@ -0,0 +1,38 @@
+use std::{fs::File, io::BufWriter, sync::mpsc};
+
+use windows_experiments::{backend::RawDevice, record::Recorder, Devices};
+
+/// Records every input event to the file given as the first argument until Enter is pressed.
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "session.wxin".to_string());
+
+    let devices = Devices::new();
+    devices.add_all_devices().unwrap();
+    let mice = devices.mice();
+    let keyboards = devices.keyboards();
+    let known = mice
+        .iter()
+        .map(RawDevice::from)
+        .chain(keyboards.iter().map(RawDevice::from));
+
+    let file = BufWriter::new(File::create(&path).unwrap());
+    let mut recorder = Recorder::new(file, known).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event).unwrap())
+        .unwrap();
+    let writer = std::thread::spawn(move || {
+        for event in receiver {
+            recorder.record(&event).unwrap();
+        }
+        recorder.finish().unwrap();
+    });
+
+    println!("recording to {}, press Enter to stop", path);
+    std::io::stdin().read_line(&mut String::new()).unwrap();
+    drop(listener);
+    writer.join().unwrap();
+}
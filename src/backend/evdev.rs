@@ -0,0 +1,844 @@
+//! Linux backend reading evdev nodes (`/dev/input/event*`).
+//!
+//! Device metadata comes from sysfs (`/sys/class/input/eventN/device`), so no ioctls are needed
+//! and both directories can be pointed at fixture files instead of real hardware.
+//! The one exception is the range of joystick axes, see [`JoystickLayout`].
+//! Reading the nodes usually requires membership in the `input` group.
+//! New nodes are picked up by rescanning the device directory every [`RESCAN_INTERVAL`],
+//! removed ones are noticed when reading them fails.
+//!
+//! Joysticks and gamepads have no HID reports to read, so their events are put together
+//! into reports of their own, laid out by the descriptor
+//! [`report_descriptor`](EvdevBackend::report_descriptor) returns for them.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{c_int, c_short, c_ulong, c_void},
+    fs::{self, File},
+    io::{self, Read},
+    ops::Range,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{Capture, InputBackend, RawDevice};
+use crate::{
+    ButtonState, DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Error, Event, HidUsage,
+    InputEvent, KeyCode, KeyboardDetails, MouseButton, MouseDetails, Result, Timestamp, WheelAxis,
+};
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+
+const SYN_REPORT: u16 = 0x00;
+
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const REL_HWHEEL_HI_RES: u16 = 0x0c;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_WHEEL: u16 = 0x08;
+const ABS_GAS: u16 = 0x09;
+const ABS_BRAKE: u16 = 0x0a;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+const KEY_ENTER: u16 = 28;
+const KEY_A: u16 = 30;
+const KEY_Z: u16 = 44;
+const KEY_SPACE: u16 = 57;
+const KEY_F1: u16 = 59;
+const KEY_F10: u16 = 68;
+const KEY_F11: u16 = 87;
+const KEY_F12: u16 = 88;
+const KEY_F13: u16 = 183;
+const KEY_F24: u16 = 194;
+const BTN_MISC: u16 = 0x100;
+const BTN_MOUSE: u16 = 0x110;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_GAMEPAD: u16 = 0x130;
+const BTN_DIGI: u16 = 0x140;
+const BTN_TRIGGER_HAPPY1: u16 = 0x2c0;
+const BTN_TRIGGER_HAPPY40: u16 = 0x2e7;
+
+const LED_MAX: u16 = 0x0f;
+
+/// Matches the raw input convention of 120 units per wheel notch.
+const WHEEL_DELTA: i32 = 120;
+
+/// How often the capture looks for newly connected devices.
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a reader waits for input before checking whether the capture was dropped.
+const READ_TIMEOUT_MS: c_int = 100;
+
+/// `struct input_event` as the kernel writes it. The time is always two `__kernel_ulong_t`s,
+/// even where the C library redefines it for a 64-bit `time_t`.
+#[repr(C)]
+struct InputEventRecord {
+    sec: KernelULong,
+    usec: KernelULong,
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+/// `__kernel_ulong_t`, which is 64 bits wide on x32 although pointers are not.
+#[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
+type KernelULong = u64;
+#[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
+type KernelULong = c_ulong;
+
+const EVENT_SIZE: usize = std::mem::size_of::<InputEventRecord>();
+
+/// `EVIOCGABS(0)`, which reads the `struct input_absinfo` of an axis when its code is added.
+const EVIOCGABS: c_ulong = 0x8018_4540;
+
+#[derive(Debug, Clone)]
+pub struct EvdevBackend {
+    device_dir: PathBuf,
+    sysfs_dir: PathBuf,
+}
+
+impl Default for EvdevBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvdevBackend {
+    pub fn new() -> Self {
+        Self::with_dirs("/dev/input", "/sys/class/input")
+    }
+
+    /// Uses `device_dir` in place of `/dev/input` and `sysfs_dir` in place of `/sys/class/input`.
+    ///
+    /// For every `eventN` file in `device_dir`, the name and capabilities are read from
+    /// `sysfs_dir/eventN/device/{name, capabilities/rel, capabilities/key, capabilities/abs}`.
+    pub fn with_dirs(device_dir: impl Into<PathBuf>, sysfs_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            device_dir: device_dir.into(),
+            sysfs_dir: sysfs_dir.into(),
+        }
+    }
+
+    /// Lists the `eventN` nodes in the device directory together with their number.
+    fn nodes(&self) -> Result<Vec<(usize, PathBuf)>> {
+        let entries = fs::read_dir(&self.device_dir)
+            .map_err(|err| Error::Enumeration(err.raw_os_error().unwrap_or(0)))?;
+        let mut nodes: Vec<_> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let number = entry
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix("event")?
+                    .parse()
+                    .ok()?;
+                Some((number, entry.path()))
+            })
+            .collect();
+        nodes.sort();
+        Ok(nodes)
+    }
+
+    fn device_dir(&self, number: usize) -> PathBuf {
+        self.sysfs_dir
+            .join(format!("event{}", number))
+            .join("device")
+    }
+
+    /// The layout of a node's reports, if it is a joystick or gamepad.
+    fn joystick(&self, number: usize) -> Option<JoystickLayout> {
+        let device_dir = self.device_dir(number);
+        JoystickLayout::new(
+            &read_capabilities(&device_dir.join("capabilities/key")),
+            &read_capabilities(&device_dir.join("capabilities/abs")),
+        )
+    }
+
+    fn device(&self, number: usize) -> Option<RawDevice> {
+        let device_dir = self.device_dir(number);
+        let rel = read_capabilities(&device_dir.join("capabilities/rel"));
+        let key = read_capabilities(&device_dir.join("capabilities/key"));
+        let led = read_capabilities(&device_dir.join("capabilities/led"));
+        let abs = read_capabilities(&device_dir.join("capabilities/abs"));
+
+        let (kind, details) =
+            if has_bit(&rel, REL_X) && has_bit(&rel, REL_Y) && has_bit(&key, BTN_LEFT) {
+                let details = MouseDetails {
+                    id: 0,
+                    buttons: count_bits(&key, BTN_MOUSE..BTN_JOYSTICK),
+                    sample_rate: 0,
+                    has_horizontal_wheel: has_bit(&rel, REL_HWHEEL),
+                };
+                (DeviceKind::Mouse, DeviceDetails::Mouse(details))
+            } else if [KEY_A, KEY_Z, KEY_ENTER, KEY_SPACE]
+                .iter()
+                .all(|&code| has_bit(&key, code))
+            {
+                let details = KeyboardDetails {
+                    function_keys: count_bits(&key, KEY_F1..KEY_F10 + 1)
+                        + count_bits(&key, KEY_F11..KEY_F12 + 1)
+                        + count_bits(&key, KEY_F13..KEY_F24 + 1),
+                    indicators: count_bits(&led, 0..LED_MAX),
+                    total_keys: count_bits(&key, 1..BTN_MISC),
+                    ..Default::default()
+                };
+                (DeviceKind::Keyboard, DeviceDetails::Keyboard(details))
+            } else if let Some(joystick) = JoystickLayout::new(&key, &abs) {
+                (DeviceKind::Hid, DeviceDetails::Hid(joystick.usage))
+            } else {
+                return None;
+            };
+
+        let product_name = read_string(&device_dir.join("name"))?;
+        let id = |name: &str| {
+            read_string(&device_dir.join("id").join(name))
+                .and_then(|id| u16::from_str_radix(&id, 16).ok())
+                .unwrap_or(0)
+        };
+        // the USB device, if any, sits above the HID device and the USB interface
+        let usb_dir = device_dir.join("device/../..");
+        let serial_number =
+            read_string(&device_dir.join("uniq")).or_else(|| read_string(&usb_dir.join("serial")));
+
+        Some(RawDevice {
+            handle: DeviceHandle(number),
+            kind,
+            info: DeviceInfo {
+                product_name,
+                manufacturer: read_string(&usb_dir.join("manufacturer")),
+                serial_number,
+                vendor_id: id("vendor"),
+                product_id: id("product"),
+                version: id("version"),
+                path: self
+                    .device_dir
+                    .join(format!("event{}", number))
+                    .to_string_lossy()
+                    .into_owned(),
+                location: read_string(&device_dir.join("phys")),
+                details,
+            },
+        })
+    }
+}
+
+/// Reads a sysfs attribute, treating an empty one like a missing one.
+fn read_string(path: &Path) -> Option<String> {
+    let string = fs::read_to_string(path).ok()?;
+    let string = string.trim_end();
+    (!string.is_empty()).then(|| string.to_string())
+}
+
+/// Reads a sysfs capability bitmap, printed as hex longs with the most significant one first.
+/// The result holds the least significant long first.
+fn read_capabilities(path: &Path) -> Vec<usize> {
+    fs::read_to_string(path)
+        .map(|bitmap| {
+            bitmap
+                .split_whitespace()
+                .rev()
+                .map(|word| usize::from_str_radix(word, 16).unwrap_or(0))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn count_bits(bitmap: &[usize], bits: Range<u16>) -> u32 {
+    bits.filter(|&bit| has_bit(bitmap, bit)).count() as u32
+}
+
+fn has_bit(bitmap: &[usize], bit: u16) -> bool {
+    let bits = usize::BITS as usize;
+    bitmap
+        .get(bit as usize / bits)
+        .is_some_and(|word| word & (1 << (bit as usize % bits)) != 0)
+}
+
+impl InputBackend for EvdevBackend {
+    type Capture = EvdevCapture;
+
+    fn enumerate(&self) -> Result<Vec<RawDevice>> {
+        Ok(self
+            .nodes()?
+            .into_iter()
+            .filter_map(|(number, _)| self.device(number))
+            .collect())
+    }
+
+    /// Joysticks and gamepads are only read if their usage is among `hid_usages`.
+    ///
+    /// Fails with [`Error::Registration`] and the error of the last attempt if there are
+    /// devices to read but none of their nodes could be opened.
+    fn start_capture(&self, hid_usages: &[HidUsage]) -> Result<Self::Capture> {
+        let (sender, receiver) = mpsc::channel();
+        let mut capture = EvdevCapture {
+            backend: self.clone(),
+            hid_usages: hid_usages.to_vec(),
+            sender,
+            receiver,
+            stop: Arc::new(AtomicBool::new(false)),
+            readers: HashMap::new(),
+            superseded: HashSet::new(),
+            last_scan: Instant::now(),
+            motion: HashMap::new(),
+            joysticks: HashMap::new(),
+        };
+        let mut failure = None;
+        for (number, path) in self.nodes()? {
+            if self
+                .device(number)
+                .is_some_and(|device| capture.wants(&device))
+            {
+                if let Err(err) = capture.open(number, &path) {
+                    failure = Some(err);
+                }
+            }
+        }
+        // a capture without any readers would never deliver input, which usually means
+        // the nodes are not readable for lack of permissions
+        match failure {
+            Some(err) if capture.readers.is_empty() => {
+                Err(Error::Registration(err.raw_os_error().unwrap_or(0)))
+            }
+            _ => Ok(capture),
+        }
+    }
+
+    /// Reads `sysfs_dir/eventN/device/device/report_descriptor`, which only devices
+    /// connected through the HID layer, like USB and Bluetooth ones, have.
+    ///
+    /// Joysticks and gamepads get the descriptor of the reports the capture puts together
+    /// from their events instead, see [`JoystickLayout::descriptor`].
+    fn report_descriptor(&self, handle: DeviceHandle) -> Result<Vec<u8>> {
+        if let Some(joystick) = self.joystick(handle.0) {
+            return Ok(joystick.descriptor());
+        }
+        fs::read(self.device_dir(handle.0).join("device/report_descriptor"))
+            .map_err(|err| Error::DeviceInfo(err.raw_os_error().unwrap_or(0)))
+    }
+}
+
+/// The buttons and axes of a joystick or gamepad node, which its reports hold in this
+/// order: a bit for each button, padded to a byte, 16 bits for each axis and a byte for
+/// the hat switch made of `ABS_HAT0X` and `ABS_HAT0Y`.
+///
+/// Axes are mapped to the usages the kernel's HID driver maps to them, and scaled from the
+/// range `EVIOCGABS` reads to that of an `i16`. Values of nodes whose ranges can't be read
+/// are assumed to be in that range already.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct JoystickLayout {
+    /// [`HidUsage::GAMEPAD`] for nodes with gamepad buttons, [`HidUsage::JOYSTICK`] otherwise.
+    usage: HidUsage,
+    /// The key codes of the buttons, which are numbered from 1 in this order.
+    buttons: Vec<u16>,
+    /// The codes of the axes and their usages.
+    axes: Vec<(u16, HidUsage)>,
+    hat: bool,
+}
+
+impl JoystickLayout {
+    /// Reads the layout from the `key` and `abs` capabilities. Nodes need an X and Y axis
+    /// and a joystick or gamepad button to count.
+    fn new(key: &[usize], abs: &[usize]) -> Option<Self> {
+        let buttons: Vec<_> = (BTN_JOYSTICK..BTN_DIGI)
+            .chain(BTN_TRIGGER_HAPPY1..BTN_TRIGGER_HAPPY40 + 1)
+            .filter(|&code| has_bit(key, code))
+            .collect();
+        if !(has_bit(abs, ABS_X) && has_bit(abs, ABS_Y)) || buttons.is_empty() {
+            return None;
+        }
+        let axes = (ABS_X..ABS_BRAKE + 1)
+            .filter(|&code| has_bit(abs, code))
+            .map(|code| {
+                let usage = match code {
+                    // X, Y, Z, Rx, Ry, Rz, Slider, Dial and Wheel
+                    ABS_X..=ABS_WHEEL => HidUsage::new(0x01, 0x30 + code),
+                    // Accelerator and Brake of the simulation controls page
+                    ABS_GAS => HidUsage::new(0x02, 0xC4),
+                    _ => HidUsage::new(0x02, 0xC5),
+                };
+                (code, usage)
+            })
+            .collect();
+        Some(Self {
+            usage: if buttons.contains(&BTN_GAMEPAD) {
+                HidUsage::GAMEPAD
+            } else {
+                HidUsage::JOYSTICK
+            },
+            buttons,
+            axes,
+            hat: has_bit(abs, ABS_HAT0X) && has_bit(abs, ABS_HAT0Y),
+        })
+    }
+
+    /// The report descriptor of the reports.
+    fn descriptor(&self) -> Vec<u8> {
+        let usage = self.usage.id as u8;
+        let mut descriptor = vec![0x05, 0x01, 0x09, usage, 0xA1, 0x01];
+        let count = self.buttons.len() as u8;
+        descriptor.extend([
+            0x05, 0x09, 0x19, 0x01, 0x29, count, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, count,
+            0x81, 0x02,
+        ]);
+        if !count.is_multiple_of(8) {
+            descriptor.extend([0x75, 0x01, 0x95, 8 - count % 8, 0x81, 0x03]);
+        }
+        for (_, usage) in &self.axes {
+            descriptor.extend([0x05, usage.page as u8, 0x09, usage.id as u8]);
+            descriptor.extend([0x16, 0x00, 0x80, 0x26, 0xFF, 0x7F, 0x75, 0x10, 0x95, 0x01]);
+            descriptor.extend([0x81, 0x02]);
+        }
+        if self.hat {
+            // eight directions clockwise from up in degrees, anything else is centered
+            descriptor.extend([
+                0x05, 0x01, 0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x35, 0x00, 0x46, 0x3B, 0x01, 0x65,
+                0x14, 0x75, 0x08, 0x95, 0x01, 0x81, 0x42,
+            ]);
+        }
+        descriptor.push(0xC0);
+        descriptor
+    }
+}
+
+/// The state of a joystick or gamepad, from which its reports are put together.
+#[derive(Debug)]
+struct Joystick {
+    layout: JoystickLayout,
+    /// The range of each axis, if it could be read.
+    ranges: Vec<Option<(i32, i32)>>,
+    pressed: Vec<bool>,
+    axes: Vec<i16>,
+    hat: [i32; 2],
+    /// Whether anything changed since the last report.
+    changed: bool,
+}
+
+impl Joystick {
+    /// Reads the ranges and current values of the axes from the node.
+    fn new(layout: JoystickLayout, file: &File) -> Self {
+        let absinfo: Vec<_> = layout
+            .axes
+            .iter()
+            .map(|&(code, _)| read_absinfo(file, code))
+            .collect();
+        let mut joystick = Self {
+            ranges: absinfo
+                .iter()
+                .map(|info| info.map(|info| (info[1], info[2])))
+                .collect(),
+            pressed: vec![false; layout.buttons.len()],
+            axes: vec![0; layout.axes.len()],
+            hat: [0; 2],
+            changed: false,
+            layout,
+        };
+        for (index, info) in absinfo.iter().enumerate() {
+            if let Some(info) = info {
+                joystick.axes[index] = joystick.scale(index, info[0]);
+            }
+        }
+        joystick
+    }
+
+    /// Applies an `EV_KEY` or `EV_ABS` event, returning whether it was one of the node's.
+    fn update(&mut self, raw: &RawEvent) -> bool {
+        let layout = &self.layout;
+        match raw.kind {
+            EV_KEY => match layout.buttons.iter().position(|&code| code == raw.code) {
+                Some(index) => self.pressed[index] = raw.value != 0,
+                None => return false,
+            },
+            EV_ABS if layout.hat && matches!(raw.code, ABS_HAT0X | ABS_HAT0Y) => {
+                self.hat[(raw.code - ABS_HAT0X) as usize] = raw.value.signum();
+            }
+            EV_ABS => match layout.axes.iter().position(|&(code, _)| code == raw.code) {
+                Some(index) => self.axes[index] = self.scale(index, raw.value),
+                None => return false,
+            },
+            _ => return false,
+        }
+        self.changed = true;
+        true
+    }
+
+    /// Scales the value of an axis to the range of an `i16`.
+    fn scale(&self, index: usize, value: i32) -> i16 {
+        let value = match self.ranges[index] {
+            Some((min, max)) if min < max => {
+                let (value, min, max) = (value as i64, min as i64, max as i64);
+                (value - min) * 0xFFFF / (max - min) + i16::MIN as i64
+            }
+            _ => value as i64,
+        };
+        value.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+    }
+
+    /// The report laid out by [`JoystickLayout::descriptor`].
+    fn report(&self) -> Vec<u8> {
+        let mut report = vec![0u8; self.pressed.len().div_ceil(8)];
+        for (index, _) in self
+            .pressed
+            .iter()
+            .enumerate()
+            .filter(|(_, &pressed)| pressed)
+        {
+            report[index / 8] |= 1 << (index % 8);
+        }
+        for axis in &self.axes {
+            report.extend(axis.to_le_bytes());
+        }
+        if self.layout.hat {
+            // clockwise from up, with y positive downwards
+            let direction = match self.hat {
+                [0, -1] => 0,
+                [1, -1] => 1,
+                [1, 0] => 2,
+                [1, 1] => 3,
+                [0, 1] => 4,
+                [-1, 1] => 5,
+                [-1, 0] => 6,
+                [-1, -1] => 7,
+                _ => 8,
+            };
+            report.push(direction);
+        }
+        report
+    }
+}
+
+/// Reads the value, minimum, maximum, fuzz, flat and resolution of an axis, which fails
+/// for anything but a real evdev node.
+fn read_absinfo(file: &File, code: u16) -> Option<[i32; 6]> {
+    let mut info = [0i32; 6];
+    // SAFETY: The request writes a struct input_absinfo, which is six ints
+    let result = unsafe {
+        ioctl(
+            file.as_raw_fd(),
+            EVIOCGABS + code as c_ulong,
+            info.as_mut_ptr() as *mut c_void,
+        )
+    };
+    (result >= 0).then_some(info)
+}
+
+/// What reader threads send to the capture.
+enum Message {
+    Input(DeviceHandle, RawEvent),
+    /// Reading the node failed, usually because the device was unplugged.
+    Gone(DeviceHandle),
+}
+
+/// `struct pollfd`.
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+const POLLIN: c_short = 0x1;
+
+extern "C" {
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+/// Reads raw `input_event` records from one node until it ends or `stop` is set.
+///
+/// Reads only happen once `poll` says the node is readable, so the thread checks `stop`
+/// at least every [`READ_TIMEOUT_MS`] even if the device stays quiet.
+fn read_node(mut file: File, handle: DeviceHandle, sender: Sender<Message>, stop: Arc<AtomicBool>) {
+    let mut record = [0u8; EVENT_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        let mut fd = PollFd {
+            fd: file.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        };
+        // SAFETY: A single pollfd, valid for the duration of the call
+        let ready = unsafe { poll(&mut fd, 1, READ_TIMEOUT_MS) };
+        if ready == 0
+            || (ready < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted)
+        {
+            continue;
+        }
+        // errors and hangups are reported by the read as well
+        if ready < 0 || file.read_exact(&mut record).is_err() {
+            break;
+        }
+        if sender
+            .send(Message::Input(handle, RawEvent::parse(&record)))
+            .is_err()
+        {
+            return;
+        }
+    }
+    if !stop.load(Ordering::Relaxed) {
+        let _ = sender.send(Message::Gone(handle));
+    }
+}
+
+/// One `struct input_event`.
+#[derive(Debug, Clone, Copy)]
+struct RawEvent {
+    time: Duration,
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+impl RawEvent {
+    fn parse(record: &[u8; EVENT_SIZE]) -> Self {
+        // SAFETY: The record has the size of an `InputEventRecord`, any bytes make a valid one,
+        // and the read doesn't rely on the array being aligned
+        let record: InputEventRecord = unsafe { std::ptr::read_unaligned(record.as_ptr().cast()) };
+        RawEvent {
+            time: Duration::from_secs(record.sec as u64)
+                + Duration::from_micros(record.usec as u64),
+            kind: record.kind,
+            code: record.code,
+            value: record.value,
+        }
+    }
+}
+
+pub struct EvdevCapture {
+    backend: EvdevBackend,
+    /// The usages of the joysticks and gamepads to read.
+    hid_usages: Vec<HidUsage>,
+    /// Handed to the readers of nodes that appear later.
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    /// Tells the readers to finish once the capture is dropped.
+    stop: Arc<AtomicBool>,
+    /// The reader of each open node, by node number.
+    readers: HashMap<usize, JoinHandle<()>>,
+    /// Low-resolution wheel axes to ignore because the device also reports them in high
+    /// resolution, which the kernel does in parallel.
+    superseded: HashSet<(DeviceHandle, u16)>,
+    last_scan: Instant,
+    /// Relative motion accumulated per device until the next `SYN_REPORT`.
+    motion: HashMap<DeviceHandle, (i32, i32)>,
+    joysticks: HashMap<DeviceHandle, Joystick>,
+}
+
+impl Capture for EvdevCapture {
+    fn poll_events(&mut self, timeout: Duration, events: &mut Vec<Event>) -> Result<()> {
+        if self.last_scan.elapsed() >= RESCAN_INTERVAL {
+            self.rescan(events);
+        }
+        match self.receiver.recv_timeout(timeout) {
+            Ok(message) => self.handle(message, events),
+            // the capture holds a sender itself, so this cannot happen
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(timeout),
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+        }
+        while let Ok(message) = self.receiver.try_recv() {
+            self.handle(message, events);
+        }
+        Ok(())
+    }
+}
+
+impl EvdevCapture {
+    /// Whether the node of a device is to be read.
+    fn wants(&self, device: &RawDevice) -> bool {
+        match device.info.details {
+            DeviceDetails::Hid(usage) => self.hid_usages.contains(&usage),
+            _ => true,
+        }
+    }
+
+    /// Starts a reader for a node.
+    fn open(&mut self, number: usize, path: &Path) -> io::Result<()> {
+        let file = File::open(path)?;
+        if let Some(layout) = self.backend.joystick(number) {
+            let joystick = Joystick::new(layout, &file);
+            self.joysticks.insert(DeviceHandle(number), joystick);
+        }
+        let sender = self.sender.clone();
+        let stop = self.stop.clone();
+        let reader = thread::Builder::new()
+            .name(format!("evdev reader event{}", number))
+            .spawn(move || read_node(file, DeviceHandle(number), sender, stop))?;
+        self.readers.insert(number, reader);
+        let rel = read_capabilities(&self.backend.device_dir(number).join("capabilities/rel"));
+        for (hi_res, low_res) in [
+            (REL_WHEEL_HI_RES, REL_WHEEL),
+            (REL_HWHEEL_HI_RES, REL_HWHEEL),
+        ] {
+            if has_bit(&rel, hi_res) {
+                self.superseded.insert((DeviceHandle(number), low_res));
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the devices that appeared since the last scan and reports them.
+    fn rescan(&mut self, events: &mut Vec<Event>) {
+        self.last_scan = Instant::now();
+        // a failed scan is retried with the next one
+        for (number, path) in self.backend.nodes().unwrap_or_default() {
+            if self.readers.contains_key(&number) {
+                continue;
+            }
+            let Some(device) = self
+                .backend
+                .device(number)
+                .filter(|device| self.wants(device))
+            else {
+                continue;
+            };
+            if self.open(number, &path).is_ok() {
+                events.push(Event {
+                    device: device.handle,
+                    timestamp: now(),
+                    kind: InputEvent::DeviceAdded {
+                        kind: device.kind,
+                        info: device.info,
+                    },
+                });
+            }
+        }
+    }
+
+    fn handle(&mut self, message: Message, events: &mut Vec<Event>) {
+        match message {
+            Message::Input(device, raw) => self.decode(device, raw, events),
+            Message::Gone(device) => {
+                // the reader sent this as its last message, so it is about to finish
+                if let Some(reader) = self.readers.remove(&device.0) {
+                    let _ = reader.join();
+                }
+                self.superseded.retain(|&(handle, _)| handle != device);
+                self.motion.remove(&device);
+                self.joysticks.remove(&device);
+                events.push(Event {
+                    device,
+                    timestamp: now(),
+                    kind: InputEvent::DeviceRemoved,
+                });
+            }
+        }
+    }
+
+    fn decode(&mut self, device: DeviceHandle, raw: RawEvent, events: &mut Vec<Event>) {
+        let timestamp = Timestamp(raw.time);
+        let mut push = |kind| {
+            events.push(Event {
+                device,
+                timestamp,
+                kind,
+            })
+        };
+
+        if let Some(joystick) = self.joysticks.get_mut(&device) {
+            if (raw.kind, raw.code) == (EV_SYN, SYN_REPORT) && joystick.changed {
+                joystick.changed = false;
+                push(InputEvent::HidReport {
+                    report: joystick.report(),
+                });
+            }
+            if joystick.update(&raw) {
+                return;
+            }
+        }
+
+        match (raw.kind, raw.code) {
+            (EV_SYN, SYN_REPORT) => {
+                if let Some((dx, dy)) = self.motion.remove(&device) {
+                    push(InputEvent::MouseMotion { dx, dy });
+                }
+            }
+            (EV_REL, REL_X) => self.motion.entry(device).or_default().0 += raw.value,
+            (EV_REL, REL_Y) => self.motion.entry(device).or_default().1 += raw.value,
+            (EV_REL, code) if self.superseded.contains(&(device, code)) => {}
+            (EV_REL, REL_WHEEL) => push(InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                delta: wheel_delta(raw.value.saturating_mul(WHEEL_DELTA)),
+            }),
+            (EV_REL, REL_HWHEEL) => push(InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                delta: wheel_delta(raw.value.saturating_mul(WHEEL_DELTA)),
+            }),
+            // already in 1/120 of a notch
+            (EV_REL, REL_WHEEL_HI_RES) => push(InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                delta: wheel_delta(raw.value),
+            }),
+            (EV_REL, REL_HWHEEL_HI_RES) => push(InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                delta: wheel_delta(raw.value),
+            }),
+            (EV_KEY, code) => {
+                let state = match raw.value {
+                    0 => ButtonState::Released,
+                    1 => ButtonState::Pressed,
+                    // autorepeat
+                    _ => return,
+                };
+                let button = match code {
+                    BTN_LEFT => Some(MouseButton::Left),
+                    BTN_RIGHT => Some(MouseButton::Right),
+                    BTN_MIDDLE => Some(MouseButton::Middle),
+                    BTN_SIDE => Some(MouseButton::Back),
+                    BTN_EXTRA => Some(MouseButton::Forward),
+                    _ => None,
+                };
+                if let Some(button) = button {
+                    push(InputEvent::MouseButton { button, state });
+                } else if code < BTN_MISC {
+                    if let Some(scancode) = KeyCode::from_evdev(code).and_then(KeyCode::scancode) {
+                        push(InputEvent::Key { scancode, state });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for EvdevCapture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for (_, reader) in self.readers.drain() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Clamps a wheel movement in 1/120 of a notch to what fits the event.
+fn wheel_delta(delta: i32) -> i16 {
+    delta.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
+/// The current time on the clock evdev timestamps events with by default.
+fn now() -> Timestamp {
+    Timestamp(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
@@ -0,0 +1,172 @@
+//! An in-memory backend for tests.
+//!
+//! Devices and events are scripted through a [`MockBackend`], and the same backend (or a clone,
+//! which shares its state) is handed to [`Devices::with_backend`](crate::Devices::with_backend)
+//! or [`get_devices_from`](crate::get_devices_from).
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use super::{Capture, InputBackend, RawDevice};
+use crate::{
+    DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Error, Event, HidUsage, InputEvent,
+    Result, Timestamp,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    pushed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    devices: Vec<RawDevice>,
+    events: VecDeque<Event>,
+    hid_usages: Vec<HidUsage>,
+    report_descriptors: HashMap<DeviceHandle, Vec<u8>>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_mouse(&self, product_name: &str, handle: DeviceHandle) {
+        let info = DeviceInfo {
+            product_name: product_name.to_string(),
+            details: DeviceDetails::Mouse(Default::default()),
+            ..Default::default()
+        };
+        self.add_device(DeviceKind::Mouse, handle, info);
+    }
+
+    pub fn add_keyboard(&self, product_name: &str, handle: DeviceHandle) {
+        let info = DeviceInfo {
+            product_name: product_name.to_string(),
+            details: DeviceDetails::Keyboard(Default::default()),
+            ..Default::default()
+        };
+        self.add_device(DeviceKind::Keyboard, handle, info);
+    }
+
+    pub fn add_hid(&self, product_name: &str, handle: DeviceHandle, usage: HidUsage) {
+        let info = DeviceInfo {
+            product_name: product_name.to_string(),
+            details: DeviceDetails::Hid(usage),
+            ..Default::default()
+        };
+        self.add_device(DeviceKind::Hid, handle, info);
+    }
+
+    /// The HID usages registered by the last capture started.
+    pub fn hid_usages(&self) -> Vec<HidUsage> {
+        self.shared.state.lock().unwrap().hid_usages.clone()
+    }
+
+    /// Sets the report descriptor returned for a device; devices without one are unsupported.
+    pub fn set_report_descriptor(&self, handle: DeviceHandle, descriptor: &[u8]) {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .report_descriptors
+            .insert(handle, descriptor.to_vec());
+    }
+
+    /// Declares a device with full metadata.
+    pub fn add_device(&self, kind: DeviceKind, handle: DeviceHandle, info: DeviceInfo) {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .devices
+            .push(RawDevice { handle, kind, info });
+    }
+
+    /// Declares a device and queues the [`InputEvent::DeviceAdded`] announcing it,
+    /// as if it was plugged in while listening.
+    pub fn connect(
+        &self,
+        kind: DeviceKind,
+        handle: DeviceHandle,
+        info: DeviceInfo,
+        timestamp: Timestamp,
+    ) {
+        self.add_device(kind, handle, info.clone());
+        self.push_event(handle, timestamp, InputEvent::DeviceAdded { kind, info });
+    }
+
+    /// Forgets a device and queues the [`InputEvent::DeviceRemoved`] announcing it.
+    pub fn disconnect(&self, handle: DeviceHandle, timestamp: Timestamp) {
+        self.shared
+            .state
+            .lock()
+            .unwrap()
+            .devices
+            .retain(|device| device.handle != handle);
+        self.push_event(handle, timestamp, InputEvent::DeviceRemoved);
+    }
+
+    /// Queues an event for delivery.
+    /// Events pushed before listening starts are delivered as soon as it does.
+    pub fn push_event(&self, device: DeviceHandle, timestamp: Timestamp, kind: InputEvent) {
+        self.shared.state.lock().unwrap().events.push_back(Event {
+            device,
+            timestamp,
+            kind,
+        });
+        self.shared.pushed.notify_all();
+    }
+}
+
+impl InputBackend for MockBackend {
+    type Capture = MockCapture;
+
+    fn enumerate(&self) -> Result<Vec<RawDevice>> {
+        Ok(self.shared.state.lock().unwrap().devices.clone())
+    }
+
+    fn start_capture(&self, hid_usages: &[HidUsage]) -> Result<Self::Capture> {
+        self.shared.state.lock().unwrap().hid_usages = hid_usages.to_vec();
+        Ok(MockCapture {
+            shared: self.shared.clone(),
+        })
+    }
+
+    fn report_descriptor(&self, handle: DeviceHandle) -> Result<Vec<u8>> {
+        let state = self.shared.state.lock().unwrap();
+        state
+            .report_descriptors
+            .get(&handle)
+            .cloned()
+            .ok_or(Error::Unsupported)
+    }
+}
+
+/// Takes the scripted events out of a [`MockBackend`].
+/// With several captures running, each event goes to only one of them.
+pub struct MockCapture {
+    shared: Arc<Shared>,
+}
+
+impl Capture for MockCapture {
+    fn poll_events(&mut self, timeout: Duration, events: &mut Vec<Event>) -> Result<()> {
+        let state = self.shared.state.lock().unwrap();
+        let (mut state, _) = self
+            .shared
+            .pushed
+            .wait_timeout_while(state, timeout, |state| state.events.is_empty())
+            .unwrap();
+        events.extend(state.events.drain(..));
+        Ok(())
+    }
+}
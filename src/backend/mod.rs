@@ -0,0 +1,94 @@
+//! Platform backends that enumerate devices and capture their input.
+//!
+//! [`Devices`](crate::Devices) and [`get_devices`](crate::get_devices) only talk to an
+//! [`InputBackend`], so the public API is the same on every platform.
+//! [`DefaultBackend`] is the native backend of the platform being compiled for.
+
+use std::time::Duration;
+
+use crate::{
+    hid::ReportDescriptor, DeviceHandle, DeviceInfo, DeviceKind, Error, Event, HidUsage, Result,
+};
+
+#[cfg(target_os = "linux")]
+pub mod evdev;
+pub mod mock;
+#[cfg(windows)]
+pub mod raw_input;
+pub mod replay;
+
+#[cfg(windows)]
+pub type DefaultBackend = raw_input::RawInputBackend;
+#[cfg(target_os = "linux")]
+pub type DefaultBackend = evdev::EvdevBackend;
+#[cfg(not(any(windows, target_os = "linux")))]
+pub type DefaultBackend = NullBackend;
+
+/// A device as reported by a backend's enumeration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawDevice {
+    pub handle: DeviceHandle,
+    pub kind: DeviceKind,
+    pub info: DeviceInfo,
+}
+
+pub trait InputBackend: Send + Sync + 'static {
+    type Capture: Capture;
+
+    /// Lists the devices currently connected.
+    fn enumerate(&self) -> Result<Vec<RawDevice>>;
+
+    /// Starts capturing input from all mice and keyboards, and the reports of HID devices
+    /// with one of `hid_usages` where the backend supports it.
+    ///
+    /// This is called on the listener thread, which keeps polling the returned capture until
+    /// it is stopped and then drops it, so any per-thread OS resources belong in the capture.
+    fn start_capture(&self, hid_usages: &[HidUsage]) -> Result<Self::Capture>;
+
+    /// Reads the raw HID report descriptor of a device, for
+    /// [`ReportDescriptor::parse`](crate::hid::ReportDescriptor::parse).
+    ///
+    /// Backends that have no access to descriptors return [`Error::Unsupported`].
+    fn report_descriptor(&self, handle: DeviceHandle) -> Result<Vec<u8>> {
+        let _ = handle;
+        Err(Error::Unsupported)
+    }
+
+    /// Reads the report descriptor of a device and parses it.
+    ///
+    /// Backends that can't read the descriptor itself but know the fields it declares build
+    /// the [`ReportDescriptor`] from those instead.
+    fn parse_report_descriptor(&self, handle: DeviceHandle) -> Result<ReportDescriptor> {
+        ReportDescriptor::parse(&self.report_descriptor(handle)?).map_err(Error::Descriptor)
+    }
+}
+
+pub trait Capture {
+    /// Waits up to `timeout` for input and appends whatever arrived to `events`.
+    ///
+    /// An error ends the capture.
+    fn poll_events(&mut self, timeout: Duration, events: &mut Vec<Event>) -> Result<()>;
+}
+
+/// A backend without any devices, used on platforms that have no native backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullBackend;
+
+impl InputBackend for NullBackend {
+    type Capture = NullBackend;
+
+    fn enumerate(&self) -> Result<Vec<RawDevice>> {
+        Ok(vec![])
+    }
+
+    fn start_capture(&self, _hid_usages: &[HidUsage]) -> Result<Self::Capture> {
+        Ok(NullBackend)
+    }
+}
+
+impl Capture for NullBackend {
+    fn poll_events(&mut self, timeout: Duration, _events: &mut Vec<Event>) -> Result<()> {
+        std::thread::sleep(timeout);
+        Ok(())
+    }
+}
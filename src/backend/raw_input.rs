@@ -0,0 +1,649 @@
+//! Windows backend built on the Raw Input API.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::{c_void, OsStr},
+    os::windows::ffi::OsStrExt,
+    time::{Duration, Instant},
+};
+
+use windows::{
+    core::{PCWSTR, PWSTR},
+    Win32::{
+        Devices::HumanInterfaceDevice::{
+            HidD_GetAttributes, HidD_GetManufacturerString, HidD_GetProductString,
+            HidD_GetSerialNumberString, HIDD_ATTRIBUTES,
+        },
+        Foundation::{
+            self, CloseHandle, GetLastError, BOOLEAN, ERROR_CLASS_ALREADY_EXISTS, HANDLE, HWND,
+        },
+        Storage::FileSystem::{FILE_ATTRIBUTE_READONLY, FILE_SHARE_READ, OPEN_EXISTING},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Input::{
+                GetRawInputBuffer, GetRawInputDeviceInfoW, GetRawInputDeviceList,
+                RegisterRawInputDevices, RAWINPUT, RAWINPUTDEVICE, RAWINPUTDEVICELIST,
+                RAWINPUTDEVICE_FLAGS, RAWINPUTHEADER, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK,
+                RIDEV_REMOVE, RIDI_DEVICEINFO, RIDI_DEVICENAME, RIDI_PREPARSEDDATA,
+                RID_DEVICE_INFO, RIM_TYPEHID, RIM_TYPEKEYBOARD, RIM_TYPEMOUSE,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetSystemMetrics,
+                MsgWaitForMultipleObjects, PeekMessageW, RegisterClassExW, CW_USEDEFAULT,
+                GIDC_ARRIVAL, GIDC_REMOVAL, HMENU, HWND_MESSAGE, MSG, PM_REMOVE, QS_ALLINPUT,
+                SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN, SM_CYVIRTUALSCREEN,
+                SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, WINDOW_EX_STYLE, WINDOW_STYLE,
+                WM_INPUT_DEVICE_CHANGE, WNDCLASSEXW, WNDCLASS_STYLES,
+            },
+        },
+    },
+};
+
+use super::{Capture, InputBackend, RawDevice};
+use crate::{
+    decode::{decode_mouse, Desktop, KeyboardDecoder, RawKeyboard, RawMouse},
+    hid::ReportDescriptor,
+    DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Error, Event, HidUsage, InputEvent,
+    KeyboardDetails, MouseDetails, Result, Timestamp,
+};
+
+/// Receive input even when the window is not in the foreground, which ours never is,
+/// and get `WM_INPUT_DEVICE_CHANGE` when devices come and go.
+const DW_FLAG: RAWINPUTDEVICE_FLAGS = RAWINPUTDEVICE_FLAGS(RIDEV_INPUTSINK.0 | RIDEV_DEVNOTIFY.0);
+
+thread_local! {
+    /// `WM_INPUT_DEVICE_CHANGE` notifications seen by the window procedure of this thread's
+    /// window, as the `GIDC_*` code and the device handle, waiting to be turned into events.
+    static DEVICE_CHANGES: RefCell<Vec<(u32, usize)>> = const { RefCell::new(vec![]) };
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawInputBackend;
+
+impl InputBackend for RawInputBackend {
+    type Capture = RawInputCapture;
+
+    fn enumerate(&self) -> Result<Vec<RawDevice>> {
+        let mut num_devices = 0;
+        let device_list_size = std::mem::size_of::<RAWINPUTDEVICELIST>() as u32;
+
+        // poll the number of devices
+        // SAFETY: We are not providing a buffer, just polling the required size of the future buffer
+        let result = unsafe { GetRawInputDeviceList(None, &mut num_devices, device_list_size) };
+        if result == u32::MAX {
+            return Err(Error::Enumeration(last_error()));
+        }
+
+        // make space for raw input device list
+        // RAWINPUTDEVICELIST is not actually a list, just an entry in the list...
+        let mut buffer = vec![RAWINPUTDEVICELIST::default(); num_devices as usize];
+
+        // get devices
+        // SAFETY: Required buffer size has been polled, if a device was connected since,
+        // the call fails rather than writing out of bounds
+        let result = unsafe {
+            GetRawInputDeviceList(
+                Some(buffer.as_mut_ptr()),
+                &mut num_devices,
+                device_list_size,
+            )
+        };
+        if result == u32::MAX {
+            return Err(Error::Enumeration(last_error()));
+        }
+        buffer.truncate(result as usize);
+
+        Ok(buffer
+            .iter()
+            .filter_map(|device| {
+                // 0 = mouse, 1 = keyboard, 2 = other HID
+                let kind = match device.dwType {
+                    RIM_TYPEMOUSE => DeviceKind::Mouse,
+                    RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+                    RIM_TYPEHID => DeviceKind::Hid,
+                    _ => return None,
+                };
+                // a device we cannot query is left out rather than failing the whole list
+                raw_device(device.hDevice, kind).ok()
+            })
+            .collect())
+    }
+
+    fn start_capture(&self, hid_usages: &[HidUsage]) -> Result<Self::Capture> {
+        // registering for notifications reports every connected device as arriving,
+        // these are the ones to skip
+        let known = self
+            .enumerate()?
+            .into_iter()
+            .map(|device| device.handle.0)
+            .collect();
+        RawInputCapture::new(known, hid_usages)
+    }
+
+    /// Raw input (`RIDI_PREPARSEDDATA`) and `HidD_GetPreparsedData` only hand out the
+    /// preparsed data Windows builds from the descriptor, in an undocumented format,
+    /// and the descriptor itself is only available to drivers.
+    fn report_descriptor(&self, _handle: DeviceHandle) -> Result<Vec<u8>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Rebuilds the descriptor from the preparsed data, see
+    /// [`ReportDescriptor::from_preparsed`].
+    fn parse_report_descriptor(&self, handle: DeviceHandle) -> Result<ReportDescriptor> {
+        let device = HANDLE(handle.0 as *mut c_void);
+        let mut size: u32 = 0;
+        // SAFETY: We are first polling the required buffer size
+        let result = unsafe { GetRawInputDeviceInfoW(device, RIDI_PREPARSEDDATA, None, &mut size) };
+        if result == u32::MAX {
+            return Err(Error::DeviceInfo(last_error()));
+        }
+        let mut data = vec![0u8; size as usize];
+        // SAFETY: Buffer has been allocated accordingly
+        let result = unsafe {
+            GetRawInputDeviceInfoW(
+                device,
+                RIDI_PREPARSEDDATA,
+                Some(data.as_mut_ptr() as *mut c_void),
+                &mut size,
+            )
+        };
+        if result == u32::MAX {
+            return Err(Error::DeviceInfo(last_error()));
+        }
+        data.truncate(result as usize);
+        ReportDescriptor::from_preparsed(&data).map_err(Error::Descriptor)
+    }
+}
+
+/// The current screen geometry, which changes when monitors are added or rearranged.
+fn desktop() -> Desktop {
+    let metric = |index| unsafe { GetSystemMetrics(index) };
+    Desktop {
+        left: metric(SM_XVIRTUALSCREEN),
+        top: metric(SM_YVIRTUALSCREEN),
+        width: metric(SM_CXVIRTUALSCREEN),
+        height: metric(SM_CYVIRTUALSCREEN),
+        primary_width: metric(SM_CXSCREEN),
+        primary_height: metric(SM_CYSCREEN),
+    }
+}
+
+fn last_error() -> i32 {
+    unsafe { GetLastError() }.0 as i32
+}
+
+/// Queries the path, HID attributes and strings, and raw input details of a device.
+fn raw_device(device: HANDLE, kind: DeviceKind) -> Result<RawDevice> {
+    // get size of device path string
+    let mut size: u32 = 0;
+    // SAFETY: We are first polling the required buffer size
+    let result = unsafe { GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, None, &mut size) };
+    if result == u32::MAX {
+        return Err(Error::DeviceInfo(last_error()));
+    }
+    // allocate buffer for path string
+    let mut path_buffer = vec![0u16; size as usize];
+    // get device path string
+    // SAFETY: Buffer has been allocated accordingly
+    let result = unsafe {
+        GetRawInputDeviceInfoW(
+            device,
+            RIDI_DEVICENAME,
+            Some(path_buffer.as_mut_ptr() as *mut c_void),
+            &mut size,
+        )
+    };
+    if result == u32::MAX {
+        return Err(Error::DeviceInfo(last_error()));
+    }
+
+    // cast the path string to a windows string
+    let pathstr = PWSTR::from_raw(path_buffer.as_mut_ptr());
+
+    // create a file handle on the raw input device
+    let handle = unsafe {
+        windows::Win32::Storage::FileSystem::CreateFileW(
+            pathstr,
+            0,
+            FILE_SHARE_READ,
+            None,
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_READONLY,
+            None,
+        )
+    }
+    .map_err(|_| Error::DeviceInfo(last_error()))?;
+
+    let strings = hid_strings(handle);
+    let mut attributes = HIDD_ATTRIBUTES {
+        Size: std::mem::size_of::<HIDD_ATTRIBUTES>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: Size is set as the call requires
+    let has_attributes = unsafe { HidD_GetAttributes(handle, &mut attributes) } != BOOLEAN(0);
+    unsafe {
+        let _ = CloseHandle(handle);
+    }
+    let (product_name, manufacturer, serial_number) = strings?;
+
+    let path_len = path_buffer
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(path_buffer.len());
+    let path = String::from_utf16(&path_buffer[..path_len]).map_err(|_| Error::InvalidUtf16)?;
+    // devices without HID attributes (e.g. PS/2) simply report 0 for the IDs
+    let (vendor_id, product_id, version) = if has_attributes {
+        (
+            attributes.VendorID,
+            attributes.ProductID,
+            attributes.VersionNumber,
+        )
+    } else {
+        (0, 0, 0)
+    };
+
+    Ok(RawDevice {
+        handle: DeviceHandle(device.0 as usize),
+        kind,
+        info: DeviceInfo {
+            product_name,
+            manufacturer,
+            serial_number,
+            vendor_id,
+            product_id,
+            version,
+            location: location_from_path(&path),
+            path,
+            details: device_details(device)?,
+        },
+    })
+}
+
+/// Extracts the instance segment from a path like
+/// `\\?\HID#VID_046D&PID_C08B&MI_00#7&1a2b3c4d&0&0000#{378de44c-...}`.
+/// PnP derives it from the parent's location, so it only changes when the device changes ports.
+fn location_from_path(path: &str) -> Option<String> {
+    path.split('#')
+        .nth(2)
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads the product, manufacturer and serial number strings of an open HID device.
+/// Only the product string is required, the others are `None` if the device has none.
+fn hid_strings(handle: HANDLE) -> Result<(String, Option<String>, Option<String>)> {
+    // string buffers must be allocated beforehand
+    const SIZE: usize = 1024;
+    type Getter = unsafe fn(HANDLE, *mut c_void, u32) -> BOOLEAN;
+    let read = |getter: Getter| -> Result<Option<String>> {
+        let mut buffer: [u16; SIZE] = [0u16; SIZE];
+        // SAFETY: Buffer size is handled on the OS side
+        // if the string does not fit, this will fail.
+        let result = unsafe { getter(handle, buffer.as_mut_ptr() as *mut c_void, SIZE as u32) };
+        if result == BOOLEAN(0) {
+            return Ok(None);
+        }
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(SIZE);
+        match String::from_utf16(&buffer[..len]) {
+            Ok(string) if string.is_empty() => Ok(None),
+            Ok(string) => Ok(Some(string)),
+            Err(_) => Err(Error::InvalidUtf16),
+        }
+    };
+    // get product string (typically a name)
+    let product_name =
+        read(HidD_GetProductString::<HANDLE>)?.ok_or_else(|| Error::DeviceInfo(last_error()))?;
+    let manufacturer = read(HidD_GetManufacturerString::<HANDLE>)?;
+    let serial_number = read(HidD_GetSerialNumberString::<HANDLE>)?;
+    Ok((product_name, manufacturer, serial_number))
+}
+
+/// Queries the `RID_DEVICE_INFO` of a device.
+fn device_details(device: HANDLE) -> Result<DeviceDetails> {
+    let mut info = RID_DEVICE_INFO {
+        cbSize: std::mem::size_of::<RID_DEVICE_INFO>() as u32,
+        ..Default::default()
+    };
+    let mut size = info.cbSize;
+    // SAFETY: The buffer is a RID_DEVICE_INFO with cbSize set, as the call requires
+    let result = unsafe {
+        GetRawInputDeviceInfoW(
+            device,
+            RIDI_DEVICEINFO,
+            Some(&mut info as *mut RID_DEVICE_INFO as *mut c_void),
+            &mut size,
+        )
+    };
+    if result == u32::MAX {
+        return Err(Error::DeviceInfo(last_error()));
+    }
+    // SAFETY: dwType tells which member of the union was written
+    Ok(unsafe {
+        match info.dwType {
+            RIM_TYPEMOUSE => {
+                let mouse = info.Anonymous.mouse;
+                DeviceDetails::Mouse(MouseDetails {
+                    id: mouse.dwId,
+                    buttons: mouse.dwNumberOfButtons,
+                    sample_rate: mouse.dwSampleRate,
+                    has_horizontal_wheel: mouse.fHasHorizontalWheel.as_bool(),
+                })
+            }
+            RIM_TYPEKEYBOARD => {
+                let keyboard = info.Anonymous.keyboard;
+                DeviceDetails::Keyboard(KeyboardDetails {
+                    keyboard_type: keyboard.dwType,
+                    subtype: keyboard.dwSubType,
+                    scancode_mode: keyboard.dwKeyboardMode,
+                    function_keys: keyboard.dwNumberOfFunctionKeys,
+                    indicators: keyboard.dwNumberOfIndicators,
+                    total_keys: keyboard.dwNumberOfKeysTotal,
+                })
+            }
+            RIM_TYPEHID => {
+                let hid = info.Anonymous.hid;
+                DeviceDetails::Hid(HidUsage::new(hid.usUsagePage, hid.usUsage))
+            }
+            _ => DeviceDetails::None,
+        }
+    })
+}
+
+/// A hidden message window registered for raw input, owned by the listener thread.
+pub struct RawInputCapture {
+    hwnd: HWND,
+    rawinputdevices: Vec<RAWINPUTDEVICE>,
+    buffer: Vec<RAWINPUT>,
+    start: Instant,
+    /// Handles of the devices reported so far, by enumeration or as added.
+    known: HashSet<usize>,
+    keyboards: HashMap<DeviceHandle, KeyboardDecoder>,
+}
+
+impl RawInputCapture {
+    fn new(known: HashSet<usize>, hid_usages: &[HidUsage]) -> Result<Self> {
+        let hwnd = create_window()?;
+
+        let mut usages = vec![HidUsage::MOUSE, HidUsage::KEYBOARD];
+        for usage in hid_usages {
+            if !usages.contains(usage) {
+                usages.push(*usage);
+            }
+        }
+        let rawinputdevices = usages
+            .iter()
+            .map(|usage| RAWINPUTDEVICE {
+                usUsagePage: usage.page,
+                usUsage: usage.id,
+                dwFlags: DW_FLAG,
+                hwndTarget: hwnd,
+            })
+            .collect();
+
+        // from here on, dropping the capture cleans up the window
+        let capture = RawInputCapture {
+            hwnd,
+            rawinputdevices,
+            buffer: vec![RAWINPUT::default(); 4096], // why not ...
+            start: Instant::now(),
+            known,
+            keyboards: HashMap::new(),
+        };
+
+        unsafe {
+            RegisterRawInputDevices(
+                &capture.rawinputdevices,
+                std::mem::size_of::<RAWINPUTDEVICE>() as u32,
+            )
+        }
+        .map_err(|_| Error::Registration(last_error()))?;
+
+        Ok(capture)
+    }
+}
+
+impl Capture for RawInputCapture {
+    fn poll_events(&mut self, timeout: Duration, events: &mut Vec<Event>) -> Result<()> {
+        // sleep until there is something in the queue
+        unsafe { MsgWaitForMultipleObjects(None, false, timeout.as_millis() as u32, QS_ALLINPUT) };
+
+        loop {
+            // docs say this is written to only if the buffer pointer is null,
+            // that's not true, it gets overwritten with each call.
+            let mut buffer_size = std::mem::size_of_val(self.buffer.as_slice()) as u32;
+            let n = unsafe {
+                GetRawInputBuffer(
+                    Some(self.buffer.as_mut_ptr()),
+                    &mut buffer_size,
+                    std::mem::size_of::<RAWINPUTHEADER>() as u32,
+                )
+            };
+            if n as i32 == -1 {
+                return Err(Error::BufferRead(last_error()));
+            } else if n == 0 {
+                break;
+            }
+            let timestamp = Timestamp(self.start.elapsed());
+            let desktop = desktop();
+            // the blocks are variable sized, so they have to be walked by their header size
+            // rather than indexed
+            let mut offset = 0;
+            for _ in 0..n {
+                // SAFETY: GetRawInputBuffer wrote n complete blocks into the buffer
+                unsafe {
+                    let raw = (self.buffer.as_ptr() as *const u8).add(offset) as *const RAWINPUT;
+                    decode_raw_input(raw, timestamp, &desktop, &mut self.keyboards, events);
+                    offset = next_raw_input_block(offset, (*raw).header.dwSize);
+                }
+            }
+        }
+
+        // whatever is left in the queue (WM_INPUT notifications for data already read
+        // and the usual window housekeeping) still has to be removed
+        let mut msg = MSG::default();
+        while unsafe { PeekMessageW(&mut msg, self.hwnd, 0, 0, PM_REMOVE) }.as_bool() {
+            unsafe { DispatchMessageW(&msg) };
+        }
+        self.device_changes(events);
+        Ok(())
+    }
+}
+
+impl RawInputCapture {
+    /// Turns the notifications collected by [`window_proc`] into events.
+    fn device_changes(&mut self, events: &mut Vec<Event>) {
+        let timestamp = Timestamp(self.start.elapsed());
+        for (change, device) in DEVICE_CHANGES.take() {
+            let handle = HANDLE(device as *mut c_void);
+            let kind = match change {
+                GIDC_ARRIVAL if !self.known.contains(&device) => {
+                    let kind = match device_details(handle) {
+                        Ok(DeviceDetails::Mouse(_)) => DeviceKind::Mouse,
+                        Ok(DeviceDetails::Keyboard(_)) => DeviceKind::Keyboard,
+                        Ok(DeviceDetails::Hid(_)) => DeviceKind::Hid,
+                        _ => continue,
+                    };
+                    // like in enumeration, a device we cannot query is left out
+                    let Ok(added) = raw_device(handle, kind) else {
+                        continue;
+                    };
+                    self.known.insert(device);
+                    InputEvent::DeviceAdded {
+                        kind,
+                        info: added.info,
+                    }
+                }
+                GIDC_REMOVAL if self.known.remove(&device) => {
+                    self.keyboards.remove(&DeviceHandle(device));
+                    InputEvent::DeviceRemoved
+                }
+                _ => continue,
+            };
+            events.push(Event {
+                device: DeviceHandle(device),
+                timestamp,
+                kind,
+            });
+        }
+    }
+}
+
+impl Drop for RawInputCapture {
+    fn drop(&mut self) {
+        // unregister, otherwise the system keeps routing input to the destroyed window
+        for device in self.rawinputdevices.iter_mut() {
+            device.dwFlags = RIDEV_REMOVE;
+            device.hwndTarget = HWND::default();
+        }
+        unsafe {
+            let _ = RegisterRawInputDevices(
+                &self.rawinputdevices,
+                std::mem::size_of::<RAWINPUTDEVICE>() as u32,
+            );
+            let _ = DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+/// The offset of the block following one of `size` bytes at `offset` in the buffer of
+/// `GetRawInputBuffer`, as NEXTRAWINPUTBLOCK in the C headers finds it: blocks are aligned to
+/// the size of a pointer, 8 bytes on 64-bit Windows and 4 on 32-bit.
+fn next_raw_input_block(offset: usize, size: u32) -> usize {
+    const ALIGN: usize = std::mem::size_of::<usize>();
+    (offset + size as usize + ALIGN - 1) & !(ALIGN - 1)
+}
+
+/// Decodes one `RAWINPUT` block into zero or more events.
+///
+/// The packets themselves are decoded by the platform-neutral [`crate::decode`].
+///
+/// # Safety
+/// `raw` must point to a complete block as written by `GetRawInputBuffer`,
+/// i.e. `header.dwSize` bytes must be readable.
+pub(crate) unsafe fn decode_raw_input(
+    raw: *const RAWINPUT,
+    timestamp: Timestamp,
+    desktop: &Desktop,
+    keyboards: &mut HashMap<DeviceHandle, KeyboardDecoder>,
+    events: &mut Vec<Event>,
+) {
+    let header = (*raw).header;
+    let device = DeviceHandle(header.hDevice.0 as usize);
+    let mut push = |kind| {
+        events.push(Event {
+            device,
+            timestamp,
+            kind,
+        })
+    };
+
+    match header.dwType {
+        t if t == RIM_TYPEMOUSE.0 => {
+            let mouse = (*raw).data.mouse;
+            let raw_mouse = RawMouse {
+                flags: mouse.usFlags.0,
+                button_flags: mouse.Anonymous.Anonymous.usButtonFlags,
+                button_data: mouse.Anonymous.Anonymous.usButtonData,
+                last_x: mouse.lLastX,
+                last_y: mouse.lLastY,
+            };
+            decode_mouse(&raw_mouse, desktop, &mut push);
+        }
+        t if t == RIM_TYPEKEYBOARD.0 => {
+            let keyboard = (*raw).data.keyboard;
+            let raw_keyboard = RawKeyboard {
+                make_code: keyboard.MakeCode,
+                flags: keyboard.Flags,
+            };
+            if let Some(event) = keyboards.entry(device).or_default().decode(&raw_keyboard) {
+                push(event);
+            }
+        }
+        t if t == RIM_TYPEHID.0 => {
+            let hid = std::ptr::addr_of!((*raw).data.hid);
+            let size = (*hid).dwSizeHid as usize;
+            let data = std::ptr::addr_of!((*hid).bRawData) as *const u8;
+            for i in 0..(*hid).dwCount as usize {
+                let report = std::slice::from_raw_parts(data.add(i * size), size);
+                // reports start with a report ID of 0 if the device doesn't use them,
+                // which is never a valid ID
+                let report = match report {
+                    [0, report @ ..] => report,
+                    report => report,
+                };
+                push(InputEvent::HidReport {
+                    report: report.to_vec(),
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Creates a hidden message-only window to receive raw input.
+fn create_window() -> Result<HWND> {
+    let hinstance = unsafe { GetModuleHandleW(PWSTR::null()) }
+        .map_err(|_| Error::WindowCreation(last_error()))?;
+
+    let classname_str = format!("RawInput Window");
+    let mut classname = OsStr::new(&classname_str)
+        .encode_wide()
+        .chain(Some(0).into_iter())
+        .collect::<Vec<_>>();
+    let classname = PCWSTR::from_raw(&mut classname[0]);
+
+    let wcex = WNDCLASSEXW {
+        cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+        cbClsExtra: 0,
+        cbWndExtra: 0,
+        hInstance: hinstance.into(),
+        lpfnWndProc: Some(window_proc),
+        lpszClassName: classname,
+        style: WNDCLASS_STYLES::default(),
+        ..Default::default()
+    };
+
+    let result = unsafe { RegisterClassExW(&wcex) };
+
+    // the class outlives the window, so it is already there if we listened before
+    if result == 0 && unsafe { GetLastError() } != ERROR_CLASS_ALREADY_EXISTS {
+        return Err(Error::WindowCreation(last_error()));
+    }
+
+    unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            classname,
+            classname,
+            WINDOW_STYLE::default(),
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            HWND_MESSAGE,
+            HMENU::default(),
+            hinstance,
+            None,
+        )
+    }
+    .map_err(|_| Error::WindowCreation(last_error()))
+}
+
+/// Queues device change notifications for [`RawInputCapture::device_changes`]
+/// and leaves everything else to the default window procedure.
+/// The notification may be sent rather than posted, so it is caught here instead of in the
+/// message loop.
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: Foundation::WPARAM,
+    lparam: Foundation::LPARAM,
+) -> Foundation::LRESULT {
+    if msg == WM_INPUT_DEVICE_CHANGE {
+        DEVICE_CHANGES
+            .with_borrow_mut(|changes| changes.push((wparam.0 as u32, lparam.0 as usize)));
+        return Foundation::LRESULT(0);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
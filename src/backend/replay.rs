@@ -0,0 +1,135 @@
+//! A backend that plays back sessions written by a [`Recorder`](crate::record::Recorder).
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Capture, InputBackend, RawDevice};
+use crate::{record::SessionReader, Event, HidUsage, Result};
+
+/// How fast a [`ReplayBackend`] re-emits its events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keeps the recorded gaps between events.
+    RealTime,
+    /// Divides the recorded gaps by the factor, so `Scaled(2.0)` plays twice as fast.
+    /// The factor has to be finite and greater than zero.
+    Scaled(f64),
+    /// Emits everything as soon as listening starts.
+    Instant,
+}
+
+/// Enumerates the recorded devices and replays the recorded events, with their original
+/// timestamps, every time listening starts.
+#[derive(Debug, Clone)]
+pub struct ReplayBackend {
+    devices: Vec<RawDevice>,
+    events: Arc<[Event]>,
+    speed: ReplaySpeed,
+}
+
+impl ReplayBackend {
+    pub fn open(path: impl AsRef<Path>, speed: ReplaySpeed) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?), speed)
+    }
+
+    /// Reads the whole session from `reader`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] for a [`ReplaySpeed::Scaled`] factor that
+    /// is not finite and greater than zero.
+    pub fn from_reader(reader: impl Read, speed: ReplaySpeed) -> io::Result<Self> {
+        if let ReplaySpeed::Scaled(factor) = speed {
+            if !(factor.is_finite() && factor > 0.0) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid replay speed factor {}", factor),
+                ));
+            }
+        }
+        let session = SessionReader::new(reader)?;
+        let devices = session.devices().to_vec();
+        let events = session.collect::<io::Result<Vec<_>>>()?;
+        Ok(Self {
+            devices,
+            events: events.into(),
+            speed,
+        })
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+}
+
+impl InputBackend for ReplayBackend {
+    type Capture = ReplayCapture;
+
+    fn enumerate(&self) -> Result<Vec<RawDevice>> {
+        Ok(self.devices.clone())
+    }
+
+    fn start_capture(&self, _hid_usages: &[HidUsage]) -> Result<Self::Capture> {
+        Ok(ReplayCapture {
+            events: self.events.clone(),
+            next: 0,
+            speed: self.speed,
+            start: Instant::now(),
+        })
+    }
+}
+
+pub struct ReplayCapture {
+    events: Arc<[Event]>,
+    next: usize,
+    speed: ReplaySpeed,
+    start: Instant,
+}
+
+impl ReplayCapture {
+    /// When the event at `index` is due, relative to the start of the replay.
+    fn due(&self, index: usize) -> Duration {
+        let first = self.events[0].timestamp.0;
+        let offset = self.events[index].timestamp.0.saturating_sub(first);
+        match self.speed {
+            ReplaySpeed::RealTime => offset,
+            // tiny factors push far events past what a Duration can hold
+            ReplaySpeed::Scaled(factor) => {
+                Duration::try_from_secs_f64(offset.as_secs_f64() / factor).unwrap_or(Duration::MAX)
+            }
+            ReplaySpeed::Instant => Duration::ZERO,
+        }
+    }
+}
+
+impl Capture for ReplayCapture {
+    fn poll_events(&mut self, timeout: Duration, events: &mut Vec<Event>) -> Result<()> {
+        if self.next == self.events.len() {
+            thread::sleep(timeout);
+            return Ok(());
+        }
+
+        // wait for the next event if it comes up within the timeout
+        let elapsed = self.start.elapsed();
+        let due = self.due(self.next);
+        if due > elapsed {
+            let wait = due - elapsed;
+            if wait > timeout {
+                thread::sleep(timeout);
+                return Ok(());
+            }
+            thread::sleep(wait);
+        }
+
+        let elapsed = self.start.elapsed();
+        while self.next < self.events.len() && self.due(self.next) <= elapsed {
+            events.push(self.events[self.next].clone());
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
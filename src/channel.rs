@@ -0,0 +1,282 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::Ordering,
+        mpsc::{RecvError, RecvTimeoutError, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    task::Waker,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    polling::{Shared, POLL_TIMEOUT},
+    DeviceId, InputEvent, Timestamp,
+};
+
+/// An event as delivered by [`Devices::start_listening`](crate::Devices::start_listening),
+/// with the id of the device it came from.
+pub type DeviceEvent = (DeviceId, Timestamp, InputEvent);
+
+/// What the listener does with an event when the channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Overflow {
+    /// Makes room by dropping the event that has waited longest.
+    #[default]
+    DropOldest,
+    /// Drops the new event.
+    DropNewest,
+    /// Waits for the consumer to make room, which holds up the capture in the meantime.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelConfig {
+    /// How many events can wait in the channel, at least one.
+    pub capacity: usize,
+    pub overflow: Overflow,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            overflow: Overflow::default(),
+        }
+    }
+}
+
+/// The receiving end of the channel returned by
+/// [`Devices::start_listening`](crate::Devices::start_listening).
+///
+/// It works like an [`mpsc::Receiver`](std::sync::mpsc::Receiver): receiving fails once the
+/// listener has stopped and every event sent before has been received.
+/// Events are dropped as soon as the receiver is.
+pub struct EventReceiver {
+    queue: Arc<Queue>,
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    /// Notified whenever an event is added or taken, or an end of the channel goes away.
+    changed: Condvar,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<DeviceEvent>,
+    sender_gone: bool,
+    receiver_gone: bool,
+    /// The task waiting for an event on an [`EventStream`](crate::EventStream).
+    waker: Option<Waker>,
+}
+
+/// The end of the channel owned by the listener thread.
+struct EventSender {
+    queue: Arc<Queue>,
+    overflow: Overflow,
+    shared: Arc<Shared>,
+}
+
+/// Creates a channel, returning the function the listener thread sends events with.
+pub(crate) fn channel(
+    config: ChannelConfig,
+    shared: Arc<Shared>,
+) -> (impl FnMut(DeviceEvent) + Send + 'static, EventReceiver) {
+    let queue = Arc::new(Queue {
+        state: Default::default(),
+        changed: Condvar::new(),
+        capacity: config.capacity.max(1),
+    });
+    let mut sender = EventSender {
+        queue: queue.clone(),
+        overflow: config.overflow,
+        shared,
+    };
+    (move |event| sender.send(event), EventReceiver { queue })
+}
+
+impl EventSender {
+    fn send(&mut self, event: DeviceEvent) {
+        let queue = &*self.queue;
+        let full = |state: &QueueState| state.events.len() >= queue.capacity;
+        let mut state = queue.state.lock().unwrap();
+        if full(&state) && !state.receiver_gone {
+            match self.overflow {
+                Overflow::DropOldest => {
+                    state.events.pop_front();
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Overflow::DropNewest => {
+                    self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Overflow::Block => {
+                    while full(&state) && !state.receiver_gone {
+                        // the consumer may never make room, so stopping must not wait for it
+                        if self.shared.stop.load(Ordering::Relaxed) {
+                            self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                            return;
+                        }
+                        state = queue.changed.wait_timeout(state, POLL_TIMEOUT).unwrap().0;
+                    }
+                }
+            }
+        }
+        if state.receiver_gone {
+            return;
+        }
+        state.events.push_back(event);
+        queue.changed.notify_all();
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.sender_gone = true;
+        let waker = state.waker.take();
+        drop(state);
+        self.queue.changed.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl EventReceiver {
+    /// Waits for the next event.
+    pub fn recv(&self) -> Result<DeviceEvent, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError)
+    }
+
+    pub fn try_recv(&self) -> Result<DeviceEvent, TryRecvError> {
+        let mut state = self.queue.state.lock().unwrap();
+        match self.take(&mut state) {
+            Some(event) => Ok(event),
+            None if state.sender_gone => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<DeviceEvent, RecvTimeoutError> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    /// Iterates over events as they arrive, until the listener has stopped.
+    pub fn iter(&self) -> impl Iterator<Item = DeviceEvent> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    /// Iterates over the events waiting in the channel.
+    pub fn try_iter(&self) -> impl Iterator<Item = DeviceEvent> + '_ {
+        std::iter::from_fn(|| self.try_recv().ok())
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Result<DeviceEvent, RecvTimeoutError> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if let Some(event) = self.take(&mut state) {
+                return Ok(event);
+            }
+            if state.sender_gone {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            state = match deadline {
+                None => self.queue.changed.wait(state).unwrap(),
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    self.queue.changed.wait_timeout(state, timeout).unwrap().0
+                }
+            };
+        }
+    }
+
+    /// Takes the next event, or registers the task to be woken when there is one.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_recv(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<DeviceEvent>> {
+        use std::task::Poll;
+
+        let mut state = self.queue.state.lock().unwrap();
+        if let Some(event) = self.take(&mut state) {
+            return Poll::Ready(Some(event));
+        }
+        if state.sender_gone {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn take(&self, state: &mut QueueState) -> Option<DeviceEvent> {
+        let event = state.events.pop_front()?;
+        self.queue.changed.notify_all();
+        Some(event)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.receiver_gone = true;
+        state.events.clear();
+        drop(state);
+        self.queue.changed.notify_all();
+    }
+}
+
+/// Creates a crossbeam channel, returning the function the listener thread sends events with.
+#[cfg(feature = "crossbeam")]
+pub(crate) fn crossbeam_channel(
+    config: ChannelConfig,
+    shared: Arc<Shared>,
+) -> (
+    impl FnMut(DeviceEvent) + Send + 'static,
+    crossbeam_channel::Receiver<DeviceEvent>,
+) {
+    use crossbeam_channel::{SendTimeoutError, TrySendError};
+
+    let (sender, receiver) = crossbeam_channel::bounded(config.capacity.max(1));
+    // a receiver of its own to drop the oldest event with
+    let oldest = (config.overflow == Overflow::DropOldest).then(|| receiver.clone());
+    let send = move |mut event| match config.overflow {
+        Overflow::DropOldest => {
+            while let Err(TrySendError::Full(full)) = sender.try_send(event) {
+                if oldest
+                    .as_ref()
+                    .is_some_and(|oldest| oldest.try_recv().is_ok())
+                {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                event = full;
+            }
+        }
+        Overflow::DropNewest => {
+            if let Err(TrySendError::Full(_)) = sender.try_send(event) {
+                shared.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Overflow::Block => {
+            while let Err(SendTimeoutError::Timeout(full)) =
+                sender.send_timeout(event, POLL_TIMEOUT)
+            {
+                if shared.stop.load(Ordering::Relaxed) {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                event = full;
+            }
+        }
+    };
+    (send, receiver)
+}
@@ -0,0 +1,203 @@
+//! Decoding of raw input packets into [`InputEvent`]s.
+//!
+//! The packets are mirrored in plain structs with the constants from `winuser.h`, so the
+//! decoding does not depend on any OS types and can be tested on every platform.
+
+use crate::{ButtonState, InputEvent, MouseButton, WheelAxis};
+
+pub const MOUSE_MOVE_ABSOLUTE: u16 = 0x01;
+pub const MOUSE_VIRTUAL_DESKTOP: u16 = 0x02;
+
+pub const RI_MOUSE_LEFT_BUTTON_DOWN: u16 = 0x0001;
+pub const RI_MOUSE_LEFT_BUTTON_UP: u16 = 0x0002;
+pub const RI_MOUSE_RIGHT_BUTTON_DOWN: u16 = 0x0004;
+pub const RI_MOUSE_RIGHT_BUTTON_UP: u16 = 0x0008;
+pub const RI_MOUSE_MIDDLE_BUTTON_DOWN: u16 = 0x0010;
+pub const RI_MOUSE_MIDDLE_BUTTON_UP: u16 = 0x0020;
+pub const RI_MOUSE_BUTTON_4_DOWN: u16 = 0x0040;
+pub const RI_MOUSE_BUTTON_4_UP: u16 = 0x0080;
+pub const RI_MOUSE_BUTTON_5_DOWN: u16 = 0x0100;
+pub const RI_MOUSE_BUTTON_5_UP: u16 = 0x0200;
+pub const RI_MOUSE_WHEEL: u16 = 0x0400;
+pub const RI_MOUSE_HWHEEL: u16 = 0x0800;
+
+pub const RI_KEY_BREAK: u16 = 0x01;
+pub const RI_KEY_E0: u16 = 0x02;
+pub const RI_KEY_E1: u16 = 0x04;
+
+/// Sent by a keyboard whose buffer overflowed, not a key.
+const KEYBOARD_OVERRUN_MAKE_CODE: u16 = 0xFF;
+
+/// The fields of `RAWMOUSE` that carry input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawMouse {
+    /// `usFlags`, a combination of `MOUSE_*`.
+    pub flags: u16,
+    /// `usButtonFlags`, a combination of `RI_MOUSE_*`.
+    pub button_flags: u16,
+    /// `usButtonData`, the signed wheel delta if a wheel flag is set.
+    pub button_data: u16,
+    pub last_x: i32,
+    pub last_y: i32,
+}
+
+/// The fields of `RAWKEYBOARD` that carry input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawKeyboard {
+    /// `MakeCode`, the scancode without its prefix.
+    pub make_code: u16,
+    /// `Flags`, a combination of `RI_KEY_*`.
+    pub flags: u16,
+}
+
+/// Screen geometry in pixels, needed to place absolute coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Desktop {
+    /// The bounding box of all monitors, whose origin is the top left corner of the primary one.
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+    pub primary_width: i32,
+    pub primary_height: i32,
+}
+
+/// Decodes one mouse packet, which can carry motion, button transitions and wheel data at once,
+/// so every part of it is reported as its own event.
+///
+/// Absolute coordinates range from 0 to 65535 across the primary monitor, or across the whole
+/// desktop with `MOUSE_VIRTUAL_DESKTOP`, and are reported as [`InputEvent::MousePosition`] in
+/// desktop pixels.
+pub fn decode_mouse(mouse: &RawMouse, desktop: &Desktop, mut push: impl FnMut(InputEvent)) {
+    if mouse.flags & MOUSE_MOVE_ABSOLUTE != 0 {
+        let (left, top, width, height) = if mouse.flags & MOUSE_VIRTUAL_DESKTOP != 0 {
+            (desktop.left, desktop.top, desktop.width, desktop.height)
+        } else {
+            (0, 0, desktop.primary_width, desktop.primary_height)
+        };
+        let scale = |value: i32, origin: i32, size: i32| {
+            origin + (value as i64 * size as i64 / 65535) as i32
+        };
+        push(InputEvent::MousePosition {
+            x: scale(mouse.last_x, left, width),
+            y: scale(mouse.last_y, top, height),
+        });
+    } else if mouse.last_x != 0 || mouse.last_y != 0 {
+        push(InputEvent::MouseMotion {
+            dx: mouse.last_x,
+            dy: mouse.last_y,
+        });
+    }
+
+    let flags = mouse.button_flags;
+    let buttons = [
+        (
+            MouseButton::Left,
+            RI_MOUSE_LEFT_BUTTON_DOWN,
+            RI_MOUSE_LEFT_BUTTON_UP,
+        ),
+        (
+            MouseButton::Right,
+            RI_MOUSE_RIGHT_BUTTON_DOWN,
+            RI_MOUSE_RIGHT_BUTTON_UP,
+        ),
+        (
+            MouseButton::Middle,
+            RI_MOUSE_MIDDLE_BUTTON_DOWN,
+            RI_MOUSE_MIDDLE_BUTTON_UP,
+        ),
+        (
+            MouseButton::Back,
+            RI_MOUSE_BUTTON_4_DOWN,
+            RI_MOUSE_BUTTON_4_UP,
+        ),
+        (
+            MouseButton::Forward,
+            RI_MOUSE_BUTTON_5_DOWN,
+            RI_MOUSE_BUTTON_5_UP,
+        ),
+    ];
+    for (button, down, up) in buttons {
+        if flags & down != 0 {
+            push(InputEvent::MouseButton {
+                button,
+                state: ButtonState::Pressed,
+            });
+        }
+        if flags & up != 0 {
+            push(InputEvent::MouseButton {
+                button,
+                state: ButtonState::Released,
+            });
+        }
+    }
+
+    // both wheels share usButtonData, only one of them is set per packet
+    let delta = mouse.button_data as i16;
+    if flags & RI_MOUSE_WHEEL != 0 {
+        push(InputEvent::MouseWheel {
+            axis: WheelAxis::Vertical,
+            delta,
+        });
+    } else if flags & RI_MOUSE_HWHEEL != 0 {
+        push(InputEvent::MouseWheel {
+            axis: WheelAxis::Horizontal,
+            delta,
+        });
+    }
+}
+
+/// Turns the `RAWKEYBOARD` packets of one keyboard into [`InputEvent::Key`]s carrying the full
+/// set 1 scancode: an `E0` prefix ends up in the high byte (`0xE01D` for right Ctrl), and Pause,
+/// which arrives as `E1 1D` followed by `45`, becomes `0xE11D`. Keyboards send the release of
+/// Pause right after its press rather than when the key comes up, but both are reported.
+///
+/// Packets that are not key presses of their own are dropped: the `45` completing Pause,
+/// the fake Shift presses (`E0 2A`, `E0 36`) keyboards wrap around extended keys to undo a held
+/// Shift or Num Lock, and overrun codes. Num Lock is `0x45` even if it comes with `E0`,
+/// as it does on some versions of Windows.
+///
+/// The decoder keeps state between packets, so each keyboard needs its own.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardDecoder {
+    /// The last packet started Pause.
+    in_pause: bool,
+}
+
+impl KeyboardDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn decode(&mut self, keyboard: &RawKeyboard) -> Option<InputEvent> {
+        let state = if keyboard.flags & RI_KEY_BREAK != 0 {
+            ButtonState::Released
+        } else {
+            ButtonState::Pressed
+        };
+        let code = keyboard.make_code;
+        let in_pause = std::mem::take(&mut self.in_pause);
+
+        let scancode = if keyboard.flags & RI_KEY_E1 != 0 {
+            // E1 only ever prefixes Pause
+            if code != 0x1D {
+                return None;
+            }
+            self.in_pause = true;
+            0xE11D
+        } else if keyboard.flags & RI_KEY_E0 != 0 {
+            match code {
+                0x2A | 0x36 => return None,
+                0x45 => 0x45,
+                _ => 0xE000 | code,
+            }
+        } else {
+            match code {
+                0x45 if in_pause => return None,
+                0 | KEYBOARD_OVERRUN_MAKE_CODE => return None,
+                _ => code,
+            }
+        };
+        Some(InputEvent::Key { scancode, state })
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+use crate::hid::DescriptorError;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong while enumerating devices or listening to them.
+///
+/// Where the OS reported a failure, the variant carries its error code
+/// (`GetLastError` on Windows, `errno` elsewhere).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The list of connected devices could not be retrieved.
+    Enumeration(i32),
+    /// A device's name or other information could not be queried.
+    DeviceInfo(i32),
+    /// The hidden window receiving input could not be created.
+    WindowCreation(i32),
+    /// Registering for input from the devices failed.
+    Registration(i32),
+    /// Reading buffered input failed.
+    BufferRead(i32),
+    /// The listener thread could not be started.
+    ThreadSpawn(i32),
+    /// The devices are already being listened to, see
+    /// [`Devices::start_listening`](crate::Devices::start_listening).
+    AlreadyListening,
+    /// A string reported by the OS was not valid UTF-16.
+    InvalidUtf16,
+    /// The backend can't do this, e.g. read report descriptors.
+    Unsupported,
+    /// A device's report descriptor could not be parsed.
+    Descriptor(DescriptorError),
+}
+
+impl Error {
+    /// The OS error code, if there is one.
+    pub fn code(&self) -> Option<i32> {
+        match *self {
+            Error::Enumeration(code)
+            | Error::DeviceInfo(code)
+            | Error::WindowCreation(code)
+            | Error::Registration(code)
+            | Error::BufferRead(code)
+            | Error::ThreadSpawn(code) => Some(code),
+            Error::InvalidUtf16
+            | Error::Unsupported
+            | Error::AlreadyListening
+            | Error::Descriptor(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Error::Descriptor(err) = self {
+            return write!(f, "invalid report descriptor: {}", err);
+        }
+        let what = match self {
+            Error::Enumeration(_) => "failed to enumerate devices",
+            Error::DeviceInfo(_) => "failed to query device information",
+            Error::WindowCreation(_) => "failed to create the input window",
+            Error::Registration(_) => "failed to register for input",
+            Error::BufferRead(_) => "failed to read the input buffer",
+            Error::ThreadSpawn(_) => "failed to start the listener thread",
+            Error::InvalidUtf16 => "invalid UTF-16 in a device string",
+            Error::Unsupported => "not supported by the backend",
+            Error::AlreadyListening => "already listening to these devices",
+            Error::Descriptor(_) => unreachable!(),
+        };
+        match self.code() {
+            Some(code) => write!(f, "{} (os error {})", what, code),
+            None => f.write_str(what),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use crate::{DeviceHandle, DeviceInfo, DeviceKind};
+
+/// Time elapsed since a backend-specific origin, usually the start of the capture.
+/// Only differences between timestamps from the same capture are meaningful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Timestamp(pub Duration);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// The first side button (X1, button 4).
+    Back,
+    /// The second side button (X2, button 5).
+    Forward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WheelAxis {
+    /// Positive away from the user.
+    Vertical,
+    /// Positive to the right.
+    Horizontal,
+}
+
+/// A single decoded input event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    /// Relative motion, in device units.
+    MouseMotion { dx: i32, dy: i32 },
+    MouseButton {
+        button: MouseButton,
+        state: ButtonState,
+    },
+    /// Absolute position in desktop pixels, relative to the top left corner of the primary
+    /// monitor. Reported by tablets, touch screens and remote desktop sessions.
+    MousePosition { x: i32, y: i32 },
+    /// Wheel rotation, 120 per notch.
+    /// High-resolution wheels report fractions of a notch, i.e. smaller values.
+    MouseWheel { axis: WheelAxis, delta: i16 },
+    /// A key press or release. `scancode` is the PC set 1 scancode with its `E0` prefix in the
+    /// high byte (`0xE01D` for right Ctrl). Pause is `0xE11D`.
+    /// [`KeyCode::from_scancode`](crate::KeyCode::from_scancode) tells which key it is.
+    Key { scancode: u16, state: ButtonState },
+    /// One raw report from a generic HID device, starting with the report ID if the device
+    /// uses them.
+    HidReport { report: Vec<u8> },
+    /// A device was connected while listening.
+    /// Devices connected before the capture started are not reported.
+    DeviceAdded { kind: DeviceKind, info: DeviceInfo },
+    /// The device was disconnected. Its handle may be reused for a device connected later.
+    DeviceRemoved,
+}
+
+/// An [`InputEvent`] together with the device it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub device: DeviceHandle,
+    pub timestamp: Timestamp,
+    pub kind: InputEvent,
+}
@@ -0,0 +1,164 @@
+use crate::HidUsage;
+
+// axes of the generic desktop page
+const X: HidUsage = HidUsage::new(0x01, 0x30);
+const Y: HidUsage = HidUsage::new(0x01, 0x31);
+const Z: HidUsage = HidUsage::new(0x01, 0x32);
+const RX: HidUsage = HidUsage::new(0x01, 0x33);
+const RY: HidUsage = HidUsage::new(0x01, 0x34);
+const RZ: HidUsage = HidUsage::new(0x01, 0x35);
+
+/// Which family a gamepad's face buttons are labeled like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadFamily {
+    /// A, B, X and Y, with A at the bottom.
+    Xbox,
+    /// Cross, Circle, Square and Triangle.
+    PlayStation,
+    /// A, B, X and Y, with B at the bottom.
+    Nintendo,
+    Generic,
+}
+
+/// How a gamepad model reports its sticks and triggers, and what its buttons are called.
+///
+/// [`lookup`](Self::lookup) finds the layout of a model by its vendor and product ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GamepadLayout {
+    pub name: &'static str,
+    pub family: GamepadFamily,
+    /// The usages of the horizontal and vertical axis.
+    pub left_stick: [HidUsage; 2],
+    pub right_stick: [HidUsage; 2],
+    /// The usages of the left and right trigger, `None` if they are buttons or share an axis.
+    pub triggers: Option<[HidUsage; 2]>,
+    /// The names of the buttons by number, starting with button 1.
+    pub buttons: &'static [&'static str],
+}
+
+/// The layout most HID gamepads without a driver of their own follow.
+pub(crate) const GENERIC: GamepadLayout = GamepadLayout {
+    name: "Generic Gamepad",
+    family: GamepadFamily::Generic,
+    left_stick: [X, Y],
+    right_stick: [Z, RZ],
+    triggers: Some([RX, RY]),
+    buttons: &[],
+};
+
+/// Xbox controllers through the HID driver of Windows, which reports both triggers as Z.
+const XBOX: GamepadLayout = GamepadLayout {
+    name: "Xbox Controller",
+    family: GamepadFamily::Xbox,
+    left_stick: [X, Y],
+    right_stick: [RX, RY],
+    triggers: None,
+    buttons: &["A", "B", "X", "Y", "LB", "RB", "Back", "Start", "LS", "RS"],
+};
+
+const DUALSHOCK_4: GamepadLayout = GamepadLayout {
+    name: "DualShock 4",
+    family: GamepadFamily::PlayStation,
+    left_stick: [X, Y],
+    right_stick: [Z, RZ],
+    triggers: Some([RX, RY]),
+    buttons: &[
+        "Square", "Cross", "Circle", "Triangle", "L1", "R1", "L2", "R2", "Share", "Options", "L3",
+        "R3", "PS", "Touchpad",
+    ],
+};
+
+const DUALSENSE: GamepadLayout = GamepadLayout {
+    name: "DualSense",
+    buttons: &[
+        "Square", "Cross", "Circle", "Triangle", "L1", "R1", "L2", "R2", "Create", "Options", "L3",
+        "R3", "PS", "Touchpad", "Mute",
+    ],
+    ..DUALSHOCK_4
+};
+
+const SWITCH_PRO: GamepadLayout = GamepadLayout {
+    name: "Switch Pro Controller",
+    family: GamepadFamily::Nintendo,
+    left_stick: [X, Y],
+    right_stick: [RX, RY],
+    triggers: None,
+    buttons: &[
+        "B", "A", "Y", "X", "L", "R", "ZL", "ZR", "Minus", "Plus", "L3", "R3", "Home", "Capture",
+    ],
+};
+
+const MICROSOFT: u16 = 0x045E;
+const SONY: u16 = 0x054C;
+const NINTENDO: u16 = 0x057E;
+
+/// Known models by vendor and product ID.
+static LAYOUTS: [(u16, u16, GamepadLayout); 9] = [
+    (
+        MICROSOFT,
+        0x028E,
+        GamepadLayout {
+            name: "Xbox 360 Controller",
+            ..XBOX
+        },
+    ),
+    (
+        MICROSOFT,
+        0x02D1,
+        GamepadLayout {
+            name: "Xbox One Controller",
+            ..XBOX
+        },
+    ),
+    (
+        MICROSOFT,
+        0x02EA,
+        GamepadLayout {
+            name: "Xbox One S Controller",
+            ..XBOX
+        },
+    ),
+    (
+        MICROSOFT,
+        0x0B12,
+        GamepadLayout {
+            name: "Xbox Series X|S Controller",
+            ..XBOX
+        },
+    ),
+    (SONY, 0x05C4, DUALSHOCK_4),
+    (SONY, 0x09CC, DUALSHOCK_4),
+    (SONY, 0x0CE6, DUALSENSE),
+    (NINTENDO, 0x2009, SWITCH_PRO),
+    (
+        0x0F0D,
+        0x00C1,
+        GamepadLayout {
+            name: "HORIPAD for Nintendo Switch",
+            ..SWITCH_PRO
+        },
+    ),
+];
+
+impl GamepadLayout {
+    /// The layout of a known model, or else of the vendor's usual controllers,
+    /// or else the generic one.
+    pub fn lookup(vendor_id: u16, product_id: u16) -> &'static GamepadLayout {
+        let model = LAYOUTS
+            .iter()
+            .find(|(vendor, product, _)| (*vendor, *product) == (vendor_id, product_id))
+            .map(|(_, _, layout)| layout);
+        model.unwrap_or(match vendor_id {
+            MICROSOFT => &XBOX,
+            SONY => &DUALSHOCK_4,
+            NINTENDO => &SWITCH_PRO,
+            _ => &GENERIC,
+        })
+    }
+
+    /// The name of a button, numbered from 1 as reported by the device.
+    pub fn button_name(&self, button: u16) -> Option<&'static str> {
+        let index = button.checked_sub(1)?;
+        self.buttons.get(index as usize).copied()
+    }
+}
@@ -0,0 +1,945 @@
+//! Parsing of HID report descriptors, which lay out the reports of a device.
+//!
+//! [`ReportDescriptor::parse`] turns the descriptor returned by
+//! [`Devices::report_descriptor`](crate::Devices::report_descriptor), or read from anywhere
+//! else, into the tree of collections and report fields it declares. The parser only looks
+//! at the bytes, so it works the same on every platform, and malformed descriptors are
+//! rejected with a [`DescriptorError`] rather than trusted.
+//!
+//! [`ReportDescriptor::decode_input`] then reads the [`InputEvent::HidReport`]s of the device
+//! into the values of their fields, so that any device can be read without code of its own.
+//!
+//! Windows doesn't hand out descriptors, only the "preparsed data" it builds from them, which
+//! [`ReportDescriptor::from_preparsed`] turns back into the same fields.
+//!
+//! [`InputEvent::HidReport`]: crate::InputEvent::HidReport
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::{backend::InputBackend, DeviceHandle, Event, HidUsage, InputEvent};
+
+/// How deeply collections can be nested; real devices use a handful of levels.
+const MAX_DEPTH: usize = 32;
+
+/// The longest report accepted, in bits. Linux does not take longer ones either.
+const MAX_REPORT_BITS: u32 = 16384 * 8;
+
+/// The prefix of a long item, which is followed by its data size and tag.
+const LONG_ITEM: u8 = 0xFE;
+
+/// What preparsed data starts with.
+const PREPARSED_MAGIC: &[u8] = b"HidP KDR";
+
+/// The length of the preparsed data header, which the caps follow.
+const PREPARSED_HEADER_LEN: usize = 44;
+
+/// The length of a cap, which describes a field or, for fields with several usages, a part
+/// of one.
+const CAP_LEN: usize = 104;
+
+/// The length of a link collection node, which describes a collection.
+const LINK_NODE_LEN: usize = 16;
+
+/// Bits of a cap's flags.
+const CAP_BUTTON: u8 = 0x04;
+const CAP_RANGE: u8 = 0x10;
+const CAP_ALIAS: u8 = 0x20;
+
+/// A parsed report descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportDescriptor {
+    items: Vec<Item>,
+    /// The length of every report in bits, without the report ID.
+    reports: BTreeMap<(ReportKind, Option<u8>), u32>,
+}
+
+/// A node of a [`ReportDescriptor`]'s tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Collection(Collection),
+    Field(Field),
+}
+
+/// Items grouped by a Collection item and its End Collection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collection {
+    pub kind: CollectionKind,
+    /// The first usage declared before the collection, e.g. [`HidUsage::GAMEPAD`] for the
+    /// application collection of a gamepad.
+    pub usage: Option<HidUsage>,
+    pub items: Vec<Item>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CollectionKind {
+    Physical,
+    Application,
+    Logical,
+    Report,
+    NamedArray,
+    UsageSwitch,
+    UsageModifier,
+    /// A reserved or vendor-defined kind.
+    Other(u8),
+}
+
+/// Which reports a field is part of: those sent by the device, those sent to it, or the
+/// feature reports read and written on request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReportKind {
+    Input,
+    Output,
+    Feature,
+}
+
+/// Consecutive usage IDs on one page, declared by a Usage item or a Usage Minimum and
+/// Usage Maximum pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UsageRange {
+    pub page: u16,
+    pub min: u16,
+    pub max: u16,
+}
+
+/// One Input, Output or Feature item: `report_count` elements of `report_size` bits each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub kind: ReportKind,
+    /// `None` unless a Report ID item came before.
+    pub report_id: Option<u8>,
+    /// Where the field starts, in bits from the start of the report after the report ID.
+    pub bit_offset: u32,
+    /// The size of one element in bits.
+    pub report_size: u32,
+    pub report_count: u32,
+    /// The data of the item, see [`is_constant`](Self::is_constant) and friends.
+    pub flags: u32,
+    /// In a variable field, the usage of each element in turn. In an array field, the usages
+    /// an element can select, starting at the logical minimum.
+    pub usages: Vec<UsageRange>,
+    pub logical_min: i32,
+    pub logical_max: i32,
+    /// The logical range, if the descriptor does not declare a physical one.
+    pub physical_min: i32,
+    pub physical_max: i32,
+    /// The unit as encoded in the descriptor: the system in the lowest nibble, followed by
+    /// the exponent of length, mass, time, temperature, current and luminous intensity.
+    pub unit: u32,
+    /// The power of ten physical values are scaled with.
+    pub unit_exponent: i8,
+}
+
+/// The value of one element of a field, see [`ReportDescriptor::decode_input`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldValue {
+    pub usage: HidUsage,
+    /// The logical value, sign extended if the logical minimum is negative.
+    /// Always 1 for a usage selected by an array field.
+    pub value: i32,
+    /// The range of [`value`](Self::value), e.g. to normalize it. 0 to 1 for a usage selected
+    /// by an array field.
+    pub logical_min: i32,
+    pub logical_max: i32,
+    /// The value mapped onto the physical range and scaled by the unit exponent,
+    /// i.e. in the field's [`unit`](Self::unit). `None` for array fields and for values
+    /// in the null state.
+    pub physical: Option<f64>,
+    pub unit: u32,
+}
+
+/// Why [`ReportDescriptor::parse`] rejected a descriptor, or
+/// [`ReportDescriptor::from_preparsed`] preparsed data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescriptorError {
+    /// Where the offending item, cap or collection starts, or the length of the data if it
+    /// ended early.
+    pub offset: usize,
+    pub kind: DescriptorErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DescriptorErrorKind {
+    /// An item runs past the end of the descriptor.
+    Truncated,
+    /// An End Collection without a Collection.
+    UnmatchedEndCollection,
+    /// A Collection without an End Collection.
+    UnclosedCollection,
+    /// The collections are nested too deeply.
+    TooDeep,
+    /// A Pop without a Push.
+    UnmatchedPop,
+    /// A Report ID of 0 or above 255.
+    InvalidReportId,
+    /// A report is longer than any device could send.
+    ReportTooLong,
+    /// Data without the preparsed data header, or with caps or collections that point
+    /// nowhere.
+    NotPreparsedData,
+}
+
+impl ReportDescriptor {
+    pub fn parse(descriptor: &[u8]) -> Result<Self, DescriptorError> {
+        let mut parser = Parser::default();
+        let mut offset = 0;
+        while offset < descriptor.len() {
+            let start = offset;
+            let error = |kind| DescriptorError {
+                offset: start,
+                kind,
+            };
+            let prefix = descriptor[offset];
+            if prefix == LONG_ITEM {
+                // no long items are defined, so they are only skipped
+                let size = *descriptor
+                    .get(offset + 1)
+                    .ok_or(error(DescriptorErrorKind::Truncated))?;
+                offset += 3 + size as usize;
+                if offset > descriptor.len() {
+                    return Err(error(DescriptorErrorKind::Truncated));
+                }
+                continue;
+            }
+            let size = [0, 1, 2, 4][(prefix & 0x03) as usize];
+            let data = descriptor
+                .get(offset + 1..offset + 1 + size)
+                .ok_or(error(DescriptorErrorKind::Truncated))?;
+            offset += 1 + size;
+            let value = Value {
+                data: data
+                    .iter()
+                    .rev()
+                    .fold(0, |value, &byte| value << 8 | byte as u32),
+                size,
+            };
+            let tag = prefix >> 4;
+            match (prefix >> 2) & 0x03 {
+                0 => parser.main(tag, value),
+                1 => parser.global(tag, value),
+                2 => {
+                    parser.local(tag, value);
+                    Ok(())
+                }
+                // reserved
+                _ => Ok(()),
+            }
+            .map_err(error)?;
+        }
+        if !parser.open.is_empty() {
+            return Err(DescriptorError {
+                offset: descriptor.len(),
+                kind: DescriptorErrorKind::UnclosedCollection,
+            });
+        }
+        Ok(ReportDescriptor {
+            items: parser.items,
+            reports: parser.reports,
+        })
+    }
+
+    /// Rebuilds a descriptor from the preparsed data Windows makes of it, as returned by
+    /// `GetRawInputDeviceInfoW` with `RIDI_PREPARSEDDATA`.
+    ///
+    /// The format is undocumented; this reads the layout worked out by hidapi. The fields
+    /// are the same as in the descriptor, except that they come in the order of their bits,
+    /// ahead of nested collections, a variable field with separate usages is split into one
+    /// field per usage, padding is left out and usages aliased to others are dropped.
+    pub fn from_preparsed(data: &[u8]) -> Result<Self, DescriptorError> {
+        let data = Preparsed(data);
+        if !data.0.starts_with(PREPARSED_MAGIC) {
+            return Err(DescriptorError {
+                offset: 0,
+                kind: DescriptorErrorKind::NotPreparsedData,
+            });
+        }
+
+        let mut caps = vec![];
+        let kinds = [ReportKind::Input, ReportKind::Output, ReportKind::Feature];
+        for (index, kind) in kinds.into_iter().enumerate() {
+            // the first cap and the one after the last of each kind
+            let info = 16 + 8 * index;
+            for cap in data.u16(info)?..data.u16(info + 4)? {
+                let offset = PREPARSED_HEADER_LEN + cap as usize * CAP_LEN;
+                caps.extend(data.cap(kind, offset)?);
+            }
+        }
+        // the caps of an array field with several usages share its bits, and its
+        // elements select them in the order of their data indices
+        caps.sort_by_key(|cap| {
+            let field = &cap.field;
+            (
+                field.kind,
+                field.report_id,
+                field.bit_offset,
+                cap.data_index,
+            )
+        });
+
+        let nodes = data.u16(42)? as usize;
+        let mut fields = vec![vec![]; nodes];
+        let mut reports = BTreeMap::new();
+        for cap in caps {
+            let field = cap.field;
+            let length: &mut u32 = reports.entry((field.kind, field.report_id)).or_default();
+            *length = (*length).max(field.bit_offset + field.bit_len());
+            let collection: &mut Vec<Field> =
+                fields.get_mut(cap.link_collection).ok_or(DescriptorError {
+                    offset: cap.offset,
+                    kind: DescriptorErrorKind::NotPreparsedData,
+                })?;
+            match collection.last_mut() {
+                Some(array)
+                    if !array.is_variable()
+                        && !field.is_variable()
+                        && (array.kind, array.report_id, array.bit_offset)
+                            == (field.kind, field.report_id, field.bit_offset) =>
+                {
+                    array.usages.extend(field.usages);
+                    array.logical_min = array.logical_min.min(field.logical_min);
+                    array.logical_max = array.logical_max.max(field.logical_max);
+                }
+                _ => collection.push(field),
+            }
+        }
+
+        let mut builder = LinkCollections {
+            data,
+            start: PREPARSED_HEADER_LEN + data.u16(40)? as usize,
+            fields,
+            visited: vec![false; nodes],
+        };
+        Ok(ReportDescriptor {
+            items: vec![Item::Collection(builder.collection(0, 0)?)],
+            reports,
+        })
+    }
+
+    /// The top-level items, usually one application collection per function of the device.
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    /// Every field in the descriptor, in the order they were declared.
+    pub fn fields(&self) -> impl Iterator<Item = &Field> + '_ {
+        let mut stack = vec![self.items.iter()];
+        std::iter::from_fn(move || loop {
+            match stack.last_mut()?.next() {
+                Some(Item::Field(field)) => return Some(field),
+                Some(Item::Collection(collection)) => stack.push(collection.items.iter()),
+                None => {
+                    stack.pop();
+                }
+            }
+        })
+    }
+
+    /// Whether the reports start with a report ID byte.
+    pub fn uses_report_ids(&self) -> bool {
+        self.reports.keys().any(|(_, id)| id.is_some())
+    }
+
+    /// The length in bytes of a report, including its report ID, if the descriptor declares
+    /// any fields for it.
+    pub fn report_len(&self, kind: ReportKind, report_id: Option<u8>) -> Option<usize> {
+        let bits = self.reports.get(&(kind, report_id))?;
+        Some(bits.div_ceil(8) as usize + report_id.is_some() as usize)
+    }
+
+    /// Reads the fields of an input report, which starts with the report ID if the
+    /// descriptor uses them.
+    ///
+    /// A variable field gives a value for each element, an array field one for each usage
+    /// its elements select, e.g. for every key held on a keyboard. Constant fields, elements
+    /// longer than 32 bits and fields beyond the end of a short report are left out.
+    pub fn decode_input(&self, report: &[u8]) -> Vec<FieldValue> {
+        let (report_id, data) = match (self.uses_report_ids(), report) {
+            (true, [report_id, data @ ..]) => (Some(*report_id), data),
+            (true, []) => return vec![],
+            (false, data) => (None, data),
+        };
+        let mut values = vec![];
+        for field in self.fields().filter(|field| {
+            field.kind == ReportKind::Input && field.report_id == report_id && !field.is_constant()
+        }) {
+            field.decode(data, &mut values);
+        }
+        values
+    }
+}
+
+impl Field {
+    /// A constant field carries no data, it pads the report.
+    pub fn is_constant(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// A variable field has one value per element, an array field has elements that each
+    /// hold the index of a usage, e.g. of the keys held on a keyboard.
+    pub fn is_variable(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// A relative field reports changes, like the motion of a mouse.
+    pub fn is_relative(&self) -> bool {
+        self.flags & 0x04 != 0
+    }
+
+    /// Whether values outside the logical range mean that there is no value, like a hat
+    /// switch that is not pushed in any direction.
+    pub fn has_null_state(&self) -> bool {
+        self.flags & 0x40 != 0
+    }
+
+    /// The length of the field in bits.
+    pub fn bit_len(&self) -> u32 {
+        self.report_size * self.report_count
+    }
+
+    /// The `index`th usage of [`usages`](Self::usages), counting every ID of the ranges.
+    pub fn usage(&self, index: u32) -> Option<HidUsage> {
+        let mut index = index;
+        for range in &self.usages {
+            // ranges that run backwards select nothing
+            let Some(span) = range.max.checked_sub(range.min) else {
+                continue;
+            };
+            let count = span as u32 + 1;
+            if index < count {
+                return Some(HidUsage::new(range.page, range.min + index as u16));
+            }
+            index -= count;
+        }
+        None
+    }
+
+    fn decode(&self, data: &[u8], values: &mut Vec<FieldValue>) {
+        if !(1..=32).contains(&self.report_size) {
+            return;
+        }
+        // the last usage of a variable field applies to the elements beyond it
+        let last_usage = self
+            .usages
+            .last()
+            .map(|range| HidUsage::new(range.page, range.max));
+        for element in 0..self.report_count {
+            let offset = self.bit_offset + element * self.report_size;
+            let Some(raw) = read_bits(data, offset, self.report_size) else {
+                return;
+            };
+            let value = match self.logical_min < 0 {
+                true => sign_extend(raw, self.report_size),
+                false => raw as i32,
+            };
+            if self.is_variable() {
+                let Some(usage) = self.usage(element).or(last_usage) else {
+                    return;
+                };
+                values.push(FieldValue {
+                    usage,
+                    value,
+                    logical_min: self.logical_min,
+                    logical_max: self.logical_max,
+                    physical: self.physical(value),
+                    unit: self.unit,
+                });
+            } else if (self.logical_min..=self.logical_max).contains(&value) {
+                let index = (value as i64 - self.logical_min as i64) as u32;
+                // usage 0 is undefined, keyboards select it to report that no key is held
+                if let Some(usage) = self.usage(index).filter(|usage| usage.id != 0) {
+                    values.push(FieldValue {
+                        usage,
+                        value: 1,
+                        logical_min: 0,
+                        logical_max: 1,
+                        physical: None,
+                        unit: self.unit,
+                    });
+                }
+            }
+        }
+    }
+
+    fn physical(&self, value: i32) -> Option<f64> {
+        let in_range = (self.logical_min..=self.logical_max).contains(&value);
+        if self.has_null_state() && !in_range {
+            return None;
+        }
+        let [value, logical_min, logical_max, physical_min, physical_max] = [
+            value,
+            self.logical_min,
+            self.logical_max,
+            self.physical_min,
+            self.physical_max,
+        ]
+        .map(f64::from);
+        let physical = if logical_max == logical_min {
+            value
+        } else {
+            physical_min
+                + (value - logical_min) * (physical_max - physical_min)
+                    / (logical_max - logical_min)
+        };
+        Some(physical * 10f64.powi(self.unit_exponent as i32))
+    }
+}
+
+/// Reads `size` bits, at most 32, starting `offset` bits into `data`, with the least
+/// significant bit first as in every HID report.
+fn read_bits(data: &[u8], offset: u32, size: u32) -> Option<u32> {
+    let end = offset as usize + size as usize;
+    if end > data.len() * 8 {
+        return None;
+    }
+    // at most five bytes, as the value starts within the first one
+    let bytes = data[offset as usize / 8..end.div_ceil(8)]
+        .iter()
+        .rev()
+        .fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+    Some((bytes >> (offset % 8) & ((1 << size) - 1)) as u32)
+}
+
+fn sign_extend(raw: u32, size: u32) -> i32 {
+    let unused = 32 - size;
+    ((raw << unused) as i32) >> unused
+}
+
+impl CollectionKind {
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            0x00 => Self::Physical,
+            0x01 => Self::Application,
+            0x02 => Self::Logical,
+            0x03 => Self::Report,
+            0x04 => Self::NamedArray,
+            0x05 => Self::UsageSwitch,
+            0x06 => Self::UsageModifier,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            DescriptorErrorKind::Truncated => "truncated item",
+            DescriptorErrorKind::UnmatchedEndCollection => "end collection without collection",
+            DescriptorErrorKind::UnclosedCollection => "collection without end collection",
+            DescriptorErrorKind::TooDeep => "collections nested too deeply",
+            DescriptorErrorKind::UnmatchedPop => "pop without push",
+            DescriptorErrorKind::InvalidReportId => "invalid report id",
+            DescriptorErrorKind::ReportTooLong => "report too long",
+            DescriptorErrorKind::NotPreparsedData => "not preparsed data",
+        };
+        write!(f, "{} at offset {}", what, self.offset)
+    }
+}
+
+impl std::error::Error for DescriptorError {}
+
+/// Decodes the reports of devices, reading the descriptor of each when its first report
+/// arrives.
+#[derive(Debug, Default)]
+pub(crate) struct Decoder {
+    /// `None` for devices whose descriptor can't be read or parsed.
+    descriptors: HashMap<DeviceHandle, Option<ReportDescriptor>>,
+}
+
+impl Decoder {
+    /// Decodes an [`InputEvent::HidReport`]; other events and reports that can't be decoded
+    /// give no values. Device changes make it forget the device, as its handle may be reused.
+    pub(crate) fn decode(&mut self, backend: &impl InputBackend, event: &Event) -> Vec<FieldValue> {
+        match &event.kind {
+            InputEvent::HidReport { report } => {
+                let descriptor = self
+                    .descriptors
+                    .entry(event.device)
+                    .or_insert_with(|| backend.parse_report_descriptor(event.device).ok());
+                descriptor
+                    .as_ref()
+                    .map(|descriptor| descriptor.decode_input(report))
+                    .unwrap_or_default()
+            }
+            InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved => {
+                self.descriptors.remove(&event.device);
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The descriptor of a device, once its first report was decoded.
+    pub(crate) fn descriptor(&self, device: DeviceHandle) -> Option<&ReportDescriptor> {
+        self.descriptors.get(&device)?.as_ref()
+    }
+}
+
+/// The data of a short item, whose meaning depends on the item.
+#[derive(Debug, Clone, Copy, Default)]
+struct Value {
+    data: u32,
+    /// In bytes.
+    size: usize,
+}
+
+impl Value {
+    fn signed(self) -> i32 {
+        match self.size {
+            1 => self.data as u8 as i8 as i32,
+            2 => self.data as u16 as i16 as i32,
+            _ => self.data as i32,
+        }
+    }
+}
+
+/// The state set by global items, which Push and Pop save and restore.
+#[derive(Debug, Clone, Copy, Default)]
+struct Globals {
+    usage_page: u16,
+    logical_min: Value,
+    logical_max: Value,
+    physical_min: Value,
+    physical_max: Value,
+    unit_exponent: i8,
+    unit: u32,
+    report_size: u32,
+    report_id: Option<u8>,
+    report_count: u32,
+}
+
+/// The state set by local items, which only apply to the next main item.
+#[derive(Debug, Default)]
+struct Locals {
+    usages: Vec<UsageRange>,
+    usage_min: Option<(u16, u16)>,
+    usage_max: Option<(u16, u16)>,
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    globals: Globals,
+    pushed: Vec<Globals>,
+    locals: Locals,
+    /// The top-level items.
+    items: Vec<Item>,
+    /// The collections not closed yet, innermost last.
+    open: Vec<Collection>,
+    reports: BTreeMap<(ReportKind, Option<u8>), u32>,
+}
+
+impl Parser {
+    fn main(&mut self, tag: u8, value: Value) -> Result<(), DescriptorErrorKind> {
+        let locals = std::mem::take(&mut self.locals);
+        let kind = match tag {
+            0x08 => ReportKind::Input,
+            0x09 => ReportKind::Output,
+            0x0B => ReportKind::Feature,
+            0x0A => {
+                if self.open.len() == MAX_DEPTH {
+                    return Err(DescriptorErrorKind::TooDeep);
+                }
+                self.open.push(Collection {
+                    kind: CollectionKind::from_byte(value.data as u8),
+                    usage: locals
+                        .usages
+                        .first()
+                        .map(|range| HidUsage::new(range.page, range.min)),
+                    items: vec![],
+                });
+                return Ok(());
+            }
+            0x0C => {
+                let collection = self
+                    .open
+                    .pop()
+                    .ok_or(DescriptorErrorKind::UnmatchedEndCollection)?;
+                self.container().push(Item::Collection(collection));
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+
+        let globals = self.globals;
+        let length = self.reports.entry((kind, globals.report_id)).or_default();
+        let bit_offset = *length;
+        *length = globals
+            .report_size
+            .checked_mul(globals.report_count)
+            .and_then(|bits| bits.checked_add(bit_offset))
+            .filter(|&end| end <= MAX_REPORT_BITS)
+            .ok_or(DescriptorErrorKind::ReportTooLong)?;
+
+        let logical_min = globals.logical_min.signed();
+        let logical_max = bound(globals.logical_max, logical_min);
+        let (physical_min, physical_max) =
+            if globals.physical_min.data == 0 && globals.physical_max.data == 0 {
+                (logical_min, logical_max)
+            } else {
+                let physical_min = globals.physical_min.signed();
+                (physical_min, bound(globals.physical_max, physical_min))
+            };
+        let field = Field {
+            kind,
+            report_id: globals.report_id,
+            bit_offset,
+            report_size: globals.report_size,
+            report_count: globals.report_count,
+            flags: value.data,
+            usages: locals.usages,
+            logical_min,
+            logical_max,
+            physical_min,
+            physical_max,
+            unit: globals.unit,
+            unit_exponent: globals.unit_exponent,
+        };
+        self.container().push(Item::Field(field));
+        Ok(())
+    }
+
+    fn global(&mut self, tag: u8, value: Value) -> Result<(), DescriptorErrorKind> {
+        let globals = &mut self.globals;
+        match tag {
+            0x00 => globals.usage_page = value.data as u16,
+            0x01 => globals.logical_min = value,
+            0x02 => globals.logical_max = value,
+            0x03 => globals.physical_min = value,
+            0x04 => globals.physical_max = value,
+            0x05 => globals.unit_exponent = unit_exponent(value),
+            0x06 => globals.unit = value.data,
+            0x07 => globals.report_size = value.data,
+            0x08 => {
+                let id = u8::try_from(value.data)
+                    .ok()
+                    .filter(|&id| id != 0)
+                    .ok_or(DescriptorErrorKind::InvalidReportId)?;
+                globals.report_id = Some(id);
+            }
+            0x09 => globals.report_count = value.data,
+            0x0A => self.pushed.push(*globals),
+            0x0B => *globals = self.pushed.pop().ok_or(DescriptorErrorKind::UnmatchedPop)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn local(&mut self, tag: u8, value: Value) {
+        // four bytes hold the usage page as well
+        let usage = match value.size {
+            4 => ((value.data >> 16) as u16, value.data as u16),
+            _ => (self.globals.usage_page, value.data as u16),
+        };
+        let locals = &mut self.locals;
+        match tag {
+            0x00 => locals.usages.push(UsageRange {
+                page: usage.0,
+                min: usage.1,
+                max: usage.1,
+            }),
+            0x01 => locals.usage_min = Some(usage),
+            0x02 => locals.usage_max = Some(usage),
+            // designators, strings and delimiters are of no use for reading reports
+            _ => return,
+        }
+        if let (Some((page, min)), Some((_, max))) = (locals.usage_min, locals.usage_max) {
+            locals.usage_min = None;
+            locals.usage_max = None;
+            if min <= max {
+                locals.usages.push(UsageRange { page, min, max });
+            }
+        }
+    }
+
+    /// Where the next item goes.
+    fn container(&mut self) -> &mut Vec<Item> {
+        match self.open.last_mut() {
+            Some(collection) => &mut collection.items,
+            None => &mut self.items,
+        }
+    }
+}
+
+/// Reads the maximum of a range. It is meant to be signed like the minimum, but with a
+/// non-negative minimum, many devices write e.g. 255 as `FF` rather than `FF 00`.
+fn bound(max: Value, min: i32) -> i32 {
+    if min < 0 || max.size == 4 {
+        max.signed()
+    } else {
+        max.data as i32
+    }
+}
+
+/// Reads a unit exponent, which is meant to be a signed nibble, but some devices write a
+/// whole signed byte.
+fn unit_exponent(value: Value) -> i8 {
+    match value.data {
+        0..=0x07 => value.data as i8,
+        0x08..=0x0F => value.data as i8 - 0x10,
+        _ => value.signed().clamp(i8::MIN as i32, i8::MAX as i32) as i8,
+    }
+}
+
+/// A field read from one cap of preparsed data.
+struct Cap {
+    /// Where the cap starts.
+    offset: usize,
+    field: Field,
+    /// The index of the link collection node of the collection the field is in.
+    link_collection: usize,
+    /// The index of the cap's first usage among those of its report kind.
+    data_index: u16,
+}
+
+/// Preparsed data, read in little endian like Windows writes it.
+#[derive(Debug, Clone, Copy)]
+struct Preparsed<'a>(&'a [u8]);
+
+impl Preparsed<'_> {
+    fn bytes<const N: usize>(self, offset: usize) -> Result<[u8; N], DescriptorError> {
+        self.0
+            .get(offset..offset + N)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(DescriptorError {
+                offset: self.0.len(),
+                kind: DescriptorErrorKind::Truncated,
+            })
+    }
+
+    fn u8(self, offset: usize) -> Result<u8, DescriptorError> {
+        Ok(self.bytes::<1>(offset)?[0])
+    }
+
+    fn u16(self, offset: usize) -> Result<u16, DescriptorError> {
+        self.bytes(offset).map(u16::from_le_bytes)
+    }
+
+    fn u32(self, offset: usize) -> Result<u32, DescriptorError> {
+        self.bytes(offset).map(u32::from_le_bytes)
+    }
+
+    fn i32(self, offset: usize) -> Result<i32, DescriptorError> {
+        self.bytes(offset).map(i32::from_le_bytes)
+    }
+
+    /// Reads the cap at `offset`, or `None` for an alias of the cap after it.
+    fn cap(self, kind: ReportKind, offset: usize) -> Result<Option<Cap>, DescriptorError> {
+        let error = |kind| DescriptorError { offset, kind };
+        let cap_flags = self.u8(offset + 24)?;
+        if cap_flags & CAP_ALIAS != 0 {
+            return Ok(None);
+        }
+        // counted from 1, as Windows puts the report ID first even if there is none
+        let byte_position = self.u16(offset + 8)?;
+        if byte_position == 0 {
+            return Err(error(DescriptorErrorKind::NotPreparsedData));
+        }
+        let bit_offset = (byte_position as u32 - 1) * 8 + self.u8(offset + 3)? as u32;
+        let report_size = self.u16(offset + 4)? as u32;
+        let report_count = self.u16(offset + 6)? as u32;
+        report_size
+            .checked_mul(report_count)
+            .and_then(|bits| bits.checked_add(bit_offset))
+            .filter(|&end| end <= MAX_REPORT_BITS)
+            .ok_or(error(DescriptorErrorKind::ReportTooLong))?;
+
+        let page = self.u16(offset)?;
+        let (min, max) = if cap_flags & CAP_RANGE != 0 {
+            let range = (self.u16(offset + 60)?, self.u16(offset + 62)?);
+            if range.0 > range.1 {
+                return Err(error(DescriptorErrorKind::NotPreparsedData));
+            }
+            range
+        } else {
+            let usage = self.u16(offset + 60)?;
+            (usage, usage)
+        };
+        let (logical_min, logical_max, physical_min, physical_max) = if cap_flags & CAP_BUTTON != 0
+        {
+            // buttons only have a logical range if they are part of an array
+            let range = (self.i32(offset + 76)?, self.i32(offset + 80)?);
+            let (min, max) = if range == (0, 0) { (0, 1) } else { range };
+            (min, max, min, max)
+        } else {
+            let (logical_min, logical_max) = (self.i32(offset + 80)?, self.i32(offset + 84)?);
+            match (self.i32(offset + 88)?, self.i32(offset + 92)?) {
+                (0, 0) => (logical_min, logical_max, logical_min, logical_max),
+                (physical_min, physical_max) => {
+                    (logical_min, logical_max, physical_min, physical_max)
+                }
+            }
+        };
+        let report_id = self.u8(offset + 2)?;
+        let field = Field {
+            kind,
+            report_id: (report_id != 0).then_some(report_id),
+            bit_offset,
+            report_size,
+            report_count,
+            flags: self.u32(offset + 12)?,
+            usages: vec![UsageRange { page, min, max }],
+            logical_min,
+            logical_max,
+            physical_min,
+            physical_max,
+            unit: self.u32(offset + 96)?,
+            unit_exponent: unit_exponent(Value {
+                data: self.u32(offset + 100)?,
+                size: 4,
+            }),
+        };
+        Ok(Some(Cap {
+            offset,
+            field,
+            link_collection: self.u16(offset + 18)? as usize,
+            data_index: self.u16(offset + 72)?,
+        }))
+    }
+}
+
+/// Builds the collections of preparsed data from its link collection nodes.
+struct LinkCollections<'a> {
+    data: Preparsed<'a>,
+    /// Where the first node starts.
+    start: usize,
+    /// The fields of each node, taken as its collection is built.
+    fields: Vec<Vec<Field>>,
+    /// Which nodes have been built, so that a loop of nodes is an error rather than a hang.
+    visited: Vec<bool>,
+}
+
+impl LinkCollections<'_> {
+    fn collection(&mut self, node: usize, depth: usize) -> Result<Collection, DescriptorError> {
+        let offset = self.start + node * LINK_NODE_LEN;
+        let error = |kind| DescriptorError { offset, kind };
+        if depth == MAX_DEPTH {
+            return Err(error(DescriptorErrorKind::TooDeep));
+        }
+        match self.visited.get_mut(node) {
+            Some(visited @ false) => *visited = true,
+            _ => return Err(error(DescriptorErrorKind::NotPreparsedData)),
+        }
+
+        let data = self.data;
+        let mut items: Vec<_> = std::mem::take(&mut self.fields[node])
+            .into_iter()
+            .map(Item::Field)
+            .collect();
+        // the children are linked from the last declared to the first
+        let mut children = vec![];
+        let mut child = data.u16(offset + 10)?;
+        for _ in 0..data.u16(offset + 6)? {
+            children.push(child as usize);
+            child = data.u16(self.start + child as usize * LINK_NODE_LEN + 8)?;
+        }
+        for &child in children.iter().rev() {
+            items.push(Item::Collection(self.collection(child, depth + 1)?));
+        }
+        Ok(Collection {
+            kind: CollectionKind::from_byte(data.u32(offset + 12)? as u8),
+            usage: Some(HidUsage::new(data.u16(offset + 2)?, data.u16(offset)?)),
+            items,
+        })
+    }
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{backend::RawDevice, DeviceInfo, DeviceKind};
+
+/// An identity for a device that, unlike a [`DeviceHandle`](crate::DeviceHandle), stays the same
+/// when the device is reconnected or the machine restarts, so per-device settings can be saved.
+///
+/// Besides the kind and the vendor and product ID, it holds the most stable attribute the device
+/// reports, see [`Anchor`]. Devices that still look the same, such as two identical mice plugged
+/// in on a system that reports neither serial numbers nor locations, are told apart by
+/// `instance`, numbered in order of their [`path`](DeviceInfo::path). Which of them gets which
+/// number may change as identical devices come and go, or after a restart;
+/// [`DeviceId::best_match`] deals with that.
+///
+/// The [`Display`](fmt::Display) form, e.g. `mouse:046d:c08b:serial=1A2B#1`, can be parsed back
+/// with [`FromStr`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    pub kind: DeviceKind,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub anchor: Anchor,
+    pub instance: u32,
+}
+
+/// The attribute tying a [`DeviceId`] to one physical device.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    /// The serial number, which follows the device to any port.
+    Serial(String),
+    /// The [`location`](DeviceInfo::location), used when there is no serial number.
+    /// It holds as long as the device is plugged into the same port.
+    Location(String),
+    /// Neither is known.
+    None,
+}
+
+impl DeviceId {
+    /// The id of a device, assuming it is the only one with its attributes.
+    pub fn new(kind: DeviceKind, info: &DeviceInfo) -> Self {
+        let anchor = match (&info.serial_number, &info.location) {
+            (Some(serial), _) => Anchor::Serial(serial.clone()),
+            (None, Some(location)) => Anchor::Location(location.clone()),
+            (None, None) => Anchor::None,
+        };
+        Self {
+            kind,
+            vendor_id: info.vendor_id,
+            product_id: info.product_id,
+            anchor,
+            instance: 0,
+        }
+    }
+
+    /// The ids of `devices`, in the same order, with instance numbers telling apart
+    /// the ones that would otherwise be equal.
+    pub fn assign(devices: &[RawDevice]) -> Vec<Self> {
+        let mut ids: Vec<_> = devices
+            .iter()
+            .map(|device| Self::new(device.kind, &device.info))
+            .collect();
+        let mut groups: HashMap<_, Vec<_>> = HashMap::new();
+        for (index, id) in ids.iter().enumerate() {
+            groups.entry(id.clone()).or_default().push(index);
+        }
+        for mut group in groups.into_values() {
+            group.sort_by(|&a, &b| devices[a].info.path.cmp(&devices[b].info.path));
+            for (instance, index) in group.into_iter().enumerate() {
+                ids[index].instance = instance as u32;
+            }
+        }
+        ids
+    }
+
+    /// Finds the device a saved id refers to among `candidates`, the ids of the connected devices,
+    /// and returns its position.
+    ///
+    /// The first of these rules that picks exactly one candidate wins:
+    /// 1. the ids are equal;
+    /// 2. everything but the instance number is equal,
+    ///    e.g. one of two identical devices was unplugged;
+    /// 3. kind, vendor and product ID are equal,
+    ///    e.g. a device without a serial number moved to another port.
+    ///
+    /// If a rule matches several candidates, the saved id is ambiguous and `None` is returned.
+    pub fn best_match<'a, I>(&self, candidates: I) -> Option<usize>
+    where
+        I: IntoIterator<Item = &'a DeviceId>,
+    {
+        let candidates: Vec<_> = candidates.into_iter().collect();
+        let rules: [&dyn Fn(&DeviceId) -> bool; 3] = [
+            &|id| id == self,
+            &|id| self.same_model(id) && id.anchor == self.anchor,
+            &|id| self.same_model(id),
+        ];
+        for rule in rules {
+            let mut matches = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| rule(id))
+                .map(|(index, _)| index);
+            match (matches.next(), matches.next()) {
+                (Some(index), None) => return Some(index),
+                (Some(_), Some(_)) => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn same_model(&self, other: &DeviceId) -> bool {
+        self.kind == other.kind
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+    }
+}
+
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            DeviceKind::Mouse => "mouse",
+            DeviceKind::Keyboard => "keyboard",
+            DeviceKind::Hid => "hid",
+        };
+        write!(f, "{}:{:04x}:{:04x}", kind, self.vendor_id, self.product_id)?;
+        match &self.anchor {
+            Anchor::Serial(serial) => write!(f, ":serial={}", escape(serial))?,
+            Anchor::Location(location) => write!(f, ":location={}", escape(location))?,
+            Anchor::None => {}
+        }
+        if self.instance != 0 {
+            write!(f, "#{}", self.instance)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned when a string is not a [`DeviceId`] printed by its `Display` implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseDeviceIdError;
+
+impl fmt::Display for ParseDeviceIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid device id")
+    }
+}
+
+impl std::error::Error for ParseDeviceIdError {}
+
+impl FromStr for DeviceId {
+    type Err = ParseDeviceIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // '#' is escaped in the anchor, so the last one starts the instance number
+        let (s, instance) = match s.rsplit_once('#') {
+            Some((s, instance)) => (s, instance.parse().map_err(|_| ParseDeviceIdError)?),
+            None => (s, 0),
+        };
+        // the anchor may contain ':' itself, so only split off the first three fields
+        let mut fields = s.splitn(4, ':');
+        let kind = match fields.next() {
+            Some("mouse") => DeviceKind::Mouse,
+            Some("keyboard") => DeviceKind::Keyboard,
+            Some("hid") => DeviceKind::Hid,
+            _ => return Err(ParseDeviceIdError),
+        };
+        let mut id = || {
+            fields
+                .next()
+                .and_then(|id| u16::from_str_radix(id, 16).ok())
+                .ok_or(ParseDeviceIdError)
+        };
+        let vendor_id = id()?;
+        let product_id = id()?;
+        let anchor = match fields.next().map(|anchor| anchor.split_once('=')) {
+            None => Anchor::None,
+            Some(Some(("serial", serial))) => Anchor::Serial(unescape(serial)?),
+            Some(Some(("location", location))) => Anchor::Location(unescape(location)?),
+            Some(_) => return Err(ParseDeviceIdError),
+        };
+        Ok(Self {
+            kind,
+            vendor_id,
+            product_id,
+            anchor,
+            instance,
+        })
+    }
+}
+
+/// Percent-encodes the characters that delimit the fields of the printed id.
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '%' | '#' => escaped.push_str(&format!("%{:02X}", c as u8)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(string: &str) -> Result<String, ParseDeviceIdError> {
+    let mut parts = string.split('%');
+    let mut unescaped = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let code = part
+            .get(..2)
+            .and_then(|code| u8::from_str_radix(code, 16).ok())
+            .ok_or(ParseDeviceIdError)?;
+        unescaped.push(code as char);
+        unescaped.push_str(&part[2..]);
+    }
+    Ok(unescaped)
+}
@@ -0,0 +1,86 @@
+/// Everything a backend could find out about a device.
+///
+/// Strings the device does not report are `None`, numbers it does not report are 0.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DeviceInfo {
+    pub product_name: String,
+    pub manufacturer: Option<String>,
+    pub serial_number: Option<String>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub version: u16,
+    /// The device interface path: `RIDI_DEVICENAME` on Windows, the evdev node on Linux.
+    pub path: String,
+    /// Where the device is plugged in: the `phys` topology path on Linux,
+    /// the PnP instance segment of the path on Windows.
+    /// It stays the same as long as the device is reconnected to the same port.
+    pub location: Option<String>,
+    pub details: DeviceDetails,
+}
+
+/// The part of [`DeviceInfo`] specific to the kind of device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeviceDetails {
+    Mouse(MouseDetails),
+    Keyboard(KeyboardDetails),
+    /// The usage of a [`HidDevice`](crate::HidDevice), from `RID_DEVICE_INFO_HID`.
+    Hid(HidUsage),
+    #[default]
+    None,
+}
+
+/// Mirrors `RID_DEVICE_INFO_MOUSE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseDetails {
+    pub id: u32,
+    pub buttons: u32,
+    /// Reports per second, only known for PS/2 mice.
+    pub sample_rate: u32,
+    pub has_horizontal_wheel: bool,
+}
+
+/// Mirrors `RID_DEVICE_INFO_KEYBOARD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyboardDetails {
+    /// 4 for the enhanced 101/102-key layout, 7 for Japanese keyboards and so on.
+    pub keyboard_type: u32,
+    /// OEM-specific.
+    pub subtype: u32,
+    pub scancode_mode: u32,
+    pub function_keys: u32,
+    pub indicators: u32,
+    pub total_keys: u32,
+}
+
+/// A usage page and usage ID pair from the HID Usage Tables, naming what a device is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HidUsage {
+    pub page: u16,
+    pub id: u16,
+}
+
+impl HidUsage {
+    pub const MOUSE: Self = Self::generic_desktop(0x02);
+    pub const JOYSTICK: Self = Self::generic_desktop(0x04);
+    pub const GAMEPAD: Self = Self::generic_desktop(0x05);
+    pub const KEYBOARD: Self = Self::generic_desktop(0x06);
+    pub const MULTI_AXIS_CONTROLLER: Self = Self::generic_desktop(0x08);
+    /// Media keys, volume knobs and the like.
+    pub const CONSUMER_CONTROL: Self = Self {
+        page: 0x0C,
+        id: 0x01,
+    };
+    /// Any sensor, as a collection of them.
+    pub const SENSOR: Self = Self {
+        page: 0x20,
+        id: 0x01,
+    };
+
+    pub const fn new(page: u16, id: u16) -> Self {
+        Self { page, id }
+    }
+
+    const fn generic_desktop(id: u16) -> Self {
+        Self { page: 0x01, id }
+    }
+}
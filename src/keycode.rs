@@ -0,0 +1,510 @@
+//! Key identification independent of the platform, keyboard layout and windowing library.
+
+#[cfg(feature = "winit")]
+mod winit;
+
+/// A physical key, named after its position on a US keyboard, like the W3C `code` values.
+///
+/// The variants cover the keyboard page (`0x07`) of the HID Usage Tables and their values are
+/// the HID usages. Keys can be converted from and to PC set 1 scancodes, as in
+/// [`InputEvent::Key`](crate::InputEvent::Key), and Linux evdev key codes. Not every key has
+/// a scancode or evdev code; keys sharing one convert back to the key with the lower usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum KeyCode {
+    KeyA = 0x04,
+    KeyB = 0x05,
+    KeyC = 0x06,
+    KeyD = 0x07,
+    KeyE = 0x08,
+    KeyF = 0x09,
+    KeyG = 0x0A,
+    KeyH = 0x0B,
+    KeyI = 0x0C,
+    KeyJ = 0x0D,
+    KeyK = 0x0E,
+    KeyL = 0x0F,
+    KeyM = 0x10,
+    KeyN = 0x11,
+    KeyO = 0x12,
+    KeyP = 0x13,
+    KeyQ = 0x14,
+    KeyR = 0x15,
+    KeyS = 0x16,
+    KeyT = 0x17,
+    KeyU = 0x18,
+    KeyV = 0x19,
+    KeyW = 0x1A,
+    KeyX = 0x1B,
+    KeyY = 0x1C,
+    KeyZ = 0x1D,
+    Digit1 = 0x1E,
+    Digit2 = 0x1F,
+    Digit3 = 0x20,
+    Digit4 = 0x21,
+    Digit5 = 0x22,
+    Digit6 = 0x23,
+    Digit7 = 0x24,
+    Digit8 = 0x25,
+    Digit9 = 0x26,
+    Digit0 = 0x27,
+    Enter = 0x28,
+    Escape = 0x29,
+    Backspace = 0x2A,
+    Tab = 0x2B,
+    Space = 0x2C,
+    Minus = 0x2D,
+    Equal = 0x2E,
+    BracketLeft = 0x2F,
+    BracketRight = 0x30,
+    Backslash = 0x31,
+    /// The key left of Enter on ISO layouts, which most keyboards report as [`Backslash`](Self::Backslash).
+    NonUsHash = 0x32,
+    Semicolon = 0x33,
+    Quote = 0x34,
+    Backquote = 0x35,
+    Comma = 0x36,
+    Period = 0x37,
+    Slash = 0x38,
+    CapsLock = 0x39,
+    F1 = 0x3A,
+    F2 = 0x3B,
+    F3 = 0x3C,
+    F4 = 0x3D,
+    F5 = 0x3E,
+    F6 = 0x3F,
+    F7 = 0x40,
+    F8 = 0x41,
+    F9 = 0x42,
+    F10 = 0x43,
+    F11 = 0x44,
+    F12 = 0x45,
+    PrintScreen = 0x46,
+    ScrollLock = 0x47,
+    Pause = 0x48,
+    Insert = 0x49,
+    Home = 0x4A,
+    PageUp = 0x4B,
+    Delete = 0x4C,
+    End = 0x4D,
+    PageDown = 0x4E,
+    ArrowRight = 0x4F,
+    ArrowLeft = 0x50,
+    ArrowDown = 0x51,
+    ArrowUp = 0x52,
+    NumLock = 0x53,
+    NumpadDivide = 0x54,
+    NumpadMultiply = 0x55,
+    NumpadSubtract = 0x56,
+    NumpadAdd = 0x57,
+    NumpadEnter = 0x58,
+    Numpad1 = 0x59,
+    Numpad2 = 0x5A,
+    Numpad3 = 0x5B,
+    Numpad4 = 0x5C,
+    Numpad5 = 0x5D,
+    Numpad6 = 0x5E,
+    Numpad7 = 0x5F,
+    Numpad8 = 0x60,
+    Numpad9 = 0x61,
+    Numpad0 = 0x62,
+    NumpadDecimal = 0x63,
+    /// The key between left Shift and Z on ISO layouts.
+    IntlBackslash = 0x64,
+    ContextMenu = 0x65,
+    Power = 0x66,
+    NumpadEqual = 0x67,
+    F13 = 0x68,
+    F14 = 0x69,
+    F15 = 0x6A,
+    F16 = 0x6B,
+    F17 = 0x6C,
+    F18 = 0x6D,
+    F19 = 0x6E,
+    F20 = 0x6F,
+    F21 = 0x70,
+    F22 = 0x71,
+    F23 = 0x72,
+    F24 = 0x73,
+    Execute = 0x74,
+    Help = 0x75,
+    Menu = 0x76,
+    Select = 0x77,
+    Stop = 0x78,
+    Again = 0x79,
+    Undo = 0x7A,
+    Cut = 0x7B,
+    Copy = 0x7C,
+    Paste = 0x7D,
+    Find = 0x7E,
+    Mute = 0x7F,
+    VolumeUp = 0x80,
+    VolumeDown = 0x81,
+    LockingCapsLock = 0x82,
+    LockingNumLock = 0x83,
+    LockingScrollLock = 0x84,
+    NumpadComma = 0x85,
+    /// The keypad equals sign of AS/400 keyboards.
+    NumpadEqualAs400 = 0x86,
+    /// International 1, <kbd>ろ</kbd> on Japanese keyboards.
+    IntlRo = 0x87,
+    /// International 2, <kbd>カタカナ/ひらがな</kbd> on Japanese keyboards.
+    KanaMode = 0x88,
+    /// International 3, <kbd>¥</kbd> on Japanese keyboards.
+    IntlYen = 0x89,
+    /// International 4, <kbd>変換</kbd> on Japanese keyboards.
+    Convert = 0x8A,
+    /// International 5, <kbd>無変換</kbd> on Japanese keyboards.
+    NonConvert = 0x8B,
+    /// The keypad comma of Japanese keyboards.
+    International6 = 0x8C,
+    International7 = 0x8D,
+    International8 = 0x8E,
+    International9 = 0x8F,
+    /// <kbd>한/영</kbd> on Korean keyboards.
+    Lang1 = 0x90,
+    /// <kbd>한자</kbd> on Korean keyboards.
+    Lang2 = 0x91,
+    /// <kbd>カタカナ</kbd> on Japanese keyboards.
+    Lang3 = 0x92,
+    /// <kbd>ひらがな</kbd> on Japanese keyboards.
+    Lang4 = 0x93,
+    /// <kbd>半角/全角</kbd> on Japanese keyboards.
+    Lang5 = 0x94,
+    Lang6 = 0x95,
+    Lang7 = 0x96,
+    Lang8 = 0x97,
+    Lang9 = 0x98,
+    AltErase = 0x99,
+    SysReq = 0x9A,
+    Cancel = 0x9B,
+    Clear = 0x9C,
+    Prior = 0x9D,
+    Return = 0x9E,
+    Separator = 0x9F,
+    Out = 0xA0,
+    Oper = 0xA1,
+    ClearAgain = 0xA2,
+    CrSel = 0xA3,
+    ExSel = 0xA4,
+    Numpad00 = 0xB0,
+    Numpad000 = 0xB1,
+    ThousandsSeparator = 0xB2,
+    DecimalSeparator = 0xB3,
+    CurrencyUnit = 0xB4,
+    CurrencySubunit = 0xB5,
+    NumpadParenLeft = 0xB6,
+    NumpadParenRight = 0xB7,
+    NumpadBraceLeft = 0xB8,
+    NumpadBraceRight = 0xB9,
+    NumpadTab = 0xBA,
+    NumpadBackspace = 0xBB,
+    NumpadA = 0xBC,
+    NumpadB = 0xBD,
+    NumpadC = 0xBE,
+    NumpadD = 0xBF,
+    NumpadE = 0xC0,
+    NumpadF = 0xC1,
+    NumpadXor = 0xC2,
+    NumpadCaret = 0xC3,
+    NumpadPercent = 0xC4,
+    NumpadLess = 0xC5,
+    NumpadGreater = 0xC6,
+    NumpadAmpersand = 0xC7,
+    NumpadDoubleAmpersand = 0xC8,
+    NumpadBar = 0xC9,
+    NumpadDoubleBar = 0xCA,
+    NumpadColon = 0xCB,
+    NumpadHash = 0xCC,
+    NumpadSpace = 0xCD,
+    NumpadAt = 0xCE,
+    NumpadBang = 0xCF,
+    NumpadMemoryStore = 0xD0,
+    NumpadMemoryRecall = 0xD1,
+    NumpadMemoryClear = 0xD2,
+    NumpadMemoryAdd = 0xD3,
+    NumpadMemorySubtract = 0xD4,
+    NumpadMemoryMultiply = 0xD5,
+    NumpadMemoryDivide = 0xD6,
+    NumpadPlusMinus = 0xD7,
+    NumpadClear = 0xD8,
+    NumpadClearEntry = 0xD9,
+    NumpadBinary = 0xDA,
+    NumpadOctal = 0xDB,
+    NumpadDecimalBase = 0xDC,
+    NumpadHexadecimal = 0xDD,
+    ControlLeft = 0xE0,
+    ShiftLeft = 0xE1,
+    AltLeft = 0xE2,
+    SuperLeft = 0xE3,
+    ControlRight = 0xE4,
+    ShiftRight = 0xE5,
+    AltRight = 0xE6,
+    SuperRight = 0xE7,
+}
+
+/// Marks a key without a scancode or evdev code; 0 is neither.
+const NONE: u16 = 0;
+
+/// Every key with its set 1 scancode and evdev code.
+const KEYS: [(KeyCode, u16, u16); 215] = [
+    (KeyCode::KeyA, 0x1E, 30),
+    (KeyCode::KeyB, 0x30, 48),
+    (KeyCode::KeyC, 0x2E, 46),
+    (KeyCode::KeyD, 0x20, 32),
+    (KeyCode::KeyE, 0x12, 18),
+    (KeyCode::KeyF, 0x21, 33),
+    (KeyCode::KeyG, 0x22, 34),
+    (KeyCode::KeyH, 0x23, 35),
+    (KeyCode::KeyI, 0x17, 23),
+    (KeyCode::KeyJ, 0x24, 36),
+    (KeyCode::KeyK, 0x25, 37),
+    (KeyCode::KeyL, 0x26, 38),
+    (KeyCode::KeyM, 0x32, 50),
+    (KeyCode::KeyN, 0x31, 49),
+    (KeyCode::KeyO, 0x18, 24),
+    (KeyCode::KeyP, 0x19, 25),
+    (KeyCode::KeyQ, 0x10, 16),
+    (KeyCode::KeyR, 0x13, 19),
+    (KeyCode::KeyS, 0x1F, 31),
+    (KeyCode::KeyT, 0x14, 20),
+    (KeyCode::KeyU, 0x16, 22),
+    (KeyCode::KeyV, 0x2F, 47),
+    (KeyCode::KeyW, 0x11, 17),
+    (KeyCode::KeyX, 0x2D, 45),
+    (KeyCode::KeyY, 0x15, 21),
+    (KeyCode::KeyZ, 0x2C, 44),
+    (KeyCode::Digit1, 0x02, 2),
+    (KeyCode::Digit2, 0x03, 3),
+    (KeyCode::Digit3, 0x04, 4),
+    (KeyCode::Digit4, 0x05, 5),
+    (KeyCode::Digit5, 0x06, 6),
+    (KeyCode::Digit6, 0x07, 7),
+    (KeyCode::Digit7, 0x08, 8),
+    (KeyCode::Digit8, 0x09, 9),
+    (KeyCode::Digit9, 0x0A, 10),
+    (KeyCode::Digit0, 0x0B, 11),
+    (KeyCode::Enter, 0x1C, 28),
+    (KeyCode::Escape, 0x01, 1),
+    (KeyCode::Backspace, 0x0E, 14),
+    (KeyCode::Tab, 0x0F, 15),
+    (KeyCode::Space, 0x39, 57),
+    (KeyCode::Minus, 0x0C, 12),
+    (KeyCode::Equal, 0x0D, 13),
+    (KeyCode::BracketLeft, 0x1A, 26),
+    (KeyCode::BracketRight, 0x1B, 27),
+    (KeyCode::Backslash, 0x2B, 43),
+    (KeyCode::NonUsHash, 0x2B, 43),
+    (KeyCode::Semicolon, 0x27, 39),
+    (KeyCode::Quote, 0x28, 40),
+    (KeyCode::Backquote, 0x29, 41),
+    (KeyCode::Comma, 0x33, 51),
+    (KeyCode::Period, 0x34, 52),
+    (KeyCode::Slash, 0x35, 53),
+    (KeyCode::CapsLock, 0x3A, 58),
+    (KeyCode::F1, 0x3B, 59),
+    (KeyCode::F2, 0x3C, 60),
+    (KeyCode::F3, 0x3D, 61),
+    (KeyCode::F4, 0x3E, 62),
+    (KeyCode::F5, 0x3F, 63),
+    (KeyCode::F6, 0x40, 64),
+    (KeyCode::F7, 0x41, 65),
+    (KeyCode::F8, 0x42, 66),
+    (KeyCode::F9, 0x43, 67),
+    (KeyCode::F10, 0x44, 68),
+    (KeyCode::F11, 0x57, 87),
+    (KeyCode::F12, 0x58, 88),
+    (KeyCode::PrintScreen, 0xE037, 99),
+    (KeyCode::ScrollLock, 0x46, 70),
+    (KeyCode::Pause, 0xE11D, 119),
+    (KeyCode::Insert, 0xE052, 110),
+    (KeyCode::Home, 0xE047, 102),
+    (KeyCode::PageUp, 0xE049, 104),
+    (KeyCode::Delete, 0xE053, 111),
+    (KeyCode::End, 0xE04F, 107),
+    (KeyCode::PageDown, 0xE051, 109),
+    (KeyCode::ArrowRight, 0xE04D, 106),
+    (KeyCode::ArrowLeft, 0xE04B, 105),
+    (KeyCode::ArrowDown, 0xE050, 108),
+    (KeyCode::ArrowUp, 0xE048, 103),
+    (KeyCode::NumLock, 0x45, 69),
+    (KeyCode::NumpadDivide, 0xE035, 98),
+    (KeyCode::NumpadMultiply, 0x37, 55),
+    (KeyCode::NumpadSubtract, 0x4A, 74),
+    (KeyCode::NumpadAdd, 0x4E, 78),
+    (KeyCode::NumpadEnter, 0xE01C, 96),
+    (KeyCode::Numpad1, 0x4F, 79),
+    (KeyCode::Numpad2, 0x50, 80),
+    (KeyCode::Numpad3, 0x51, 81),
+    (KeyCode::Numpad4, 0x4B, 75),
+    (KeyCode::Numpad5, 0x4C, 76),
+    (KeyCode::Numpad6, 0x4D, 77),
+    (KeyCode::Numpad7, 0x47, 71),
+    (KeyCode::Numpad8, 0x48, 72),
+    (KeyCode::Numpad9, 0x49, 73),
+    (KeyCode::Numpad0, 0x52, 82),
+    (KeyCode::NumpadDecimal, 0x53, 83),
+    (KeyCode::IntlBackslash, 0x56, 86),
+    (KeyCode::ContextMenu, 0xE05D, 127),
+    (KeyCode::Power, 0xE05E, 116),
+    (KeyCode::NumpadEqual, 0x59, 117),
+    (KeyCode::F13, 0x64, 183),
+    (KeyCode::F14, 0x65, 184),
+    (KeyCode::F15, 0x66, 185),
+    (KeyCode::F16, 0x67, 186),
+    (KeyCode::F17, 0x68, 187),
+    (KeyCode::F18, 0x69, 188),
+    (KeyCode::F19, 0x6A, 189),
+    (KeyCode::F20, 0x6B, 190),
+    (KeyCode::F21, 0x6C, 191),
+    (KeyCode::F22, 0x6D, 192),
+    (KeyCode::F23, 0x6E, 193),
+    (KeyCode::F24, 0x76, 194),
+    (KeyCode::Execute, NONE, 134),
+    (KeyCode::Help, NONE, 138),
+    (KeyCode::Menu, NONE, 130),
+    (KeyCode::Select, NONE, 132),
+    (KeyCode::Stop, NONE, 128),
+    (KeyCode::Again, NONE, 129),
+    (KeyCode::Undo, NONE, 131),
+    (KeyCode::Cut, NONE, 137),
+    (KeyCode::Copy, NONE, 133),
+    (KeyCode::Paste, NONE, 135),
+    (KeyCode::Find, NONE, 136),
+    (KeyCode::Mute, 0xE020, 113),
+    (KeyCode::VolumeUp, 0xE030, 115),
+    (KeyCode::VolumeDown, 0xE02E, 114),
+    (KeyCode::LockingCapsLock, NONE, NONE),
+    (KeyCode::LockingNumLock, NONE, NONE),
+    (KeyCode::LockingScrollLock, NONE, NONE),
+    (KeyCode::NumpadComma, 0x7E, 121),
+    (KeyCode::NumpadEqualAs400, NONE, NONE),
+    (KeyCode::IntlRo, 0x73, 89),
+    (KeyCode::KanaMode, 0x70, 93),
+    (KeyCode::IntlYen, 0x7D, 124),
+    (KeyCode::Convert, 0x79, 92),
+    (KeyCode::NonConvert, 0x7B, 94),
+    (KeyCode::International6, 0x5C, 95),
+    (KeyCode::International7, NONE, NONE),
+    (KeyCode::International8, NONE, NONE),
+    (KeyCode::International9, NONE, NONE),
+    (KeyCode::Lang1, 0xF2, 122),
+    (KeyCode::Lang2, 0xF1, 123),
+    (KeyCode::Lang3, 0x78, 90),
+    (KeyCode::Lang4, 0x77, 91),
+    (KeyCode::Lang5, 0x76, 85),
+    (KeyCode::Lang6, NONE, NONE),
+    (KeyCode::Lang7, NONE, NONE),
+    (KeyCode::Lang8, NONE, NONE),
+    (KeyCode::Lang9, NONE, NONE),
+    (KeyCode::AltErase, NONE, NONE),
+    (KeyCode::SysReq, NONE, NONE),
+    (KeyCode::Cancel, NONE, NONE),
+    (KeyCode::Clear, NONE, NONE),
+    (KeyCode::Prior, NONE, NONE),
+    (KeyCode::Return, NONE, NONE),
+    (KeyCode::Separator, NONE, NONE),
+    (KeyCode::Out, NONE, NONE),
+    (KeyCode::Oper, NONE, NONE),
+    (KeyCode::ClearAgain, NONE, NONE),
+    (KeyCode::CrSel, NONE, NONE),
+    (KeyCode::ExSel, NONE, NONE),
+    (KeyCode::Numpad00, NONE, NONE),
+    (KeyCode::Numpad000, NONE, NONE),
+    (KeyCode::ThousandsSeparator, NONE, NONE),
+    (KeyCode::DecimalSeparator, NONE, NONE),
+    (KeyCode::CurrencyUnit, NONE, NONE),
+    (KeyCode::CurrencySubunit, NONE, NONE),
+    (KeyCode::NumpadParenLeft, NONE, 179),
+    (KeyCode::NumpadParenRight, NONE, 180),
+    (KeyCode::NumpadBraceLeft, NONE, NONE),
+    (KeyCode::NumpadBraceRight, NONE, NONE),
+    (KeyCode::NumpadTab, NONE, NONE),
+    (KeyCode::NumpadBackspace, NONE, NONE),
+    (KeyCode::NumpadA, NONE, NONE),
+    (KeyCode::NumpadB, NONE, NONE),
+    (KeyCode::NumpadC, NONE, NONE),
+    (KeyCode::NumpadD, NONE, NONE),
+    (KeyCode::NumpadE, NONE, NONE),
+    (KeyCode::NumpadF, NONE, NONE),
+    (KeyCode::NumpadXor, NONE, NONE),
+    (KeyCode::NumpadCaret, NONE, NONE),
+    (KeyCode::NumpadPercent, NONE, NONE),
+    (KeyCode::NumpadLess, NONE, NONE),
+    (KeyCode::NumpadGreater, NONE, NONE),
+    (KeyCode::NumpadAmpersand, NONE, NONE),
+    (KeyCode::NumpadDoubleAmpersand, NONE, NONE),
+    (KeyCode::NumpadBar, NONE, NONE),
+    (KeyCode::NumpadDoubleBar, NONE, NONE),
+    (KeyCode::NumpadColon, NONE, NONE),
+    (KeyCode::NumpadHash, NONE, NONE),
+    (KeyCode::NumpadSpace, NONE, NONE),
+    (KeyCode::NumpadAt, NONE, NONE),
+    (KeyCode::NumpadBang, NONE, NONE),
+    (KeyCode::NumpadMemoryStore, NONE, NONE),
+    (KeyCode::NumpadMemoryRecall, NONE, NONE),
+    (KeyCode::NumpadMemoryClear, NONE, NONE),
+    (KeyCode::NumpadMemoryAdd, NONE, NONE),
+    (KeyCode::NumpadMemorySubtract, NONE, NONE),
+    (KeyCode::NumpadMemoryMultiply, NONE, NONE),
+    (KeyCode::NumpadMemoryDivide, NONE, NONE),
+    (KeyCode::NumpadPlusMinus, NONE, NONE),
+    (KeyCode::NumpadClear, NONE, NONE),
+    (KeyCode::NumpadClearEntry, NONE, NONE),
+    (KeyCode::NumpadBinary, NONE, NONE),
+    (KeyCode::NumpadOctal, NONE, NONE),
+    (KeyCode::NumpadDecimalBase, NONE, NONE),
+    (KeyCode::NumpadHexadecimal, NONE, NONE),
+    (KeyCode::ControlLeft, 0x1D, 29),
+    (KeyCode::ShiftLeft, 0x2A, 42),
+    (KeyCode::AltLeft, 0x38, 56),
+    (KeyCode::SuperLeft, 0xE05B, 125),
+    (KeyCode::ControlRight, 0xE01D, 97),
+    (KeyCode::ShiftRight, 0x36, 54),
+    (KeyCode::AltRight, 0xE038, 100),
+    (KeyCode::SuperRight, 0xE05C, 126),
+];
+
+impl KeyCode {
+    /// The key with a usage from the HID keyboard page.
+    pub fn from_hid_usage(usage: u16) -> Option<Self> {
+        find(|&(key, _, _)| key as u16 == usage).map(|(key, _, _)| key)
+    }
+
+    pub fn hid_usage(self) -> u16 {
+        self as u16
+    }
+
+    /// The key with a set 1 scancode in the form of [`InputEvent::Key`](crate::InputEvent::Key).
+    pub fn from_scancode(scancode: u16) -> Option<Self> {
+        if scancode == NONE {
+            return None;
+        }
+        find(|&(_, key_scancode, _)| key_scancode == scancode).map(|(key, _, _)| key)
+    }
+
+    pub fn scancode(self) -> Option<u16> {
+        find(|&(key, _, _)| key == self)
+            .map(|(_, scancode, _)| scancode)
+            .filter(|&scancode| scancode != NONE)
+    }
+
+    /// The key with a `KEY_*` code from `linux/input-event-codes.h`.
+    pub fn from_evdev(code: u16) -> Option<Self> {
+        if code == NONE {
+            return None;
+        }
+        find(|&(_, _, key_code)| key_code == code).map(|(key, _, _)| key)
+    }
+
+    pub fn evdev_code(self) -> Option<u16> {
+        find(|&(key, _, _)| key == self)
+            .map(|(_, _, code)| code)
+            .filter(|&code| code != NONE)
+    }
+}
+
+/// The first entry of [`KEYS`] matching `predicate`.
+fn find(predicate: impl Fn(&(KeyCode, u16, u16)) -> bool) -> Option<(KeyCode, u16, u16)> {
+    KEYS.iter().copied().find(predicate)
+}
@@ -0,0 +1,187 @@
+//! Conversions to and from the key codes of winit.
+
+use ::winit::keyboard::{KeyCode as Winit, NativeKeyCode, PhysicalKey};
+
+use super::KeyCode;
+
+/// The keys winit knows. [`KeyCode::NonUsHash`] is winit's [`Winit::Backslash`] as well.
+const KEYS: [(KeyCode, Winit); 155] = [
+    (KeyCode::KeyA, Winit::KeyA),
+    (KeyCode::KeyB, Winit::KeyB),
+    (KeyCode::KeyC, Winit::KeyC),
+    (KeyCode::KeyD, Winit::KeyD),
+    (KeyCode::KeyE, Winit::KeyE),
+    (KeyCode::KeyF, Winit::KeyF),
+    (KeyCode::KeyG, Winit::KeyG),
+    (KeyCode::KeyH, Winit::KeyH),
+    (KeyCode::KeyI, Winit::KeyI),
+    (KeyCode::KeyJ, Winit::KeyJ),
+    (KeyCode::KeyK, Winit::KeyK),
+    (KeyCode::KeyL, Winit::KeyL),
+    (KeyCode::KeyM, Winit::KeyM),
+    (KeyCode::KeyN, Winit::KeyN),
+    (KeyCode::KeyO, Winit::KeyO),
+    (KeyCode::KeyP, Winit::KeyP),
+    (KeyCode::KeyQ, Winit::KeyQ),
+    (KeyCode::KeyR, Winit::KeyR),
+    (KeyCode::KeyS, Winit::KeyS),
+    (KeyCode::KeyT, Winit::KeyT),
+    (KeyCode::KeyU, Winit::KeyU),
+    (KeyCode::KeyV, Winit::KeyV),
+    (KeyCode::KeyW, Winit::KeyW),
+    (KeyCode::KeyX, Winit::KeyX),
+    (KeyCode::KeyY, Winit::KeyY),
+    (KeyCode::KeyZ, Winit::KeyZ),
+    (KeyCode::Digit1, Winit::Digit1),
+    (KeyCode::Digit2, Winit::Digit2),
+    (KeyCode::Digit3, Winit::Digit3),
+    (KeyCode::Digit4, Winit::Digit4),
+    (KeyCode::Digit5, Winit::Digit5),
+    (KeyCode::Digit6, Winit::Digit6),
+    (KeyCode::Digit7, Winit::Digit7),
+    (KeyCode::Digit8, Winit::Digit8),
+    (KeyCode::Digit9, Winit::Digit9),
+    (KeyCode::Digit0, Winit::Digit0),
+    (KeyCode::Enter, Winit::Enter),
+    (KeyCode::Escape, Winit::Escape),
+    (KeyCode::Backspace, Winit::Backspace),
+    (KeyCode::Tab, Winit::Tab),
+    (KeyCode::Space, Winit::Space),
+    (KeyCode::Minus, Winit::Minus),
+    (KeyCode::Equal, Winit::Equal),
+    (KeyCode::BracketLeft, Winit::BracketLeft),
+    (KeyCode::BracketRight, Winit::BracketRight),
+    (KeyCode::Backslash, Winit::Backslash),
+    (KeyCode::NonUsHash, Winit::Backslash),
+    (KeyCode::Semicolon, Winit::Semicolon),
+    (KeyCode::Quote, Winit::Quote),
+    (KeyCode::Backquote, Winit::Backquote),
+    (KeyCode::Comma, Winit::Comma),
+    (KeyCode::Period, Winit::Period),
+    (KeyCode::Slash, Winit::Slash),
+    (KeyCode::CapsLock, Winit::CapsLock),
+    (KeyCode::F1, Winit::F1),
+    (KeyCode::F2, Winit::F2),
+    (KeyCode::F3, Winit::F3),
+    (KeyCode::F4, Winit::F4),
+    (KeyCode::F5, Winit::F5),
+    (KeyCode::F6, Winit::F6),
+    (KeyCode::F7, Winit::F7),
+    (KeyCode::F8, Winit::F8),
+    (KeyCode::F9, Winit::F9),
+    (KeyCode::F10, Winit::F10),
+    (KeyCode::F11, Winit::F11),
+    (KeyCode::F12, Winit::F12),
+    (KeyCode::PrintScreen, Winit::PrintScreen),
+    (KeyCode::ScrollLock, Winit::ScrollLock),
+    (KeyCode::Pause, Winit::Pause),
+    (KeyCode::Insert, Winit::Insert),
+    (KeyCode::Home, Winit::Home),
+    (KeyCode::PageUp, Winit::PageUp),
+    (KeyCode::Delete, Winit::Delete),
+    (KeyCode::End, Winit::End),
+    (KeyCode::PageDown, Winit::PageDown),
+    (KeyCode::ArrowRight, Winit::ArrowRight),
+    (KeyCode::ArrowLeft, Winit::ArrowLeft),
+    (KeyCode::ArrowDown, Winit::ArrowDown),
+    (KeyCode::ArrowUp, Winit::ArrowUp),
+    (KeyCode::NumLock, Winit::NumLock),
+    (KeyCode::NumpadDivide, Winit::NumpadDivide),
+    (KeyCode::NumpadMultiply, Winit::NumpadMultiply),
+    (KeyCode::NumpadSubtract, Winit::NumpadSubtract),
+    (KeyCode::NumpadAdd, Winit::NumpadAdd),
+    (KeyCode::NumpadEnter, Winit::NumpadEnter),
+    (KeyCode::Numpad1, Winit::Numpad1),
+    (KeyCode::Numpad2, Winit::Numpad2),
+    (KeyCode::Numpad3, Winit::Numpad3),
+    (KeyCode::Numpad4, Winit::Numpad4),
+    (KeyCode::Numpad5, Winit::Numpad5),
+    (KeyCode::Numpad6, Winit::Numpad6),
+    (KeyCode::Numpad7, Winit::Numpad7),
+    (KeyCode::Numpad8, Winit::Numpad8),
+    (KeyCode::Numpad9, Winit::Numpad9),
+    (KeyCode::Numpad0, Winit::Numpad0),
+    (KeyCode::NumpadDecimal, Winit::NumpadDecimal),
+    (KeyCode::IntlBackslash, Winit::IntlBackslash),
+    (KeyCode::ContextMenu, Winit::ContextMenu),
+    (KeyCode::Power, Winit::Power),
+    (KeyCode::NumpadEqual, Winit::NumpadEqual),
+    (KeyCode::F13, Winit::F13),
+    (KeyCode::F14, Winit::F14),
+    (KeyCode::F15, Winit::F15),
+    (KeyCode::F16, Winit::F16),
+    (KeyCode::F17, Winit::F17),
+    (KeyCode::F18, Winit::F18),
+    (KeyCode::F19, Winit::F19),
+    (KeyCode::F20, Winit::F20),
+    (KeyCode::F21, Winit::F21),
+    (KeyCode::F22, Winit::F22),
+    (KeyCode::F23, Winit::F23),
+    (KeyCode::F24, Winit::F24),
+    (KeyCode::Execute, Winit::Open),
+    (KeyCode::Help, Winit::Help),
+    (KeyCode::Menu, Winit::Props),
+    (KeyCode::Select, Winit::Select),
+    (KeyCode::Again, Winit::Again),
+    (KeyCode::Undo, Winit::Undo),
+    (KeyCode::Cut, Winit::Cut),
+    (KeyCode::Copy, Winit::Copy),
+    (KeyCode::Paste, Winit::Paste),
+    (KeyCode::Find, Winit::Find),
+    (KeyCode::Mute, Winit::AudioVolumeMute),
+    (KeyCode::VolumeUp, Winit::AudioVolumeUp),
+    (KeyCode::VolumeDown, Winit::AudioVolumeDown),
+    (KeyCode::NumpadComma, Winit::NumpadComma),
+    (KeyCode::IntlRo, Winit::IntlRo),
+    (KeyCode::KanaMode, Winit::KanaMode),
+    (KeyCode::IntlYen, Winit::IntlYen),
+    (KeyCode::Convert, Winit::Convert),
+    (KeyCode::NonConvert, Winit::NonConvert),
+    (KeyCode::Lang1, Winit::Lang1),
+    (KeyCode::Lang2, Winit::Lang2),
+    (KeyCode::Lang3, Winit::Lang3),
+    (KeyCode::Lang4, Winit::Lang4),
+    (KeyCode::Lang5, Winit::Lang5),
+    (KeyCode::NumpadParenLeft, Winit::NumpadParenLeft),
+    (KeyCode::NumpadParenRight, Winit::NumpadParenRight),
+    (KeyCode::NumpadBackspace, Winit::NumpadBackspace),
+    (KeyCode::NumpadHash, Winit::NumpadHash),
+    (KeyCode::NumpadMemoryStore, Winit::NumpadMemoryStore),
+    (KeyCode::NumpadMemoryRecall, Winit::NumpadMemoryRecall),
+    (KeyCode::NumpadMemoryClear, Winit::NumpadMemoryClear),
+    (KeyCode::NumpadMemoryAdd, Winit::NumpadMemoryAdd),
+    (KeyCode::NumpadMemorySubtract, Winit::NumpadMemorySubtract),
+    (KeyCode::NumpadClear, Winit::NumpadClear),
+    (KeyCode::NumpadClearEntry, Winit::NumpadClearEntry),
+    (KeyCode::ControlLeft, Winit::ControlLeft),
+    (KeyCode::ShiftLeft, Winit::ShiftLeft),
+    (KeyCode::AltLeft, Winit::AltLeft),
+    (KeyCode::SuperLeft, Winit::SuperLeft),
+    (KeyCode::ControlRight, Winit::ControlRight),
+    (KeyCode::ShiftRight, Winit::ShiftRight),
+    (KeyCode::AltRight, Winit::AltRight),
+    (KeyCode::SuperRight, Winit::SuperRight),
+];
+
+impl KeyCode {
+    pub fn to_winit(self) -> Option<Winit> {
+        KEYS.iter()
+            .find(|&&(key, _)| key == self)
+            .map(|&(_, winit)| winit)
+    }
+
+    pub fn from_winit(winit: Winit) -> Option<Self> {
+        KEYS.iter()
+            .find(|&&(_, winit_key)| winit_key == winit)
+            .map(|&(key, _)| key)
+    }
+}
+
+impl From<KeyCode> for PhysicalKey {
+    fn from(key: KeyCode) -> Self {
+        match key.to_winit() {
+            Some(code) => PhysicalKey::Code(code),
+            None => PhysicalKey::Unidentified(NativeKeyCode::Unidentified),
+        }
+    }
+}
@@ -1,355 +1,952 @@
 use std::{
-    collections::HashSet,
-    ffi::{c_void, OsStr, OsString},
-    os::windows::{
-        ffi::{OsStrExt, OsStringExt},
-        raw::HANDLE,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
     },
 };
 
-use windows::Win32::{
-    Devices::HumanInterfaceDevice::HidD_GetProductString,
-    Foundation::{self, GetLastError, BOOLEAN},
-};
-use windows::Win32::{
-    Devices::HumanInterfaceDevice::HID_USAGE_GENERIC_MOUSE,
-    UI::WindowsAndMessaging::{
-        CreateWindowExW, DefWindowProcW, RegisterClassExW, CW_USEDEFAULT, HMENU, HWND_MESSAGE,
-        WINDOW_EX_STYLE, WINDOW_STYLE, WNDCLASS_STYLES,
-    },
-};
-use windows::Win32::{
-    Devices::HumanInterfaceDevice::{HID_USAGE_GENERIC_KEYBOARD, HID_USAGE_PAGE_GENERIC},
-    UI::Input::{
-        GetRawInputBuffer, RAWINPUT, RAWINPUTDEVICE_FLAGS, RAWINPUTHEADER, RIDEV_INPUTSINK,
-    },
-};
-use windows::{
-    core::PCWSTR,
-    Win32::{
-        Storage::FileSystem::{FILE_ATTRIBUTE_READONLY, FILE_SHARE_READ, OPEN_EXISTING},
-        UI::Input::{GetRawInputDeviceInfoW, GetRawInputDeviceList, RIDI_DEVICENAME},
-    },
-};
-use windows::{
-    core::PWSTR,
-    Win32::{
-        Foundation::HWND,
-        System::LibraryLoader::GetModuleHandleW,
-        UI::{
-            Input::{RegisterRawInputDevices, RAWINPUTDEVICE, RAWINPUTDEVICELIST},
-            WindowsAndMessaging::WNDCLASSEXW,
-        },
-    },
-};
-pub struct Devices {
-    //devices: HashSet<*mut c_void>,
+pub mod backend;
+mod channel;
+pub mod decode;
+mod error;
+mod event;
+mod gamepad;
+pub mod hid;
+mod id;
+mod info;
+mod keycode;
+pub mod mapping;
+mod polling;
+pub mod record;
+mod state;
+#[cfg(feature = "async")]
+mod stream;
+mod subscription;
+
+use backend::{DefaultBackend, InputBackend, RawDevice};
+pub use channel::{ChannelConfig, DeviceEvent, EventReceiver, Overflow};
+pub use error::{Error, Result};
+pub use event::{ButtonState, Event, InputEvent, MouseButton, Timestamp, WheelAxis};
+pub use gamepad::{GamepadFamily, GamepadLayout};
+use hid::{Decoder, FieldValue, ReportDescriptor};
+pub use id::{Anchor, DeviceId, ParseDeviceIdError};
+pub use info::{DeviceDetails, DeviceInfo, HidUsage, KeyboardDetails, MouseDetails};
+pub use keycode::KeyCode;
+use mapping::{Guid, Mapping, MappingDatabase};
+pub use polling::ListenerHandle;
+pub use state::{GamepadState, Hat, KeyboardState, Modifiers, MouseState, Rect};
+#[cfg(feature = "async")]
+pub use stream::EventStream;
+use subscription::Handlers;
+pub use subscription::Subscription;
+
+/// An OS-level handle identifying a connected device.
+/// It is only valid for as long as the device stays connected,
+/// use a [`DeviceId`] to recognize a device across reconnects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceHandle(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    Mouse,
+    Keyboard,
+    /// Any other HID device, such as a gamepad, see [`HidDevice`].
+    Hid,
+}
+
+pub struct Devices<B: InputBackend = DefaultBackend> {
+    backend: Arc<B>,
+    /// Shared with the listener thread, which applies device changes as they arrive.
+    lists: Arc<Mutex<DeviceLists>>,
+    /// Shared with the listener thread, which calls them.
+    handlers: Arc<Mutex<Handlers>>,
+    /// Set while a listener thread runs, see [`Listening`].
+    listening: Arc<AtomicBool>,
+}
+
+/// Decides whether a device connected while listening is added.
+type Filter = Box<dyn Fn(DeviceKind, &DeviceInfo) -> bool + Send>;
+
+/// The added devices, whose events are delivered.
+#[derive(Default)]
+struct DeviceLists {
     mice: Vec<Mouse>,
     keyboards: Vec<Keyboard>,
-    // thread_handle: Option<_>,
+    hid_devices: Vec<HidDevice>,
+    /// One per [`Devices::add_all_devices`], [`Devices::add_devices_where`] or
+    /// [`Devices::add_hid_devices`] call.
+    filters: Vec<Filter>,
+    /// Registered with the backend when listening starts.
+    hid_usages: Vec<HidUsage>,
+    mouse_states: HashMap<DeviceHandle, MouseState>,
+    keyboard_states: HashMap<DeviceHandle, KeyboardState>,
+    gamepad_states: HashMap<DeviceHandle, GamepadState>,
+    /// Where the mappings of gamepads are looked up when they are added.
+    mappings: MappingDatabase,
+}
+
+impl DeviceLists {
+    fn add(&mut self, kind: DeviceKind, handle: DeviceHandle, info: &DeviceInfo) {
+        self.insert(kind, handle, info);
+        self.renumber();
+    }
+
+    /// Adds enumerated devices, numbering them once they are all in.
+    fn add_all<'a>(&mut self, devices: impl IntoIterator<Item = &'a RawDevice>) {
+        for device in devices {
+            self.insert(device.kind, device.handle, &device.info);
+        }
+        self.renumber();
+    }
+
+    /// Adds a device without renumbering the identical ones, see [`renumber`](Self::renumber).
+    fn insert(&mut self, kind: DeviceKind, handle: DeviceHandle, info: &DeviceInfo) {
+        match kind {
+            DeviceKind::Mouse => {
+                insert(&mut self.mice, handle, info);
+                self.mouse_states.entry(handle).or_default();
+            }
+            DeviceKind::Keyboard => {
+                insert(&mut self.keyboards, handle, info);
+                self.keyboard_states.entry(handle).or_default();
+            }
+            DeviceKind::Hid => {
+                insert(&mut self.hid_devices, handle, info);
+                if is_gamepad(info) {
+                    let layout = GamepadLayout::lookup(info.vendor_id, info.product_id);
+                    self.gamepad_states.entry(handle).or_insert_with(|| {
+                        let mut state = GamepadState::new(layout);
+                        state.set_mapping(self.mappings.lookup(info).cloned());
+                        state
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns whether the device was added.
+    fn remove(&mut self, handle: DeviceHandle) -> bool {
+        let count = self.len();
+        self.mouse_states.remove(&handle);
+        self.keyboard_states.remove(&handle);
+        self.gamepad_states.remove(&handle);
+        self.mice.retain(|mouse| mouse.handle != handle);
+        self.keyboards.retain(|keyboard| keyboard.handle != handle);
+        self.hid_devices.retain(|device| device.handle != handle);
+        self.renumber();
+        count != self.len()
+    }
+
+    /// Numbers the identical devices of each kind by their path.
+    fn renumber(&mut self) {
+        renumber(&mut self.mice);
+        renumber(&mut self.keyboards);
+        renumber(&mut self.hid_devices);
+    }
+
+    /// Looks up the mappings of the added gamepads again.
+    fn apply_mappings(&mut self) {
+        for device in &self.hid_devices {
+            if let Some(state) = self.gamepad_states.get_mut(&device.handle) {
+                state.set_mapping(self.mappings.lookup(&device.info).cloned());
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.mice.len() + self.keyboards.len() + self.hid_devices.len()
+    }
+
+    fn id(&self, handle: DeviceHandle) -> Option<DeviceId> {
+        fn find<T: Device>(list: &[T], handle: DeviceHandle) -> Option<&DeviceId> {
+            list.iter()
+                .find(|device| device.get_handle() == handle)
+                .map(T::id)
+        }
+        find(&self.mice, handle)
+            .or_else(|| find(&self.keyboards, handle))
+            .or_else(|| find(&self.hid_devices, handle))
+            .cloned()
+    }
+
+    /// Applies a device change or input and returns the id of the added device the event
+    /// concerns, if any, i.e. whether it should be delivered.
+    ///
+    /// `values` are those of the event if it is a decoded HID report.
+    /// When a keyboard is removed with keys held, their releases are pushed to `released`,
+    /// to be delivered before the removal.
+    fn update(
+        &mut self,
+        event: &Event,
+        values: &[FieldValue],
+        released: &mut Vec<Event>,
+    ) -> Option<DeviceId> {
+        match &event.kind {
+            InputEvent::DeviceAdded { kind, info } => {
+                // the handle may have belonged to a device that went away unnoticed
+                self.remove(event.device);
+                if !self.filters.iter().any(|filter| filter(*kind, info)) {
+                    return None;
+                }
+                self.add(*kind, event.device, info);
+                self.id(event.device)
+            }
+            InputEvent::DeviceRemoved => {
+                if let Some(state) = self.keyboard_states.get_mut(&event.device) {
+                    let keys = state.release_all().into_iter();
+                    released.extend(keys.filter_map(KeyCode::scancode).map(|scancode| Event {
+                        device: event.device,
+                        timestamp: event.timestamp,
+                        kind: InputEvent::Key {
+                            scancode,
+                            state: ButtonState::Released,
+                        },
+                    }));
+                }
+                let id = self.id(event.device);
+                self.remove(event.device);
+                id
+            }
+            kind => {
+                if let Some(state) = self.mouse_states.get_mut(&event.device) {
+                    state.update(event);
+                }
+                if let Some(state) = self.keyboard_states.get_mut(&event.device) {
+                    state.update(kind);
+                }
+                if let Some(state) = self.gamepad_states.get_mut(&event.device) {
+                    state.update(values);
+                }
+                self.id(event.device)
+            }
+        }
+    }
+}
+
+/// Adds a device to `list`, replacing any entry with the same handle.
+fn insert<T: Device>(list: &mut Vec<T>, handle: DeviceHandle, info: &DeviceInfo) {
+    list.retain(|device| device.get_handle() != handle);
+    list.push(T::new(handle, DeviceId::new(T::KIND, info), info.clone()));
+}
+
+/// Numbers the identical devices in `list` by their path, like [`DeviceId::assign`] does
+/// for [`get_devices`], so that a device gets the same id from both.
+fn renumber<T: Device>(list: &mut [T]) {
+    let devices: Vec<_> = list
+        .iter()
+        .map(|device| RawDevice {
+            handle: device.get_handle(),
+            kind: T::KIND,
+            info: device.info().clone(),
+        })
+        .collect();
+    for (device, id) in list.iter_mut().zip(DeviceId::assign(&devices)) {
+        if *device.id() != id {
+            *device = T::new(device.get_handle(), id, device.info().clone());
+        }
+    }
+}
+
+/// Marks a [`Devices`] as listening for as long as the listener thread holds it.
+///
+/// The device lists and states are updated by the listener, so a second one would apply
+/// every event twice.
+struct Listening(Arc<AtomicBool>);
+
+impl Listening {
+    fn start(flag: &Arc<AtomicBool>) -> Result<Self> {
+        match flag.swap(true, Ordering::AcqRel) {
+            true => Err(Error::AlreadyListening),
+            false => Ok(Self(flag.clone())),
+        }
+    }
+}
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
 }
 
 impl Devices {
     pub fn new() -> Self {
+        Self::with_backend(DefaultBackend::default())
+    }
+}
+
+impl Default for Devices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: InputBackend> Devices<B> {
+    pub fn with_backend(backend: B) -> Self {
         Self {
-            mice: vec![],
-            keyboards: vec![],
-            //thread_handle: None,
+            backend: Arc::new(backend),
+            lists: Default::default(),
+            handlers: Default::default(),
+            listening: Default::default(),
         }
     }
 
     /// This starts a thread polling for new events coming from the added devices.
-    /// On Windows, some parent window is required for this, and a handle to such a window can be provided via the hwnd argument.
-    /// Otherwise, this will start a hidden window.
-    pub fn start_listening(&self, hwnd: Option<HWND>) {
-        // a set of devices we want to listen to
-        let device_set: HashSet<*mut c_void> = HashSet::new();
-
-        let hwnd = match hwnd {
-            Some(hwnd) => hwnd,
-            None => {
-                let hinstance = unsafe { GetModuleHandleW(PWSTR::null()).unwrap() };
-
-                let classname_str = format!("RawInput Window");
-                let mut classname = OsStr::new(&classname_str)
-                    .encode_wide()
-                    .chain(Some(0).into_iter())
-                    .collect::<Vec<_>>();
-                let classname = PCWSTR::from_raw(&mut classname[0]);
-
-                let wcex = WNDCLASSEXW {
-                    cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
-                    cbClsExtra: 0,
-                    cbWndExtra: 0,
-                    hInstance: hinstance.into(),
-                    lpfnWndProc: Some(DefWindowProcWSystem),
-                    lpszClassName: classname,
-                    style: WNDCLASS_STYLES::default(),
-                    ..Default::default()
-                };
-
-                let result = unsafe { RegisterClassExW(&wcex) };
-
-                if result == 0 {
-                    panic!("WindowClass Registration failed");
-                }
+    /// On Windows, raw input is delivered to a window owned by the receiving thread,
+    /// so the thread creates its own hidden message window.
+    /// On Linux, each evdev node gets an extra thread reading it, which ends with the listener.
+    ///
+    /// Every decoded event from an added device is sent to the returned channel,
+    /// events from other devices are dropped. The listener never waits for the consumer
+    /// unless `config` asks for [`Overflow::Block`]; the events dropped because the channel was
+    /// full are counted by [`ListenerHandle::dropped_events`].
+    /// The thread runs until the returned handle is stopped or dropped, or reading input fails.
+    ///
+    /// A device connected while listening is added if one of the filters given to
+    /// [`add_all_devices`](Self::add_all_devices) or [`add_devices_where`](Self::add_devices_where)
+    /// accepts it, and a disconnected one is removed. This happens before the
+    /// [`InputEvent::DeviceAdded`] or [`InputEvent::DeviceRemoved`] event is sent.
+    /// Keys still held on a removed keyboard are released first, with [`InputEvent::Key`] events
+    /// carrying the removal's timestamp.
+    ///
+    /// Only one listener runs at a time, as it is the one keeping the device lists and states
+    /// current; this fails with [`Error::AlreadyListening`] until the previous listener thread
+    /// has exited. Dropping or [joining](ListenerHandle::join) its handle waits for that,
+    /// [`stop`](ListenerHandle::stop) and dropping an [`EventStream`] do not.
+    pub fn start_listening(
+        &self,
+        config: ChannelConfig,
+    ) -> Result<(ListenerHandle, EventReceiver)> {
+        let shared = Arc::<polling::Shared>::default();
+        let (mut send, receiver) = channel::channel(config, shared.clone());
+        let listener = self.listen(shared, move |id, event| {
+            send((id, event.timestamp, event.kind))
+        })?;
+        Ok((listener, receiver))
+    }
+
+    /// Like [`start_listening`](Self::start_listening), but sends to a bounded
+    /// [crossbeam channel](crossbeam_channel), which can be used with `select!`.
+    #[cfg(feature = "crossbeam")]
+    pub fn start_listening_crossbeam(
+        &self,
+        config: ChannelConfig,
+    ) -> Result<(ListenerHandle, crossbeam_channel::Receiver<DeviceEvent>)> {
+        let shared = Arc::<polling::Shared>::default();
+        let (mut send, receiver) = channel::crossbeam_channel(config, shared.clone());
+        let listener = self.listen(shared, move |id, event| {
+            send((id, event.timestamp, event.kind))
+        })?;
+        Ok((listener, receiver))
+    }
+
+    /// Like [`start_listening`](Self::start_listening), but returns the events as an async
+    /// [`Stream`](futures_core::Stream). Dropping the stream stops listening.
+    #[cfg(feature = "async")]
+    pub fn stream(&self, config: ChannelConfig) -> Result<EventStream> {
+        let (listener, receiver) = self.start_listening(config)?;
+        Ok(EventStream::new(listener, receiver))
+    }
+
+    /// Like [`start_listening`](Self::start_listening), but hands every event to `callback`
+    /// on the listener thread, which holds up the capture until it returns.
+    pub fn start_listening_with<F>(&self, mut callback: F) -> Result<ListenerHandle>
+    where
+        F: FnMut(Event) + Send + 'static,
+    {
+        self.listen(Default::default(), move |_, event| callback(event))
+    }
 
-                unsafe {
-                    CreateWindowExW(
-                        WINDOW_EX_STYLE::default(),
-                        classname,
-                        classname,
-                        WINDOW_STYLE::default(),
-                        CW_USEDEFAULT,
-                        CW_USEDEFAULT,
-                        CW_USEDEFAULT,
-                        CW_USEDEFAULT,
-                        HWND_MESSAGE,
-                        HMENU::default(),
-                        hinstance,
-                        None,
-                    )
+    fn listen<F>(&self, shared: Arc<polling::Shared>, mut callback: F) -> Result<ListenerHandle>
+    where
+        F: FnMut(DeviceId, Event) + Send + 'static,
+    {
+        let lists = self.lists.clone();
+        let handlers = self.handlers.clone();
+        let backend = self.backend.clone();
+        let hid_usages = lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .hid_usages
+            .clone();
+        let listening = Listening::start(&self.listening)?;
+        let mut decoder = Decoder::default();
+        let mut released = vec![];
+        polling::spawn(self.backend.clone(), hid_usages, shared, move |event| {
+            // released with the callback when the thread exits
+            let _ = &listening;
+            let mut lists = lists.lock().unwrap_or_else(PoisonError::into_inner);
+            // only the reports of gamepads are decoded, to keep their state
+            let values = match lists.gamepad_states.get_mut(&event.device) {
+                Some(state) => {
+                    let values = decoder.decode(&*backend, &event);
+                    if !state.has_descriptor() {
+                        if let Some(descriptor) = decoder.descriptor(event.device) {
+                            state.set_descriptor(descriptor);
+                        }
+                    }
+                    values
                 }
-                .expect("Window creation failed")
-            }
-        };
-        let mut rawinputdevices = vec![];
-
-        let rawdevice = RAWINPUTDEVICE {
-            usUsagePage: Mouse::USAGE_PAGE,
-            usUsage: Mouse::USAGE_ID,
-            dwFlags: Mouse::DW_FLAG,
-            hwndTarget: hwnd,
-        };
-        rawinputdevices.push(rawdevice);
-
-        let rawdevice = RAWINPUTDEVICE {
-            usUsagePage: Keyboard::USAGE_PAGE,
-            usUsage: Keyboard::USAGE_ID,
-            dwFlags: Mouse::DW_FLAG,
-            hwndTarget: hwnd,
-        };
-        rawinputdevices.push(rawdevice);
-
-        unsafe {
-            RegisterRawInputDevices(
-                &rawinputdevices,
-                std::mem::size_of::<RAWINPUTDEVICE>() as u32,
-            )
-        }
-        .expect("Failed to register raw input devices");
-
-        let mut buffer_size = 4096; // why not ...
-        let mut buffer = vec![RAWINPUT::default(); buffer_size as usize];
-        // this should be the callback function in new thread...
-        loop {
-            buffer_size = 4096;
-            let n = unsafe {
-                GetRawInputBuffer(
-                    Some(buffer.as_mut_ptr()),
-                    &mut buffer_size,
-                    std::mem::size_of::<RAWINPUTHEADER>() as u32,
-                )
+                None => vec![],
             };
-            if n as i32 == -1 {
-                panic!("failed to get input buffer: {:?}", unsafe {
-                    GetLastError()
-                });
-            } else if n != 0 {
-                for point in 0..(n as usize) {
-                    unsafe {
-                        println!("got some package, this is point {}", n);
-                    }
+            let id = lists.update(&event, &values, &mut released);
+            drop(lists);
+            if let Some(id) = id {
+                for release in released.drain(..) {
+                    subscription::dispatch(&handlers, &id, &release);
+                    callback(id.clone(), release);
                 }
+                subscription::dispatch(&handlers, &id, &event);
+                callback(id, event)
             }
+        })
+    }
+
+    /// A snapshot of the mice, which changes as devices come and go while listening.
+    pub fn mice(&self) -> Vec<Mouse> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .mice
+            .clone()
+    }
+
+    /// A snapshot of the keyboards, which changes as devices come and go while listening.
+    pub fn keyboards(&self) -> Vec<Keyboard> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keyboards
+            .clone()
+    }
+
+    /// A snapshot of the HID devices, which changes as devices come and go while listening.
+    pub fn hid_devices(&self) -> Vec<HidDevice> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .hid_devices
+            .clone()
+    }
+
+    /// A snapshot of the gamepads among the HID devices.
+    pub fn gamepads(&self) -> Vec<Gamepad> {
+        let lists = self.lists.lock().unwrap_or_else(PoisonError::into_inner);
+        let gamepads = lists
+            .hid_devices
+            .iter()
+            .filter(|device| is_gamepad(&device.info));
+        gamepads
+            .map(|device| Gamepad::new(device.handle, device.id.clone(), device.info.clone()))
+            .collect()
+    }
+
+    /// Reads and parses the report descriptor of a connected device, which lays out the
+    /// reports it sends as [`InputEvent::HidReport`]s.
+    /// On Windows, which doesn't hand out descriptors, it is rebuilt from the device's
+    /// preparsed data with [`ReportDescriptor::from_preparsed`].
+    pub fn report_descriptor(&self, handle: DeviceHandle) -> Result<ReportDescriptor> {
+        self.backend.parse_report_descriptor(handle)
+    }
+
+    /// The cursor of an added mouse, as of the last event the listener delivered.
+    pub fn mouse_state(&self, handle: DeviceHandle) -> Option<MouseState> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .mouse_states
+            .get(&handle)
+            .cloned()
+    }
+
+    /// Changes the state of an added mouse, e.g. to [bound](MouseState::set_bounds) or
+    /// [place](MouseState::set_position) its cursor. Returns whether the mouse was found.
+    ///
+    /// The state is reset when the mouse is removed, including when it reconnects.
+    pub fn modify_mouse_state(
+        &self,
+        handle: DeviceHandle,
+        f: impl FnOnce(&mut MouseState),
+    ) -> bool {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .mouse_states
+            .get_mut(&handle)
+            .map(f)
+            .is_some()
+    }
+
+    /// The keys held on an added keyboard, as of the last event the listener delivered.
+    pub fn keyboard_state(&self, handle: DeviceHandle) -> Option<KeyboardState> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keyboard_states
+            .get(&handle)
+            .cloned()
+    }
+
+    /// The sticks, triggers and buttons of an added gamepad, as of the last report the
+    /// listener delivered. Reports are decoded with the gamepad's
+    /// [report descriptor](Self::report_descriptor), so the state stays at rest where it
+    /// can't be read.
+    pub fn gamepad_state(&self, handle: DeviceHandle) -> Option<GamepadState> {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .gamepad_states
+            .get(&handle)
+            .cloned()
+    }
+
+    /// Changes the state of an added gamepad, e.g. its [deadzone](GamepadState::deadzone).
+    /// Returns whether the gamepad was found.
+    ///
+    /// The state is reset when the gamepad is removed, including when it reconnects.
+    pub fn modify_gamepad_state(
+        &self,
+        handle: DeviceHandle,
+        f: impl FnOnce(&mut GamepadState),
+    ) -> bool {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .gamepad_states
+            .get_mut(&handle)
+            .map(f)
+            .is_some()
+    }
+
+    /// Replaces the mappings gamepads are read with, e.g. by those of a
+    /// `gamecontrollerdb.txt`. Added gamepads get their new mapping right away.
+    pub fn set_mappings(&self, mappings: MappingDatabase) {
+        let mut lists = self.lists.lock().unwrap_or_else(PoisonError::into_inner);
+        lists.mappings = mappings;
+        lists.apply_mappings();
+    }
+
+    /// Adds a mapping, overriding any for the same GUID and platform, see
+    /// [`MappingDatabase::add_mapping`]. Added gamepads get their new mapping right away.
+    pub fn add_mapping(&self, mapping: Mapping) {
+        let mut lists = self.lists.lock().unwrap_or_else(PoisonError::into_inner);
+        lists.mappings.add_mapping(mapping);
+        lists.apply_mappings();
+    }
+
+    /// Adds every connected mouse and keyboard, as well as those connected later.
+    pub fn add_all_devices(&self) -> Result<()> {
+        self.add_devices_where(|_| true)
+    }
+
+    /// Adds the connected mice and keyboards `filter` accepts,
+    /// and keeps it to decide about devices connected later.
+    pub fn add_devices_where<F>(&self, filter: F) -> Result<()>
+    where
+        F: Fn(&DeviceInfo) -> bool + Send + 'static,
+    {
+        self.add_filtered(move |kind, info| kind != DeviceKind::Hid && filter(info))
+    }
+
+    /// Adds the connected HID devices with `usage`, as well as those connected later,
+    /// and has their reports delivered as [`InputEvent::HidReport`]s.
+    ///
+    /// The usage is registered when listening starts, so this has to be called before.
+    /// Reading HID reports is only supported on Windows.
+    pub fn add_hid_devices(&self, usage: HidUsage) -> Result<()> {
+        self.add_filtered(move |kind, info| {
+            kind == DeviceKind::Hid && info.details == DeviceDetails::Hid(usage)
+        })?;
+        let mut lists = self.lists.lock().unwrap_or_else(PoisonError::into_inner);
+        if !lists.hid_usages.contains(&usage) {
+            lists.hid_usages.push(usage);
         }
+        Ok(())
+    }
+
+    /// Adds the connected joysticks and gamepads, as well as those connected later,
+    /// see [`add_hid_devices`](Self::add_hid_devices).
+    pub fn add_gamepads(&self) -> Result<()> {
+        self.add_hid_devices(HidUsage::JOYSTICK)?;
+        self.add_hid_devices(HidUsage::GAMEPAD)
+    }
+
+    fn add_filtered<F>(&self, filter: F) -> Result<()>
+    where
+        F: Fn(DeviceKind, &DeviceInfo) -> bool + Send + 'static,
+    {
+        let mut devices = self.backend.enumerate()?;
+        devices.retain(|device| filter(device.kind, &device.info));
+        let mut lists = self.lists.lock().unwrap_or_else(PoisonError::into_inner);
+        lists.add_all(&devices);
+        lists.filters.push(Box::new(filter));
+        Ok(())
+    }
+
+    /// Adds one device, e.g. a [`Mouse`] or [`Keyboard`] from [`get_devices`].
+    /// The reports of a [`HidDevice`] are only delivered if its usage is registered with
+    /// [`add_hid_devices`](Self::add_hid_devices).
+    pub fn add_device(&self, device: impl Into<RawDevice>) {
+        let device = device.into();
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add(device.kind, device.handle, &device.info);
+    }
+
+    /// Removes a device, so its events are no longer delivered.
+    /// If a filter accepts it, it is added again when it reconnects.
+    ///
+    /// Returns whether the device had been added.
+    pub fn remove_device(&self, handle: DeviceHandle) -> bool {
+        self.lists
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(handle)
+    }
+
+    /// Registers a handler for every event delivered while listening, or only for those of
+    /// `device`. It is called on the listener thread with the id of the device,
+    /// right before the event is delivered, for as long as it stays registered.
+    ///
+    /// A panic in a handler is caught, so it neither ends the listener nor keeps the other
+    /// handlers from being called.
+    pub fn on_event<F>(&self, device: Option<DeviceId>, callback: F) -> Subscription
+    where
+        F: FnMut(&DeviceId, &Event) + Send + 'static,
+    {
+        self.subscribe(device, |_| true, callback)
+    }
+
+    /// Like [`on_event`](Self::on_event), for [`InputEvent::MouseMotion`] and
+    /// [`InputEvent::MousePosition`] only.
+    pub fn on_mouse_move<F>(&self, device: Option<DeviceId>, callback: F) -> Subscription
+    where
+        F: FnMut(&DeviceId, &Event) + Send + 'static,
+    {
+        self.subscribe(
+            device,
+            |event| {
+                matches!(
+                    event,
+                    InputEvent::MouseMotion { .. } | InputEvent::MousePosition { .. }
+                )
+            },
+            callback,
+        )
+    }
+
+    /// Like [`on_event`](Self::on_event), for [`InputEvent::Key`] only.
+    pub fn on_key<F>(&self, device: Option<DeviceId>, callback: F) -> Subscription
+    where
+        F: FnMut(&DeviceId, &Event) + Send + 'static,
+    {
+        self.subscribe(
+            device,
+            |event| matches!(event, InputEvent::Key { .. }),
+            callback,
+        )
+    }
+
+    /// Like [`on_event`](Self::on_event), for [`InputEvent::DeviceAdded`] and
+    /// [`InputEvent::DeviceRemoved`] only.
+    pub fn on_device_change<F>(&self, device: Option<DeviceId>, callback: F) -> Subscription
+    where
+        F: FnMut(&DeviceId, &Event) + Send + 'static,
+    {
+        self.subscribe(
+            device,
+            |event| {
+                matches!(
+                    event,
+                    InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved
+                )
+            },
+            callback,
+        )
+    }
+
+    /// Registers a handler for the input reports of HID devices, decoded with
+    /// [`ReportDescriptor::decode_input`]. It is only called for reports with any values.
+    ///
+    /// The descriptor of a device is read when its first report arrives. Reports of devices
+    /// whose descriptor can't be read or parsed are skipped, see
+    /// [`report_descriptor`](Self::report_descriptor).
+    pub fn on_hid_input<F>(&self, device: Option<DeviceId>, mut callback: F) -> Subscription
+    where
+        F: FnMut(&DeviceId, &[FieldValue]) + Send + 'static,
+    {
+        let backend = self.backend.clone();
+        let mut decoder = Decoder::default();
+        self.subscribe(
+            device,
+            |event| {
+                matches!(
+                    event,
+                    InputEvent::HidReport { .. } | InputEvent::DeviceRemoved
+                )
+            },
+            move |id, event| {
+                let values = decoder.decode(&*backend, event);
+                if !values.is_empty() {
+                    callback(id, &values);
+                }
+            },
+        )
+    }
+
+    /// Unregisters a handler; a listener calling it finishes that call.
+    /// Returns whether the handler was registered.
+    pub fn unsubscribe(&self, subscription: Subscription) -> bool {
+        self.handlers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(subscription)
     }
 
-    pub fn add_all_devices(&mut self) {
-        //self.keyboards.extend(get_devices::<Keyboard>());
-        //self.mice.extend(get_devices::<Mouse>())
+    fn subscribe<F>(
+        &self,
+        device: Option<DeviceId>,
+        accepts: fn(&InputEvent) -> bool,
+        callback: F,
+    ) -> Subscription
+    where
+        F: FnMut(&DeviceId, &Event) + Send + 'static,
+    {
+        self.handlers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .add(device, accepts, Box::new(callback))
     }
 }
 
 pub trait Device {
-    const DW_TYPE_MASK: u32;
-    const USAGE_PAGE: u16;
-    const USAGE_ID: u16;
-    const DW_FLAG: RAWINPUTDEVICE_FLAGS;
-    fn get_handle(&self) -> HANDLE;
+    const KIND: DeviceKind;
 
-    fn new(product_name: String, handle: HANDLE) -> Self;
+    /// Whether an enumerated device is one of these, by default whether it is of
+    /// [`KIND`](Self::KIND).
+    fn matches(device: &RawDevice) -> bool {
+        device.kind == Self::KIND
+    }
+
+    fn get_handle(&self) -> DeviceHandle;
+
+    fn id(&self) -> &DeviceId;
+
+    fn info(&self) -> &DeviceInfo;
+
+    fn new(handle: DeviceHandle, id: DeviceId, info: DeviceInfo) -> Self;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Mouse {
-    pub product_name: String,
-    pub handle: HANDLE,
+    pub handle: DeviceHandle,
+    pub id: DeviceId,
+    pub info: DeviceInfo,
 }
 
 impl Device for Mouse {
-    // MS provided
-    const DW_TYPE_MASK: u32 = 0;
-    const USAGE_ID: u16 = HID_USAGE_GENERIC_MOUSE;
-    const USAGE_PAGE: u16 = HID_USAGE_PAGE_GENERIC;
-    const DW_FLAG: RAWINPUTDEVICE_FLAGS = RIDEV_INPUTSINK;
+    const KIND: DeviceKind = DeviceKind::Mouse;
+
+    fn get_handle(&self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn id(&self) -> &DeviceId {
+        &self.id
+    }
 
-    fn get_handle(&self) -> HANDLE {
-        todo!()
+    fn info(&self) -> &DeviceInfo {
+        &self.info
     }
 
-    fn new(product_name: String, handle: HANDLE) -> Self {
-        Mouse {
-            product_name: product_name,
-            handle: handle,
+    fn new(handle: DeviceHandle, id: DeviceId, info: DeviceInfo) -> Self {
+        Mouse { handle, id, info }
+    }
+}
+
+impl From<&Mouse> for RawDevice {
+    fn from(mouse: &Mouse) -> Self {
+        RawDevice {
+            handle: mouse.handle,
+            kind: DeviceKind::Mouse,
+            info: mouse.info.clone(),
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Keyboard {
-    pub product_name: String,
-    pub handle: HANDLE,
+    pub handle: DeviceHandle,
+    pub id: DeviceId,
+    pub info: DeviceInfo,
 }
 
 impl Device for Keyboard {
-    // MS provided
-    const DW_TYPE_MASK: u32 = 1;
-    const USAGE_ID: u16 = HID_USAGE_GENERIC_KEYBOARD;
-    const USAGE_PAGE: u16 = HID_USAGE_PAGE_GENERIC;
-    const DW_FLAG: RAWINPUTDEVICE_FLAGS = RIDEV_INPUTSINK;
+    const KIND: DeviceKind = DeviceKind::Keyboard;
 
-    fn get_handle(&self) -> HANDLE {
-        todo!()
+    fn get_handle(&self) -> DeviceHandle {
+        self.handle
     }
 
-    fn new(product_name: String, handle: HANDLE) -> Self {
-        Keyboard {
-            product_name,
-            handle,
+    fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    fn new(handle: DeviceHandle, id: DeviceId, info: DeviceInfo) -> Self {
+        Keyboard { handle, id, info }
+    }
+}
+
+impl From<&Keyboard> for RawDevice {
+    fn from(keyboard: &Keyboard) -> Self {
+        RawDevice {
+            handle: keyboard.handle,
+            kind: DeviceKind::Keyboard,
+            info: keyboard.info.clone(),
         }
     }
 }
 
-pub fn get_devices<T>() -> Vec<T>
-where
-    T: Device,
-{
-    let type_mask = T::DW_TYPE_MASK;
-
-    let mut devices_vec = vec![];
-    let mut num_devices = 0;
-    let device_list_size = std::mem::size_of::<RAWINPUTDEVICELIST>() as u32;
-
-    // poll the number of devices
-    // SAFETY: We are not providing a buffer, just polling the required size of the future buffer
-    let mut result = unsafe { GetRawInputDeviceList(None, &mut num_devices, device_list_size) };
-    if result == u32::MAX {
-        panic!("Failed to Get Raw Device List!");
-    }
-
-    // make space for raw input device list
-    // RAWINPUTDEVICELIST is not actually a list, just an entry in the list...
-    let mut buffer: Vec<RAWINPUTDEVICELIST> = vec![];
-    for _ in 0..num_devices {
-        buffer.push(RAWINPUTDEVICELIST::default());
-    }
-
-    // get devices
-    // SAFETY: Required buffer size has been polled, could this write out of bounds
-    // if the amount of connected devices changes?
-    unsafe {
-        result = GetRawInputDeviceList(Some(&mut buffer[0]), &mut num_devices, device_list_size);
-    };
-    if result == u32::MAX {
-        panic!("Failed to Get Raw Device List!");
-    }
-    // 0 = mouse, 1 = keyboard, 2 = other HID
-    // get only keyboards
-    let devices = buffer.iter().filter(|device| device.dwType.0 == type_mask);
-
-    for device in devices {
-        // get size of device path string
-
-        let mut size: u32 = 0;
-        // SAFETY: We are first polling the required buffer size
-        let result =
-            unsafe { GetRawInputDeviceInfoW(device.hDevice, RIDI_DEVICENAME, None, &mut size) };
-        // if failed to get device info
-        if result == u32::MAX {
-            continue;
+/// A HID device other than a mouse or keyboard, such as a gamepad.
+#[derive(Debug, Clone)]
+pub struct HidDevice {
+    pub handle: DeviceHandle,
+    pub id: DeviceId,
+    pub info: DeviceInfo,
+}
+
+impl HidDevice {
+    /// The usage page and usage of the device's top-level collection, if known.
+    pub fn usage(&self) -> Option<HidUsage> {
+        match self.info.details {
+            DeviceDetails::Hid(usage) => Some(usage),
+            _ => None,
         }
-        // allocate buffer for path string
-        let mut path_buffer = vec![];
-        for _ in 0..size {
-            path_buffer.push(0u16);
+    }
+}
+
+impl Device for HidDevice {
+    const KIND: DeviceKind = DeviceKind::Hid;
+
+    fn get_handle(&self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    fn new(handle: DeviceHandle, id: DeviceId, info: DeviceInfo) -> Self {
+        HidDevice { handle, id, info }
+    }
+}
+
+impl From<&HidDevice> for RawDevice {
+    fn from(device: &HidDevice) -> Self {
+        RawDevice {
+            handle: device.handle,
+            kind: DeviceKind::Hid,
+            info: device.info.clone(),
         }
-        // get device path string
-        // SAFETY: Buffer has been allocated accordingly
-        unsafe {
-            GetRawInputDeviceInfoW(
-                device.hDevice,
-                RIDI_DEVICENAME,
-                Some(std::mem::transmute(path_buffer.as_mut_ptr())),
-                &mut size,
-            )
-        };
-
-        // cast the path string to a windows string
-        let pathstr = PWSTR::from_raw(&mut path_buffer[0]);
-
-        // create a file handle on the raw input device
-        let handle = unsafe {
-            windows::Win32::Storage::FileSystem::CreateFileW(
-                pathstr,
-                0,
-                FILE_SHARE_READ,
-                None,
-                OPEN_EXISTING,
-                FILE_ATTRIBUTE_READONLY,
-                None,
-            )
-        };
-        match handle {
-            Ok(handle) => {
-                // product string buffer must be allocated beforehand
-                const SIZE: usize = 1024;
-                let mut buffer: [u16; SIZE] = [0u16; SIZE];
-                // get product string (typically a name)
-                // SAFETY: Buffer size is handled on the OS side
-                // if the string does not fit, this will fail.
-                let result = unsafe {
-                    HidD_GetProductString(
-                        handle,
-                        std::mem::transmute(buffer.as_mut_ptr()),
-                        SIZE as u32,
-                    )
-                };
-                if result == BOOLEAN(0) {
-                    continue;
-                }
-                // OsString :D
-                let string = OsString::from_wide(&buffer).into_string().unwrap();
-                let string = string.trim_end_matches("\0");
-                devices_vec.push(T::new(string.to_string(), device.hDevice.0));
-            }
-            Err(_) => {}
+    }
+}
+
+/// A joystick or gamepad, i.e. a HID device with the usage [`HidUsage::JOYSTICK`] or
+/// [`HidUsage::GAMEPAD`].
+#[derive(Debug, Clone)]
+pub struct Gamepad {
+    pub handle: DeviceHandle,
+    pub id: DeviceId,
+    pub info: DeviceInfo,
+}
+
+impl Gamepad {
+    /// The layout of the model, see [`GamepadLayout::lookup`].
+    pub fn layout(&self) -> &'static GamepadLayout {
+        GamepadLayout::lookup(self.info.vendor_id, self.info.product_id)
+    }
+
+    /// The GUID SDL would give the gamepad, see [`Guid::from_info`].
+    pub fn guid(&self) -> Guid {
+        Guid::from_info(&self.info)
+    }
+
+    /// The mapping of the model in `mappings`, see [`MappingDatabase::lookup`].
+    pub fn mapping<'a>(&self, mappings: &'a MappingDatabase) -> Option<&'a Mapping> {
+        mappings.lookup(&self.info)
+    }
+}
+
+impl Device for Gamepad {
+    const KIND: DeviceKind = DeviceKind::Hid;
+
+    fn matches(device: &RawDevice) -> bool {
+        device.kind == DeviceKind::Hid && is_gamepad(&device.info)
+    }
+
+    fn get_handle(&self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    fn info(&self) -> &DeviceInfo {
+        &self.info
+    }
+
+    fn new(handle: DeviceHandle, id: DeviceId, info: DeviceInfo) -> Self {
+        Gamepad { handle, id, info }
+    }
+}
+
+impl From<&Gamepad> for RawDevice {
+    fn from(gamepad: &Gamepad) -> Self {
+        RawDevice {
+            handle: gamepad.handle,
+            kind: DeviceKind::Hid,
+            info: gamepad.info.clone(),
         }
     }
-    devices_vec
 }
 
-#[allow(non_snake_case)]
-// spicy...
-unsafe extern "system" fn DefWindowProcWSystem<P0, P1, P2>(
-    hwnd: P0,
-    msg: u32,
-    wparam: P1,
-    lparam: P2,
-) -> Foundation::LRESULT
+fn is_gamepad(info: &DeviceInfo) -> bool {
+    matches!(
+        info.details,
+        DeviceDetails::Hid(HidUsage::JOYSTICK | HidUsage::GAMEPAD)
+    )
+}
+
+/// Lists the connected devices of type `T` using the platform's native backend.
+pub fn get_devices<T>() -> Result<Vec<T>>
 where
-    P0: windows_core::Param<HWND>,
-    P1: windows_core::Param<Foundation::WPARAM>,
-    P2: windows_core::Param<Foundation::LPARAM>,
+    T: Device,
+{
+    get_devices_from(&DefaultBackend::default())
+}
+
+/// Lists the connected devices of type `T` known to `backend`.
+pub fn get_devices_from<T, B>(backend: &B) -> Result<Vec<T>>
+where
+    T: Device,
+    B: InputBackend,
 {
-    DefWindowProcW(hwnd, msg, wparam, lparam)
+    let devices = backend.enumerate()?;
+    let ids = DeviceId::assign(&devices);
+    Ok(devices
+        .into_iter()
+        .zip(ids)
+        .filter(|(device, _)| T::matches(device))
+        .map(|(device, id)| T::new(device.handle, id, device.info))
+        .collect())
 }
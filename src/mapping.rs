@@ -0,0 +1,495 @@
+//! Controller mappings in the format of SDL's `gamecontrollerdb.txt`.
+//!
+//! Generic gamepads number their buttons and axes in whatever order their maker chose.
+//! A [`Mapping`] tells which of them is the A button, the left stick and so on, for the
+//! model its [`Guid`] names. A [`MappingDatabase`] holds the mappings of many models, such as
+//! the community-maintained database SDL uses, and finds the one for a device by its vendor
+//! and product ID.
+//!
+//! A mapping line is the GUID, the name of the model and the bindings, separated by commas:
+//!
+//! ```text
+//! 030000004c050000c405000000000000,PS4 Controller,a:b1,b:b2,x:b0,y:b3,leftx:a0,lefty:a1,
+//! lefttrigger:a3,righttrigger:a4,dpup:h0.1,platform:Windows,
+//! ```
+//!
+//! Like in SDL, `b1` is the button with index 1, `a0` the axis with index 0 and `h0.1` the
+//! up direction of the first hat switch. See [`Source`] for how the indices relate to the
+//! HID usages of the device.
+
+use std::{fmt, str::FromStr};
+
+use crate::DeviceInfo;
+
+/// The buttons of the standard gamepad, with SDL's names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Button {
+    /// The bottom face button.
+    A,
+    /// The right face button.
+    B,
+    /// The left face button.
+    X,
+    /// The top face button.
+    Y,
+    Back,
+    Guide,
+    Start,
+    LeftStick,
+    RightStick,
+    LeftShoulder,
+    RightShoulder,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    /// E.g. the share or capture button.
+    Misc1,
+    Paddle1,
+    Paddle2,
+    Paddle3,
+    Paddle4,
+    Touchpad,
+}
+
+/// The axes of the standard gamepad, with SDL's names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl Button {
+    pub const ALL: [Button; 21] = [
+        Button::A,
+        Button::B,
+        Button::X,
+        Button::Y,
+        Button::Back,
+        Button::Guide,
+        Button::Start,
+        Button::LeftStick,
+        Button::RightStick,
+        Button::LeftShoulder,
+        Button::RightShoulder,
+        Button::DpadUp,
+        Button::DpadDown,
+        Button::DpadLeft,
+        Button::DpadRight,
+        Button::Misc1,
+        Button::Paddle1,
+        Button::Paddle2,
+        Button::Paddle3,
+        Button::Paddle4,
+        Button::Touchpad,
+    ];
+
+    /// The name in mapping strings, e.g. `leftshoulder`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Button::A => "a",
+            Button::B => "b",
+            Button::X => "x",
+            Button::Y => "y",
+            Button::Back => "back",
+            Button::Guide => "guide",
+            Button::Start => "start",
+            Button::LeftStick => "leftstick",
+            Button::RightStick => "rightstick",
+            Button::LeftShoulder => "leftshoulder",
+            Button::RightShoulder => "rightshoulder",
+            Button::DpadUp => "dpup",
+            Button::DpadDown => "dpdown",
+            Button::DpadLeft => "dpleft",
+            Button::DpadRight => "dpright",
+            Button::Misc1 => "misc1",
+            Button::Paddle1 => "paddle1",
+            Button::Paddle2 => "paddle2",
+            Button::Paddle3 => "paddle3",
+            Button::Paddle4 => "paddle4",
+            Button::Touchpad => "touchpad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|button| button.name() == name)
+    }
+}
+
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::LeftX,
+        Axis::LeftY,
+        Axis::RightX,
+        Axis::RightY,
+        Axis::LeftTrigger,
+        Axis::RightTrigger,
+    ];
+
+    /// The name in mapping strings, e.g. `righttrigger`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::LeftX => "leftx",
+            Axis::LeftY => "lefty",
+            Axis::RightX => "rightx",
+            Axis::RightY => "righty",
+            Axis::LeftTrigger => "lefttrigger",
+            Axis::RightTrigger => "righttrigger",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|axis| axis.name() == name)
+    }
+
+    /// Whether the axis is a trigger, which goes from 0 to 1 rather than from -1 to 1.
+    pub fn is_trigger(self) -> bool {
+        matches!(self, Axis::LeftTrigger | Axis::RightTrigger)
+    }
+}
+
+/// Half of an axis, written `+` or `-` in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Half {
+    Positive,
+    Negative,
+}
+
+/// What a binding sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Button(Button),
+    /// An axis, or only one half of it, e.g. `-leftx` for a d-pad driving the stick.
+    Axis {
+        axis: Axis,
+        half: Option<Half>,
+    },
+}
+
+/// The input of the device a binding reads.
+///
+/// Indices count from 0. For HID gamepads, button `n` is the button usage `n + 1`, axis `n`
+/// is the `n`-th of the generic desktop axes (X, Y, Z, Rx, Ry, Rz, slider, dial and wheel)
+/// the report descriptor declares, in that order, and hat 0 is its hat switch, as with SDL's
+/// raw input and DirectInput drivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// `b<index>`
+    Button(u8),
+    /// `a<index>`, only half of it with a `+` or `-` in front and inverted with a `~` after.
+    Axis {
+        index: u8,
+        half: Option<Half>,
+        inverted: bool,
+    },
+    /// `h<index>.<mask>`: the directions of the hat switch in `mask`, 1 up, 2 right, 4 down and
+    /// 8 left.
+    Hat { index: u8, mask: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub target: Target,
+    pub source: Source,
+}
+
+/// SDL's 16-byte joystick GUID, written as 32 hex digits.
+///
+/// Current SDL versions put the bus type, vendor ID, product ID and version into it as
+/// little-endian `u16`s at bytes 0, 4, 8 and 12. Older mappings for Windows use the
+/// DirectInput product GUID, with the vendor and product ID at bytes 0 and 2 followed by
+/// `PIDVID` at the end. Other GUIDs, e.g. those made up from the product name, don't name a
+/// vendor and product at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// The GUID SDL gives a USB device, which is what most mappings are made for.
+    pub fn from_info(info: &DeviceInfo) -> Self {
+        const USB: u16 = 0x03;
+        let mut guid = [0; 16];
+        guid[0..2].copy_from_slice(&USB.to_le_bytes());
+        guid[4..6].copy_from_slice(&info.vendor_id.to_le_bytes());
+        guid[8..10].copy_from_slice(&info.product_id.to_le_bytes());
+        guid[12..14].copy_from_slice(&info.version.to_le_bytes());
+        Self(guid)
+    }
+
+    pub fn vendor_id(&self) -> Option<u16> {
+        self.ids().map(|(vendor, _, _)| vendor)
+    }
+
+    pub fn product_id(&self) -> Option<u16> {
+        self.ids().map(|(_, product, _)| product)
+    }
+
+    /// The product version, if the GUID has one.
+    pub fn version(&self) -> Option<u16> {
+        self.ids().and_then(|(_, _, version)| version)
+    }
+
+    fn ids(&self) -> Option<(u16, u16, Option<u16>)> {
+        let g = &self.0;
+        let u16_at = |i: usize| u16::from_le_bytes([g[i], g[i + 1]]);
+        if &g[10..] == b"PIDVID" {
+            Some((u16_at(0), u16_at(2), None))
+        } else if u16_at(0) != 0 && u16_at(6) == 0 && u16_at(10) == 0 {
+            let version = u16_at(12);
+            Some((u16_at(4), u16_at(8), (version != 0).then_some(version)))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl FromStr for Guid {
+    type Err = ParseMappingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 32 || !s.is_ascii() {
+            return Err(ParseMappingError);
+        }
+        let mut guid = [0; 16];
+        for (i, byte) in guid.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| ParseMappingError)?;
+        }
+        Ok(Self(guid))
+    }
+}
+
+/// The bindings of one gamepad model.
+///
+/// It parses from and prints as a line of `gamecontrollerdb.txt`. Fields SDL uses for
+/// something else, such as `crc` or `hint`, and buttons and axes this crate does not know
+/// are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mapping {
+    pub guid: Guid,
+    pub name: String,
+    /// The platform the mapping is for, e.g. `Windows` or `Linux`, or `None` for any.
+    pub platform: Option<String>,
+    pub bindings: Vec<Binding>,
+}
+
+impl Mapping {
+    /// The binding of a button, if it has one.
+    pub fn button(&self, button: Button) -> Option<Source> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.target == Target::Button(button))
+            .map(|binding| binding.source)
+    }
+
+    /// The bindings of an axis, including those of its halves.
+    pub fn axis(&self, axis: Axis) -> impl Iterator<Item = (Option<Half>, Source)> + '_ {
+        self.bindings
+            .iter()
+            .filter_map(move |binding| match binding.target {
+                Target::Axis { axis: a, half } if a == axis => Some((half, binding.source)),
+                _ => None,
+            })
+    }
+
+    /// Whether the mapping is for the platform this crate was built for.
+    fn is_for_this_platform(&self) -> bool {
+        self.platform
+            .as_deref()
+            .is_none_or(|platform| Some(platform) == PLATFORM)
+    }
+}
+
+/// The name SDL gives the platform in mappings.
+const PLATFORM: Option<&str> = if cfg!(windows) {
+    Some("Windows")
+} else if cfg!(target_os = "linux") {
+    Some("Linux")
+} else if cfg!(target_os = "macos") {
+    Some("Mac OS X")
+} else {
+    None
+};
+
+/// Returned when a string is not a mapping line or GUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseMappingError;
+
+impl fmt::Display for ParseMappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid controller mapping")
+    }
+}
+
+impl std::error::Error for ParseMappingError {}
+
+impl FromStr for Mapping {
+    type Err = ParseMappingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.trim().split(',');
+        let guid = fields.next().ok_or(ParseMappingError)?.parse()?;
+        let name = fields.next().ok_or(ParseMappingError)?.to_string();
+        let mut mapping = Mapping {
+            guid,
+            name,
+            platform: None,
+            bindings: vec![],
+        };
+        for field in fields.filter(|field| !field.is_empty()) {
+            let (key, value) = field.split_once(':').ok_or(ParseMappingError)?;
+            if key == "platform" {
+                mapping.platform = Some(value.to_string());
+                continue;
+            }
+            if let Some(target) = parse_target(key) {
+                let source = parse_source(value).ok_or(ParseMappingError)?;
+                mapping.bindings.push(Binding { target, source });
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+fn parse_half(s: &str) -> (Option<Half>, &str) {
+    if let Some(s) = s.strip_prefix('+') {
+        (Some(Half::Positive), s)
+    } else if let Some(s) = s.strip_prefix('-') {
+        (Some(Half::Negative), s)
+    } else {
+        (None, s)
+    }
+}
+
+fn parse_target(key: &str) -> Option<Target> {
+    let (half, name) = parse_half(key);
+    match (Button::from_name(name), Axis::from_name(name)) {
+        (Some(button), _) if half.is_none() => Some(Target::Button(button)),
+        (_, Some(axis)) => Some(Target::Axis { axis, half }),
+        _ => None,
+    }
+}
+
+fn parse_source(value: &str) -> Option<Source> {
+    let (half, value) = parse_half(value);
+    if let Some(index) = value.strip_prefix('b').filter(|_| half.is_none()) {
+        return Some(Source::Button(index.parse().ok()?));
+    }
+    if let Some(hat) = value.strip_prefix('h').filter(|_| half.is_none()) {
+        let (index, mask) = hat.split_once('.')?;
+        return Some(Source::Hat {
+            index: index.parse().ok()?,
+            mask: mask.parse().ok()?,
+        });
+    }
+    let index = value.strip_prefix('a')?;
+    let (index, inverted) = match index.strip_suffix('~') {
+        Some(index) => (index, true),
+        None => (index, false),
+    };
+    Some(Source::Axis {
+        index: index.parse().ok()?,
+        half,
+        inverted,
+    })
+}
+
+fn half_prefix(half: Option<Half>) -> &'static str {
+    match half {
+        Some(Half::Positive) => "+",
+        Some(Half::Negative) => "-",
+        None => "",
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},", self.guid, self.name)?;
+        for binding in &self.bindings {
+            match binding.target {
+                Target::Button(button) => write!(f, "{}:", button.name())?,
+                Target::Axis { axis, half } => write!(f, "{}{}:", half_prefix(half), axis.name())?,
+            }
+            match binding.source {
+                Source::Button(index) => write!(f, "b{},", index)?,
+                Source::Axis {
+                    index,
+                    half,
+                    inverted,
+                } => {
+                    let inverted = if inverted { "~" } else { "" };
+                    write!(f, "{}a{}{},", half_prefix(half), index, inverted)?
+                }
+                Source::Hat { index, mask } => write!(f, "h{}.{},", index, mask)?,
+            }
+        }
+        if let Some(platform) = &self.platform {
+            write!(f, "platform:{},", platform)?;
+        }
+        Ok(())
+    }
+}
+
+/// A collection of mappings, looked up by the vendor and product ID of a device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MappingDatabase {
+    mappings: Vec<Mapping>,
+}
+
+impl MappingDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the mappings in the contents of a `gamecontrollerdb.txt`, one per line, and
+    /// returns how many there were. Like in SDL, empty lines, `#` comments and lines that
+    /// don't parse are skipped, so that one odd entry doesn't keep the others from loading.
+    pub fn add_mappings(&mut self, text: &str) -> usize {
+        let lines = text.lines().map(str::trim);
+        let lines = lines.filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let mappings: Vec<_> = lines.filter_map(|line| line.parse().ok()).collect();
+        let count = mappings.len();
+        mappings
+            .into_iter()
+            .for_each(|mapping| self.add_mapping(mapping));
+        count
+    }
+
+    /// Adds a mapping, replacing the one with the same GUID and platform, so that mappings
+    /// added later override those loaded before.
+    pub fn add_mapping(&mut self, mapping: Mapping) {
+        self.mappings
+            .retain(|m| (m.guid, &m.platform) != (mapping.guid, &mapping.platform));
+        self.mappings.push(mapping);
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// The mapping for a device on the platform this crate was built for.
+    ///
+    /// Mappings for the exact version of the device win over those for any version, which
+    /// win over those for another version. Among equals, the one added last wins.
+    pub fn lookup(&self, info: &DeviceInfo) -> Option<&Mapping> {
+        let candidates = self.mappings.iter().rev().filter(|mapping| {
+            mapping.is_for_this_platform()
+                && mapping.guid.vendor_id() == Some(info.vendor_id)
+                && mapping.guid.product_id() == Some(info.product_id)
+        });
+        // min_by_key returns the first of equals, i.e. the one added last
+        candidates.min_by_key(|mapping| match mapping.guid.version() {
+            Some(version) if version == info.version => 0,
+            None => 1,
+            Some(_) => 2,
+        })
+    }
+}
@@ -1 +1,147 @@
-// NAME TBD
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    backend::{Capture, InputBackend},
+    Error, Event, HidUsage, Result,
+};
+
+/// How long the listener thread waits for input before it rechecks the stop flag.
+pub(crate) const POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Handle to a running listener thread, returned by
+/// [`Devices::start_listening`](crate::Devices::start_listening) and
+/// [`Devices::start_listening_with`](crate::Devices::start_listening_with).
+///
+/// Dropping the handle stops the thread and waits for it to clean up.
+pub struct ListenerHandle {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<Result<()>>>,
+}
+
+impl ListenerHandle {
+    /// Asks the listener thread to stop, without waiting for it.
+    pub fn stop(&self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// How many events were dropped because the channel they were sent to was full,
+    /// see [`Overflow`](crate::Overflow).
+    pub fn dropped_events(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns true once the listener thread has exited.
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    /// Waits for the listener thread to exit, which it does after [`stop`](Self::stop)
+    /// or when reading input fails.
+    ///
+    /// Returns the error that ended the capture, if any.
+    /// A panic in the callback is propagated.
+    pub fn join(mut self) -> Result<()> {
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ListenerHandle {
+    /// Asks the listener thread to stop and lets it finish on its own,
+    /// for where waiting is not an option.
+    #[cfg(feature = "async")]
+    pub(crate) fn detach(mut self) {
+        self.stop();
+        self.thread = None;
+    }
+}
+
+impl Drop for ListenerHandle {
+    fn drop(&mut self) {
+        self.stop();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// State shared between a [`ListenerHandle`] and its thread.
+#[derive(Default)]
+pub(crate) struct Shared {
+    pub(crate) stop: AtomicBool,
+    pub(crate) dropped: AtomicU64,
+}
+
+/// Starts the listener thread and waits until its capture is up, so that failing to start
+/// is reported here rather than from [`ListenerHandle::join`].
+pub(crate) fn spawn<B, F>(
+    backend: Arc<B>,
+    hid_usages: Vec<HidUsage>,
+    shared: Arc<Shared>,
+    callback: F,
+) -> Result<ListenerHandle>
+where
+    B: InputBackend,
+    F: FnMut(Event) + Send + 'static,
+{
+    let (started_sender, started) = mpsc::sync_channel(1);
+    let thread = {
+        let shared = shared.clone();
+        thread::Builder::new()
+            .name("input listener".to_string())
+            .spawn(move || {
+                let capture = match backend.start_capture(&hid_usages) {
+                    Ok(capture) => capture,
+                    Err(err) => {
+                        let _ = started_sender.send(Err(err));
+                        return Ok(());
+                    }
+                };
+                let _ = started_sender.send(Ok(()));
+                listen(capture, &shared.stop, callback)
+            })
+            .map_err(|err| Error::ThreadSpawn(err.raw_os_error().unwrap_or(0)))?
+    };
+    let handle = ListenerHandle {
+        shared,
+        thread: Some(thread),
+    };
+    match started.recv() {
+        Ok(Ok(())) => Ok(handle),
+        Ok(Err(err)) => Err(err),
+        // the sender is only dropped unsent if starting the capture panicked,
+        // which joining propagates
+        Err(_) => handle.join().map(|_| unreachable!()),
+    }
+}
+
+/// The body of the listener thread.
+/// The capture is started on this thread because some backends (raw input) deliver input
+/// to the thread that set it up.
+fn listen<C, F>(mut capture: C, stop: &AtomicBool, mut callback: F) -> Result<()>
+where
+    C: Capture,
+    F: FnMut(Event),
+{
+    let mut events = vec![];
+    while !stop.load(Ordering::Relaxed) {
+        capture.poll_events(POLL_TIMEOUT, &mut events)?;
+        for event in events.drain(..) {
+            callback(event);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,408 @@
+//! Recording input sessions to a compact binary log, and reading them back.
+//!
+//! A log starts with a header: the magic bytes `WXIN`, a little-endian `u16` format version
+//! and the list of devices known when recording started, each with its whole [`DeviceInfo`].
+//! Logs of any other version are rejected.
+//! It is followed by one record per event until the end of the file:
+//! a tag byte, the device handle as `u64`, the timestamp in nanoseconds as `u64`
+//! and a tag-specific payload. All integers are little-endian.
+
+use std::{
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    backend::RawDevice, ButtonState, DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Event,
+    HidUsage, InputEvent, KeyboardDetails, MouseButton, MouseDetails, Timestamp, WheelAxis,
+};
+
+const MAGIC: &[u8; 4] = b"WXIN";
+pub const FORMAT_VERSION: u16 = 1;
+
+const TAG_MOUSE_MOTION: u8 = 0;
+const TAG_MOUSE_BUTTON: u8 = 1;
+const TAG_MOUSE_WHEEL: u8 = 2;
+const TAG_KEY: u8 = 3;
+const TAG_HID_REPORT: u8 = 4;
+const TAG_DEVICE_ADDED: u8 = 5;
+const TAG_DEVICE_REMOVED: u8 = 6;
+const TAG_MOUSE_HWHEEL: u8 = 7;
+const TAG_MOUSE_POSITION: u8 = 8;
+
+/// Writes a session log.
+pub struct Recorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Writes the header, listing `devices`, and returns a recorder ready for events.
+    pub fn new<I>(mut writer: W, devices: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = RawDevice>,
+    {
+        let devices: Vec<_> = devices.into_iter().collect();
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(devices.len() as u32).to_le_bytes())?;
+        for device in devices {
+            writer.write_all(&(device.handle.0 as u64).to_le_bytes())?;
+            writer.write_all(&[kind_to_byte(device.kind)])?;
+            write_info(&mut writer, &device.info)?;
+        }
+        Ok(Self { writer })
+    }
+
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        let tag = match event.kind {
+            InputEvent::MouseMotion { .. } => TAG_MOUSE_MOTION,
+            InputEvent::MouseButton { .. } => TAG_MOUSE_BUTTON,
+            InputEvent::MousePosition { .. } => TAG_MOUSE_POSITION,
+            InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                ..
+            } => TAG_MOUSE_WHEEL,
+            InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                ..
+            } => TAG_MOUSE_HWHEEL,
+            InputEvent::Key { .. } => TAG_KEY,
+            InputEvent::HidReport { .. } => TAG_HID_REPORT,
+            InputEvent::DeviceAdded { .. } => TAG_DEVICE_ADDED,
+            InputEvent::DeviceRemoved => TAG_DEVICE_REMOVED,
+        };
+        let w = &mut self.writer;
+        w.write_all(&[tag])?;
+        w.write_all(&(event.device.0 as u64).to_le_bytes())?;
+        w.write_all(&(event.timestamp.0.as_nanos() as u64).to_le_bytes())?;
+        match &event.kind {
+            InputEvent::MouseMotion { dx, dy } => {
+                w.write_all(&dx.to_le_bytes())?;
+                w.write_all(&dy.to_le_bytes())
+            }
+            InputEvent::MouseButton { button, state } => {
+                w.write_all(&[button_to_byte(*button), state_to_byte(*state)])
+            }
+            InputEvent::MousePosition { x, y } => {
+                w.write_all(&x.to_le_bytes())?;
+                w.write_all(&y.to_le_bytes())
+            }
+            InputEvent::MouseWheel { delta, .. } => w.write_all(&delta.to_le_bytes()),
+            InputEvent::Key { scancode, state } => {
+                w.write_all(&scancode.to_le_bytes())?;
+                w.write_all(&[state_to_byte(*state)])
+            }
+            InputEvent::HidReport { report } => write_bytes(w, report),
+            InputEvent::DeviceAdded { kind, info } => {
+                w.write_all(&[kind_to_byte(*kind)])?;
+                write_info(w, info)
+            }
+            InputEvent::DeviceRemoved => Ok(()),
+        }
+    }
+
+    /// Flushes the log and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads a session log written by a [`Recorder`].
+///
+/// The device list is read up front, the events are yielded by iterating.
+pub struct SessionReader<R: Read> {
+    reader: R,
+    devices: Vec<RawDevice>,
+}
+
+impl<R: Read> SessionReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an input session log"));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported session log version {}",
+                version
+            )));
+        }
+        let count = u32::from_le_bytes(read_array(&mut reader)?);
+        let mut devices = vec![];
+        for _ in 0..count {
+            let handle = DeviceHandle(u64::from_le_bytes(read_array(&mut reader)?) as usize);
+            let [kind] = read_array(&mut reader)?;
+            devices.push(RawDevice {
+                handle,
+                kind: kind_from_byte(kind)?,
+                info: read_info(&mut reader)?,
+            });
+        }
+        Ok(Self { reader, devices })
+    }
+
+    /// The devices listed in the header.
+    pub fn devices(&self) -> &[RawDevice] {
+        &self.devices
+    }
+
+    fn read_event(&mut self, tag: u8) -> io::Result<Event> {
+        let r = &mut self.reader;
+        let device = DeviceHandle(u64::from_le_bytes(read_array(r)?) as usize);
+        let timestamp = Timestamp(Duration::from_nanos(u64::from_le_bytes(read_array(r)?)));
+        let kind = match tag {
+            TAG_MOUSE_MOTION => InputEvent::MouseMotion {
+                dx: i32::from_le_bytes(read_array(r)?),
+                dy: i32::from_le_bytes(read_array(r)?),
+            },
+            TAG_MOUSE_BUTTON => {
+                let [button, state] = read_array(r)?;
+                InputEvent::MouseButton {
+                    button: button_from_byte(button)?,
+                    state: state_from_byte(state)?,
+                }
+            }
+            TAG_MOUSE_POSITION => InputEvent::MousePosition {
+                x: i32::from_le_bytes(read_array(r)?),
+                y: i32::from_le_bytes(read_array(r)?),
+            },
+            TAG_MOUSE_WHEEL => InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                delta: i16::from_le_bytes(read_array(r)?),
+            },
+            TAG_MOUSE_HWHEEL => InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                delta: i16::from_le_bytes(read_array(r)?),
+            },
+            TAG_KEY => {
+                let scancode = u16::from_le_bytes(read_array(r)?);
+                let [state] = read_array(r)?;
+                InputEvent::Key {
+                    scancode,
+                    state: state_from_byte(state)?,
+                }
+            }
+            TAG_HID_REPORT => InputEvent::HidReport {
+                report: read_bytes(r)?,
+            },
+            TAG_DEVICE_ADDED => {
+                let [kind] = read_array(r)?;
+                InputEvent::DeviceAdded {
+                    kind: kind_from_byte(kind)?,
+                    info: read_info(r)?,
+                }
+            }
+            TAG_DEVICE_REMOVED => InputEvent::DeviceRemoved,
+            _ => return Err(invalid_data(format!("unknown event tag {}", tag))),
+        };
+        Ok(Event {
+            device,
+            timestamp,
+            kind,
+        })
+    }
+}
+
+impl<R: Read> Iterator for SessionReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the log simply ends after the last event
+        let mut tag = [0u8];
+        match self.reader.read(&mut tag) {
+            Ok(0) => None,
+            Ok(_) => Some(self.read_event(tag[0])),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads a `u32` length followed by that many bytes.
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = u32::from_le_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| invalid_data("string is not UTF-8"))
+}
+
+/// A presence byte, followed by the string if it is 1.
+fn write_optional_string(writer: &mut impl Write, string: &Option<String>) -> io::Result<()> {
+    match string {
+        Some(string) => {
+            writer.write_all(&[1])?;
+            write_bytes(writer, string.as_bytes())
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+fn read_optional_string(reader: &mut impl Read) -> io::Result<Option<String>> {
+    match read_array(reader)? {
+        [0] => Ok(None),
+        [1] => read_string(reader).map(Some),
+        _ => Err(invalid_data("invalid presence byte")),
+    }
+}
+
+fn write_info(writer: &mut impl Write, info: &DeviceInfo) -> io::Result<()> {
+    write_bytes(writer, info.product_name.as_bytes())?;
+    write_optional_string(writer, &info.manufacturer)?;
+    write_optional_string(writer, &info.serial_number)?;
+    for id in [info.vendor_id, info.product_id, info.version] {
+        writer.write_all(&id.to_le_bytes())?;
+    }
+    write_bytes(writer, info.path.as_bytes())?;
+    write_optional_string(writer, &info.location)?;
+    let (tag, fields) = match info.details {
+        DeviceDetails::None => (0, vec![]),
+        DeviceDetails::Mouse(mouse) => (
+            1,
+            vec![
+                mouse.id,
+                mouse.buttons,
+                mouse.sample_rate,
+                mouse.has_horizontal_wheel as u32,
+            ],
+        ),
+        DeviceDetails::Keyboard(keyboard) => (
+            2,
+            vec![
+                keyboard.keyboard_type,
+                keyboard.subtype,
+                keyboard.scancode_mode,
+                keyboard.function_keys,
+                keyboard.indicators,
+                keyboard.total_keys,
+            ],
+        ),
+        DeviceDetails::Hid(usage) => (3, vec![usage.page as u32, usage.id as u32]),
+    };
+    writer.write_all(&[tag])?;
+    for field in fields {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_info(reader: &mut impl Read) -> io::Result<DeviceInfo> {
+    let product_name = read_string(reader)?;
+    let manufacturer = read_optional_string(reader)?;
+    let serial_number = read_optional_string(reader)?;
+    let vendor_id = u16::from_le_bytes(read_array(reader)?);
+    let product_id = u16::from_le_bytes(read_array(reader)?);
+    let version = u16::from_le_bytes(read_array(reader)?);
+    let path = read_string(reader)?;
+    let location = read_optional_string(reader)?;
+    let field = |reader: &mut _| read_array(reader).map(u32::from_le_bytes);
+    let details = match read_array(reader)? {
+        [0] => DeviceDetails::None,
+        [1] => DeviceDetails::Mouse(MouseDetails {
+            id: field(reader)?,
+            buttons: field(reader)?,
+            sample_rate: field(reader)?,
+            has_horizontal_wheel: field(reader)? != 0,
+        }),
+        [2] => DeviceDetails::Keyboard(KeyboardDetails {
+            keyboard_type: field(reader)?,
+            subtype: field(reader)?,
+            scancode_mode: field(reader)?,
+            function_keys: field(reader)?,
+            indicators: field(reader)?,
+            total_keys: field(reader)?,
+        }),
+        [3] => DeviceDetails::Hid(HidUsage::new(field(reader)? as u16, field(reader)? as u16)),
+        [tag] => return Err(invalid_data(format!("unknown device details {}", tag))),
+    };
+    Ok(DeviceInfo {
+        product_name,
+        manufacturer,
+        serial_number,
+        vendor_id,
+        product_id,
+        version,
+        path,
+        location,
+        details,
+    })
+}
+
+fn kind_to_byte(kind: DeviceKind) -> u8 {
+    match kind {
+        DeviceKind::Mouse => 0,
+        DeviceKind::Keyboard => 1,
+        DeviceKind::Hid => 2,
+    }
+}
+
+fn kind_from_byte(byte: u8) -> io::Result<DeviceKind> {
+    match byte {
+        0 => Ok(DeviceKind::Mouse),
+        1 => Ok(DeviceKind::Keyboard),
+        2 => Ok(DeviceKind::Hid),
+        _ => Err(invalid_data(format!("unknown device kind {}", byte))),
+    }
+}
+
+fn button_to_byte(button: MouseButton) -> u8 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+    }
+}
+
+fn button_from_byte(byte: u8) -> io::Result<MouseButton> {
+    match byte {
+        0 => Ok(MouseButton::Left),
+        1 => Ok(MouseButton::Right),
+        2 => Ok(MouseButton::Middle),
+        3 => Ok(MouseButton::Back),
+        4 => Ok(MouseButton::Forward),
+        _ => Err(invalid_data(format!("unknown mouse button {}", byte))),
+    }
+}
+
+fn state_to_byte(state: ButtonState) -> u8 {
+    match state {
+        ButtonState::Released => 0,
+        ButtonState::Pressed => 1,
+    }
+}
+
+fn state_from_byte(byte: u8) -> io::Result<ButtonState> {
+    match byte {
+        0 => Ok(ButtonState::Released),
+        1 => Ok(ButtonState::Pressed),
+        _ => Err(invalid_data(format!("unknown button state {}", byte))),
+    }
+}
@@ -0,0 +1,565 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::{
+    gamepad::{self, GamepadLayout},
+    hid::{FieldValue, ReportDescriptor, ReportKind},
+    mapping::{self, Axis, Half, Mapping, Source},
+    ButtonState, Event, HidUsage, InputEvent, KeyCode, MouseButton, Timestamp, WheelAxis,
+};
+
+const GENERIC_DESKTOP: u16 = 0x01;
+const HAT_SWITCH: HidUsage = HidUsage::new(GENERIC_DESKTOP, 0x39);
+const BUTTON_PAGE: u16 = 0x09;
+/// The generic desktop axes from X to wheel, which mappings number in this order.
+const AXES: std::ops::RangeInclusive<u16> = 0x30..=0x38;
+
+/// The keys held on one keyboard, and its lock keys.
+///
+/// [`Devices`](crate::Devices) keeps one for every added keyboard while listening, see
+/// [`Devices::keyboard_state`](crate::Devices::keyboard_state), but it can also be fed events
+/// by hand with [`update`](Self::update).
+///
+/// The locks start off, as the state of a keyboard's lights can't be read;
+/// set them if they are known to be on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyboardState {
+    pressed: BTreeSet<KeyCode>,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+/// Which modifier keys are held, per side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+    /// The Windows or Command key.
+    pub left_meta: bool,
+    pub right_meta: bool,
+}
+
+impl KeyboardState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a key event; other events are ignored, as are scancodes without a [`KeyCode`].
+    pub fn update(&mut self, event: &InputEvent) {
+        let InputEvent::Key { scancode, state } = *event else {
+            return;
+        };
+        let Some(key) = KeyCode::from_scancode(scancode) else {
+            return;
+        };
+        match state {
+            // held keys repeat their presses, only the first one toggles a lock
+            ButtonState::Pressed if self.pressed.insert(key) => match key {
+                KeyCode::CapsLock => self.caps_lock = !self.caps_lock,
+                KeyCode::NumLock => self.num_lock = !self.num_lock,
+                KeyCode::ScrollLock => self.scroll_lock = !self.scroll_lock,
+                _ => {}
+            },
+            ButtonState::Pressed => {}
+            ButtonState::Released => {
+                self.pressed.remove(&key);
+            }
+        }
+    }
+
+    pub fn is_pressed(&self, key: KeyCode) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// The held keys, ordered by HID usage.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = KeyCode> + '_ {
+        self.pressed.iter().copied()
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let held = |key| self.is_pressed(key);
+        Modifiers {
+            left_shift: held(KeyCode::ShiftLeft),
+            right_shift: held(KeyCode::ShiftRight),
+            left_ctrl: held(KeyCode::ControlLeft),
+            right_ctrl: held(KeyCode::ControlRight),
+            left_alt: held(KeyCode::AltLeft),
+            right_alt: held(KeyCode::AltRight),
+            left_meta: held(KeyCode::SuperLeft),
+            right_meta: held(KeyCode::SuperRight),
+        }
+    }
+
+    /// Releases every key and returns the ones that were held, e.g. when the keyboard
+    /// was unplugged before their release could be reported.
+    pub fn release_all(&mut self) -> Vec<KeyCode> {
+        std::mem::take(&mut self.pressed).into_iter().collect()
+    }
+}
+
+impl Modifiers {
+    pub fn shift(&self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+
+    pub fn alt(&self) -> bool {
+        self.left_alt || self.right_alt
+    }
+
+    pub fn meta(&self) -> bool {
+        self.left_meta || self.right_meta
+    }
+}
+
+/// A cursor driven by one mouse, with its held buttons and wheel totals.
+///
+/// [`Devices`](crate::Devices) keeps one for every added mouse while listening, see
+/// [`Devices::mouse_state`](crate::Devices::mouse_state), but it can also be fed events
+/// by hand with [`update`](Self::update).
+///
+/// Relative motion moves the position in device units, absolute positions set it in desktop
+/// pixels. Either way it stays within the bounds, if there are any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MouseState {
+    x: i32,
+    y: i32,
+    bounds: Option<Rect>,
+    /// In the order they were pressed.
+    buttons: Vec<MouseButton>,
+    vertical_wheel: i64,
+    horizontal_wheel: i64,
+    last_activity: Option<Timestamp>,
+}
+
+/// An area the position of a [`MouseState`] is kept in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies a mouse event; other events are ignored.
+    pub fn update(&mut self, event: &Event) {
+        match event.kind {
+            InputEvent::MouseMotion { dx, dy } => {
+                self.set_position(self.x.saturating_add(dx), self.y.saturating_add(dy))
+            }
+            InputEvent::MousePosition { x, y } => self.set_position(x, y),
+            InputEvent::MouseButton { button, state } => {
+                self.buttons.retain(|&held| held != button);
+                if state == ButtonState::Pressed {
+                    self.buttons.push(button);
+                }
+            }
+            InputEvent::MouseWheel { axis, delta } => match axis {
+                WheelAxis::Vertical => self.vertical_wheel += delta as i64,
+                WheelAxis::Horizontal => self.horizontal_wheel += delta as i64,
+            },
+            _ => return,
+        }
+        self.last_activity = Some(event.timestamp);
+    }
+
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// Moves the cursor, e.g. to center it, keeping it within the bounds.
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        (self.x, self.y) = match self.bounds {
+            Some(bounds) => bounds.clamp(x, y),
+            None => (x, y),
+        };
+    }
+
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds
+    }
+
+    /// Confines the cursor to `bounds`, moving it inside if needed, or lets it roam freely.
+    pub fn set_bounds(&mut self, bounds: Option<Rect>) {
+        self.bounds = bounds;
+        self.set_position(self.x, self.y);
+    }
+
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// The held buttons, in the order they were pressed.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.buttons.iter().copied()
+    }
+
+    /// The sum of all wheel deltas on `axis`, 120 per notch.
+    pub fn wheel(&self, axis: WheelAxis) -> i64 {
+        match axis {
+            WheelAxis::Vertical => self.vertical_wheel,
+            WheelAxis::Horizontal => self.horizontal_wheel,
+        }
+    }
+
+    /// When the mouse last moved or had a button or wheel used.
+    pub fn last_activity(&self) -> Option<Timestamp> {
+        self.last_activity
+    }
+}
+
+impl Rect {
+    /// The point inside the rectangle closest to `(x, y)`.
+    /// An empty rectangle holds only its top left corner.
+    pub fn clamp(&self, x: i32, y: i32) -> (i32, i32) {
+        let right = self.left.saturating_add(self.width.max(1) - 1);
+        let bottom = self.top.saturating_add(self.height.max(1) - 1);
+        (x.clamp(self.left, right), y.clamp(self.top, bottom))
+    }
+}
+
+/// The sticks, triggers, hat switch and buttons of one gamepad.
+///
+/// [`Devices`](crate::Devices) keeps one for every added gamepad while listening, see
+/// [`Devices::gamepad_state`](crate::Devices::gamepad_state), but it can also be fed the
+/// values of decoded reports by hand with [`update`](Self::update).
+///
+/// The sticks go from -1 to 1 on each axis, with y positive downwards like HID reports it,
+/// and the triggers from 0 to 1. Both read as resting within the deadzone.
+///
+/// With a [`Mapping`], the sticks, triggers and [standard buttons](Self::button) are read
+/// as it says rather than by the layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadState {
+    layout: &'static GamepadLayout,
+    mapping: Option<Arc<Mapping>>,
+    /// How far sticks and triggers have to move before they count, from 0 to 1.
+    /// The rest of their travel is stretched to the full range.
+    pub deadzone: f32,
+    left_stick: [f32; 2],
+    right_stick: [f32; 2],
+    triggers: [f32; 2],
+    hat: Hat,
+    buttons: BTreeSet<u16>,
+    /// The generic desktop axes reported so far by usage ID, from -1 to 1.
+    axes: BTreeMap<u16, f32>,
+    /// The usage IDs of the generic desktop axes the descriptor declares, in ascending order,
+    /// which mappings number axes by. `None` until the descriptor is known.
+    declared_axes: Option<Vec<u16>>,
+}
+
+/// The direction a hat switch, usually the d-pad, is pushed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Hat {
+    #[default]
+    Centered,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self::new(&gamepad::GENERIC)
+    }
+}
+
+impl GamepadState {
+    /// A resting gamepad whose reports are read with `layout`, with a deadzone of 0.1.
+    pub fn new(layout: &'static GamepadLayout) -> Self {
+        Self {
+            layout,
+            mapping: None,
+            deadzone: 0.1,
+            left_stick: [0.0; 2],
+            right_stick: [0.0; 2],
+            triggers: [0.0; 2],
+            hat: Hat::Centered,
+            buttons: BTreeSet::new(),
+            axes: BTreeMap::new(),
+            declared_axes: None,
+        }
+    }
+
+    pub fn layout(&self) -> &'static GamepadLayout {
+        self.layout
+    }
+
+    pub fn mapping(&self) -> Option<&Mapping> {
+        self.mapping.as_deref()
+    }
+
+    /// Reads the gamepad with `mapping` from now on, or by its layout with `None`.
+    pub fn set_mapping(&mut self, mapping: Option<Mapping>) {
+        self.mapping = mapping.map(Arc::new);
+        if self.mapping.is_some() {
+            self.apply_mapping();
+        }
+    }
+
+    /// Takes the axes mappings number from the descriptor the gamepad's reports are decoded
+    /// with. [`Devices`](crate::Devices) does this when it reads the descriptor.
+    pub fn set_descriptor(&mut self, descriptor: &ReportDescriptor) {
+        let mut axes: Vec<_> = descriptor
+            .fields()
+            .filter(|field| {
+                field.kind == ReportKind::Input && field.is_variable() && !field.is_constant()
+            })
+            .flat_map(|field| &field.usages)
+            .filter(|range| range.page == GENERIC_DESKTOP)
+            .flat_map(|range| range.min..=range.max)
+            .filter(|id| AXES.contains(id))
+            .collect();
+        axes.sort();
+        axes.dedup();
+        self.declared_axes = Some(axes);
+        if self.mapping.is_some() {
+            self.apply_mapping();
+        }
+    }
+
+    pub(crate) fn has_descriptor(&self) -> bool {
+        self.declared_axes.is_some()
+    }
+
+    /// Applies the values of a decoded input report, see
+    /// [`ReportDescriptor::decode_input`](crate::hid::ReportDescriptor::decode_input).
+    /// Values of usages neither the layout nor the mapping knows are ignored.
+    pub fn update(&mut self, values: &[FieldValue]) {
+        let layout = self.layout;
+        for value in values {
+            let usage = value.usage;
+            if usage.page == BUTTON_PAGE && usage.id != 0 {
+                if value.value != 0 {
+                    self.buttons.insert(usage.id);
+                } else {
+                    self.buttons.remove(&usage.id);
+                }
+            } else if usage == HAT_SWITCH {
+                self.hat = Hat::from_value(value);
+            }
+            let normalized = normalize(value);
+            let centered = normalized * 2.0 - 1.0;
+            if usage.page == GENERIC_DESKTOP && AXES.contains(&usage.id) {
+                self.axes.insert(usage.id, centered);
+            }
+            if self.mapping.is_some() {
+                continue;
+            }
+            set_axes(&mut self.left_stick, layout.left_stick, usage, centered);
+            set_axes(&mut self.right_stick, layout.right_stick, usage, centered);
+            if let Some(triggers) = layout.triggers {
+                set_axes(&mut self.triggers, triggers, usage, normalized);
+            }
+        }
+        if self.mapping.is_some() {
+            self.apply_mapping();
+        }
+    }
+
+    pub fn left_stick(&self) -> (f32, f32) {
+        self.stick(self.left_stick)
+    }
+
+    pub fn right_stick(&self) -> (f32, f32) {
+        self.stick(self.right_stick)
+    }
+
+    pub fn left_trigger(&self) -> f32 {
+        self.trigger(self.triggers[0])
+    }
+
+    pub fn right_trigger(&self) -> f32 {
+        self.trigger(self.triggers[1])
+    }
+
+    pub fn hat(&self) -> Hat {
+        self.hat
+    }
+
+    /// Whether a button is held, numbered from 1 as reported by the device.
+    /// [`GamepadLayout::button_name`] tells what it is called.
+    pub fn is_pressed(&self, button: u16) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// The held buttons, in ascending order.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = u16> + '_ {
+        self.buttons.iter().copied()
+    }
+
+    /// Whether a button of the standard gamepad is held, as the mapping says; always
+    /// `false` without one.
+    pub fn button(&self, button: mapping::Button) -> bool {
+        let source = self.mapping().and_then(|mapping| mapping.button(button));
+        source.is_some_and(|source| self.read(source).0 > 0.5)
+    }
+
+    /// An axis by its index in mappings, from -1 to 1, see [`Source`].
+    /// It is `None` until the gamepad has reported it, and for axes its
+    /// [descriptor](Self::set_descriptor) doesn't declare, or without one.
+    pub fn raw_axis(&self, index: usize) -> Option<f32> {
+        let usage = self.declared_axes.as_ref()?.get(index)?;
+        self.axes.get(usage).copied()
+    }
+
+    /// Sets the sticks and triggers from the raw input, as the mapping says.
+    fn apply_mapping(&mut self) {
+        let Some(mapping) = self.mapping.clone() else {
+            return;
+        };
+        let axis = |axis: Axis| {
+            let (min, max) = if axis.is_trigger() {
+                (0.0, 1.0)
+            } else {
+                (-1.0, 1.0)
+            };
+            let value: f32 = mapping
+                .axis(axis)
+                .map(|(half, source)| {
+                    let (value, whole) = self.read(source);
+                    // a whole axis spans -1 to 1, anything else 0 to 1
+                    let unit = if whole { (value + 1.0) / 2.0 } else { value };
+                    match half {
+                        Some(Half::Positive) => unit,
+                        Some(Half::Negative) => -unit,
+                        None if whole && !axis.is_trigger() => value,
+                        None => min + unit * (max - min),
+                    }
+                })
+                .sum();
+            value.clamp(min, max)
+        };
+        let left_stick = [axis(Axis::LeftX), axis(Axis::LeftY)];
+        let right_stick = [axis(Axis::RightX), axis(Axis::RightY)];
+        let triggers = [axis(Axis::LeftTrigger), axis(Axis::RightTrigger)];
+        self.left_stick = left_stick;
+        self.right_stick = right_stick;
+        self.triggers = triggers;
+    }
+
+    /// The value of an input, and whether it is a whole axis from -1 to 1 rather than
+    /// something from 0 to 1.
+    fn read(&self, source: Source) -> (f32, bool) {
+        match source {
+            Source::Button(index) => {
+                let pressed = self.is_pressed(u16::from(index) + 1);
+                (if pressed { 1.0 } else { 0.0 }, false)
+            }
+            Source::Axis {
+                index,
+                half,
+                inverted,
+            } => {
+                let value = self.raw_axis(index.into()).unwrap_or(0.0);
+                let value = if inverted { -value } else { value };
+                match half {
+                    None => (value, true),
+                    Some(Half::Positive) => (value.max(0.0), false),
+                    Some(Half::Negative) => ((-value).max(0.0), false),
+                }
+            }
+            // only one hat switch is kept track of
+            Source::Hat { index, mask } => {
+                let held = index == 0 && self.hat.mask() & mask != 0;
+                (if held { 1.0 } else { 0.0 }, false)
+            }
+        }
+    }
+
+    /// Applies the deadzone to the distance from the center, so that diagonals are not cut
+    /// short, and keeps the result within the unit circle.
+    fn stick(&self, [x, y]: [f32; 2]) -> (f32, f32) {
+        let magnitude = x.hypot(y);
+        if magnitude <= self.deadzone {
+            return (0.0, 0.0);
+        }
+        let scaled = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        (x / magnitude * scaled, y / magnitude * scaled)
+    }
+
+    fn trigger(&self, value: f32) -> f32 {
+        if value <= self.deadzone {
+            return 0.0;
+        }
+        ((value - self.deadzone) / (1.0 - self.deadzone)).min(1.0)
+    }
+}
+
+impl Hat {
+    /// The directions as in mappings: 1 up, 2 right, 4 down and 8 left.
+    fn mask(self) -> u8 {
+        match self {
+            Hat::Centered => 0,
+            Hat::Up => 1,
+            Hat::UpRight => 1 | 2,
+            Hat::Right => 2,
+            Hat::DownRight => 4 | 2,
+            Hat::Down => 4,
+            Hat::DownLeft => 4 | 8,
+            Hat::Left => 8,
+            Hat::UpLeft => 1 | 8,
+        }
+    }
+
+    /// Clockwise from up.
+    const DIRECTIONS: [Hat; 8] = [
+        Hat::Up,
+        Hat::UpRight,
+        Hat::Right,
+        Hat::DownRight,
+        Hat::Down,
+        Hat::DownLeft,
+        Hat::Left,
+        Hat::UpLeft,
+    ];
+
+    /// Reads a hat switch with eight positions clockwise from up, or four without the
+    /// diagonals. Values outside its range are its null state.
+    fn from_value(value: &FieldValue) -> Self {
+        if !(value.logical_min..=value.logical_max).contains(&value.value) {
+            return Hat::Centered;
+        }
+        let positions = value.logical_max as i64 - value.logical_min as i64 + 1;
+        let position = value.value as i64 - value.logical_min as i64;
+        Self::DIRECTIONS[(position * 8 / positions) as usize]
+    }
+}
+
+/// Sets the axes of `usage` to `value`.
+fn set_axes(axes: &mut [f32; 2], usages: [HidUsage; 2], usage: HidUsage, value: f32) {
+    for (axis, axis_usage) in axes.iter_mut().zip(usages) {
+        if axis_usage == usage {
+            *axis = value;
+        }
+    }
+}
+
+/// Maps a value onto 0 to 1 by its logical range.
+fn normalize(value: &FieldValue) -> f32 {
+    let (min, max) = (value.logical_min as f32, value.logical_max as f32);
+    if max <= min {
+        return 0.0;
+    }
+    ((value.value as f32 - min) / (max - min)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,51 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+
+use crate::{DeviceEvent, EventReceiver, ListenerHandle};
+
+/// The events of a listener as an async [`Stream`], returned by
+/// [`Devices::stream`](crate::Devices::stream).
+///
+/// The listener thread wakes the task polling the stream when events arrive.
+/// The stream ends when the listener stops, which it does when the stream is dropped;
+/// dropping never blocks, the thread finishes on its own shortly after.
+pub struct EventStream {
+    receiver: EventReceiver,
+    listener: Option<ListenerHandle>,
+}
+
+impl EventStream {
+    pub(crate) fn new(listener: ListenerHandle, receiver: EventReceiver) -> Self {
+        Self {
+            receiver,
+            listener: Some(listener),
+        }
+    }
+
+    /// See [`ListenerHandle::dropped_events`].
+    pub fn dropped_events(&self) -> u64 {
+        self.listener
+            .as_ref()
+            .map_or(0, ListenerHandle::dropped_events)
+    }
+}
+
+impl Stream for EventStream {
+    type Item = DeviceEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.take() {
+            listener.detach();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{DeviceId, Event, InputEvent};
+
+/// Token for a handler registered with [`Devices::on_event`](crate::Devices::on_event) and
+/// friends, for [`Devices::unsubscribe`](crate::Devices::unsubscribe).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+pub(crate) type Callback = Box<dyn FnMut(&DeviceId, &Event) + Send>;
+
+struct Handler {
+    subscription: Subscription,
+    /// The only device whose events are handled, if any.
+    device: Option<DeviceId>,
+    /// Which events are handled.
+    accepts: fn(&InputEvent) -> bool,
+    /// In a mutex as callbacks are not `Sync`, but handlers are shared with the listener thread.
+    callback: Mutex<Callback>,
+}
+
+/// The registered handlers, shared by [`Devices`](crate::Devices) and its listener.
+#[derive(Default)]
+pub(crate) struct Handlers {
+    next: u64,
+    handlers: Vec<Arc<Handler>>,
+}
+
+impl Handlers {
+    pub(crate) fn add(
+        &mut self,
+        device: Option<DeviceId>,
+        accepts: fn(&InputEvent) -> bool,
+        callback: Callback,
+    ) -> Subscription {
+        let subscription = Subscription(self.next);
+        self.next += 1;
+        self.handlers.push(Arc::new(Handler {
+            subscription,
+            device,
+            accepts,
+            callback: Mutex::new(callback),
+        }));
+        subscription
+    }
+
+    /// Returns whether the handler was registered.
+    pub(crate) fn remove(&mut self, subscription: Subscription) -> bool {
+        let count = self.handlers.len();
+        self.handlers
+            .retain(|handler| handler.subscription != subscription);
+        count != self.handlers.len()
+    }
+}
+
+/// Calls the handlers for an event of the device `id`.
+///
+/// They are called without holding the lock on `handlers`, so that they can register and
+/// unregister handlers themselves.
+/// A panicking handler stays registered and doesn't keep the others from being called;
+/// the panic is only reported by the panic hook.
+pub(crate) fn dispatch(handlers: &Mutex<Handlers>, id: &DeviceId, event: &Event) {
+    let matching: Vec<_> = {
+        let handlers = handlers.lock().unwrap_or_else(PoisonError::into_inner);
+        handlers
+            .handlers
+            .iter()
+            .filter(|handler| handler.device.as_ref().is_none_or(|device| device == id))
+            .filter(|handler| (handler.accepts)(&event.kind))
+            .cloned()
+            .collect()
+    };
+    for handler in matching {
+        let mut callback = handler
+            .callback
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| callback(id, event)));
+    }
+}
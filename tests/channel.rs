@@ -0,0 +1,145 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use windows_experiments::{
+    backend::mock::MockBackend, ChannelConfig, DeviceHandle, Devices, InputEvent, ListenerHandle,
+    Overflow, Timestamp,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A mouse that has moved `count` times before listening starts.
+fn backend(count: i32) -> MockBackend {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    for dx in 0..count {
+        backend.push_event(
+            DeviceHandle(1),
+            Timestamp::default(),
+            InputEvent::MouseMotion { dx, dy: 0 },
+        );
+    }
+    backend
+}
+
+fn wait_for_drops(listener: &ListenerHandle, count: u64) {
+    let start = Instant::now();
+    while listener.dropped_events() < count {
+        assert!(start.elapsed() < TIMEOUT, "{}", listener.dropped_events());
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn motion(kind: InputEvent) -> i32 {
+    match kind {
+        InputEvent::MouseMotion { dx, .. } => dx,
+        kind => panic!("unexpected {kind:?}"),
+    }
+}
+
+#[test]
+fn drops_events_on_overflow() {
+    for (overflow, kept) in [
+        (Overflow::DropNewest, [0, 1]),
+        (Overflow::DropOldest, [3, 4]),
+    ] {
+        let devices = Devices::with_backend(backend(5));
+        devices.add_all_devices().unwrap();
+        let (listener, receiver) = devices
+            .start_listening(ChannelConfig {
+                capacity: 2,
+                overflow,
+            })
+            .unwrap();
+        wait_for_drops(&listener, 3);
+
+        let (id, _, kind) = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(id.to_string(), "mouse:0000:0000");
+        let second = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!([motion(kind), motion(second.2)], kept);
+        assert!(receiver.try_recv().is_err());
+
+        listener.stop();
+        listener.join().unwrap();
+        assert!(receiver.recv().is_err());
+    }
+}
+
+#[test]
+fn blocks_until_received() {
+    let devices = Devices::with_backend(backend(4));
+    devices.add_all_devices().unwrap();
+    let config = ChannelConfig {
+        capacity: 1,
+        overflow: Overflow::Block,
+    };
+    let (listener, receiver) = devices.start_listening(config).unwrap();
+    let received: Vec<_> = (0..4)
+        .map(|_| motion(receiver.recv_timeout(TIMEOUT).unwrap().2))
+        .collect();
+    assert_eq!(received, [0, 1, 2, 3]);
+    assert_eq!(listener.dropped_events(), 0);
+    listener.stop();
+    listener.join().unwrap();
+
+    // stopping doesn't wait for a consumer that stopped receiving
+    let devices = Devices::with_backend(backend(4));
+    devices.add_all_devices().unwrap();
+    let (listener, _receiver) = devices.start_listening(config).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[cfg(feature = "crossbeam")]
+#[test]
+fn sends_to_crossbeam_channels() {
+    let devices = Devices::with_backend(backend(5));
+    devices.add_all_devices().unwrap();
+    let (listener, receiver) = devices
+        .start_listening_crossbeam(ChannelConfig {
+            capacity: 2,
+            overflow: Overflow::DropOldest,
+        })
+        .unwrap();
+    wait_for_drops(&listener, 3);
+    let received: Vec<_> = receiver
+        .try_iter()
+        .map(|(_, _, kind)| motion(kind))
+        .collect();
+    assert_eq!(received, [3, 4]);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn streams_events() {
+    use futures::{executor::block_on, StreamExt};
+
+    let backend = backend(2);
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    let mut stream = devices.stream(ChannelConfig::default()).unwrap();
+    let received = block_on(async {
+        let mut received = vec![];
+        for _ in 0..3 {
+            received.push(motion(stream.next().await.unwrap().2));
+            // arrives while the task waits
+            if received.len() == 2 {
+                let backend = backend.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(20));
+                    backend.push_event(
+                        DeviceHandle(1),
+                        Timestamp::default(),
+                        InputEvent::MouseMotion { dx: 7, dy: 0 },
+                    );
+                });
+            }
+        }
+        received
+    });
+    assert_eq!(received, [0, 1, 7]);
+    assert_eq!(stream.dropped_events(), 0);
+}
@@ -0,0 +1,226 @@
+use windows_experiments::{decode::*, ButtonState, InputEvent, MouseButton, WheelAxis};
+
+const DESKTOP: Desktop = Desktop {
+    left: -1920,
+    top: 0,
+    width: 3840,
+    height: 1080,
+    primary_width: 1920,
+    primary_height: 1080,
+};
+
+fn mouse(raw: RawMouse) -> Vec<InputEvent> {
+    let mut events = vec![];
+    decode_mouse(&raw, &DESKTOP, |event| events.push(event));
+    events
+}
+
+fn button(button: MouseButton, state: ButtonState) -> InputEvent {
+    InputEvent::MouseButton { button, state }
+}
+
+#[test]
+fn decodes_mouse_packets() {
+    use ButtonState::*;
+    use MouseButton::*;
+
+    let cases = [
+        (RawMouse::default(), vec![]),
+        (
+            RawMouse {
+                last_x: -3,
+                last_y: 5,
+                ..Default::default()
+            },
+            vec![InputEvent::MouseMotion { dx: -3, dy: 5 }],
+        ),
+        (
+            RawMouse {
+                button_flags: RI_MOUSE_LEFT_BUTTON_DOWN | RI_MOUSE_RIGHT_BUTTON_UP,
+                ..Default::default()
+            },
+            vec![button(Left, Pressed), button(Right, Released)],
+        ),
+        (
+            RawMouse {
+                button_flags: RI_MOUSE_MIDDLE_BUTTON_DOWN
+                    | RI_MOUSE_BUTTON_4_DOWN
+                    | RI_MOUSE_BUTTON_5_UP,
+                last_x: 1,
+                ..Default::default()
+            },
+            vec![
+                InputEvent::MouseMotion { dx: 1, dy: 0 },
+                button(Middle, Pressed),
+                button(Back, Pressed),
+                button(Forward, Released),
+            ],
+        ),
+        (
+            RawMouse {
+                button_flags: RI_MOUSE_WHEEL,
+                button_data: (-120i16) as u16,
+                ..Default::default()
+            },
+            vec![InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                delta: -120,
+            }],
+        ),
+        // a high-resolution wheel reporting a quarter notch
+        (
+            RawMouse {
+                button_flags: RI_MOUSE_HWHEEL,
+                button_data: 30,
+                ..Default::default()
+            },
+            vec![InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                delta: 30,
+            }],
+        ),
+        // absolute coordinates span the primary monitor...
+        (
+            RawMouse {
+                flags: MOUSE_MOVE_ABSOLUTE,
+                last_x: 65535,
+                last_y: 32768,
+                ..Default::default()
+            },
+            vec![InputEvent::MousePosition { x: 1920, y: 540 }],
+        ),
+        // ...or the whole desktop, which extends left of the primary monitor here
+        (
+            RawMouse {
+                flags: MOUSE_MOVE_ABSOLUTE | MOUSE_VIRTUAL_DESKTOP,
+                last_x: 0,
+                last_y: 65535,
+                ..Default::default()
+            },
+            vec![InputEvent::MousePosition { x: -1920, y: 1080 }],
+        ),
+        (
+            RawMouse {
+                flags: MOUSE_MOVE_ABSOLUTE | MOUSE_VIRTUAL_DESKTOP,
+                last_x: 32768,
+                ..Default::default()
+            },
+            vec![InputEvent::MousePosition { x: 0, y: 0 }],
+        ),
+    ];
+    for (raw, expected) in cases {
+        assert_eq!(mouse(raw), expected, "{:?}", raw);
+    }
+}
+
+/// Feeds `packets` of (make code, flags) through one decoder.
+fn keyboard(packets: &[(u16, u16)]) -> Vec<(u16, ButtonState)> {
+    let mut decoder = KeyboardDecoder::new();
+    packets
+        .iter()
+        .filter_map(|&(make_code, flags)| decoder.decode(&RawKeyboard { make_code, flags }))
+        .map(|event| match event {
+            InputEvent::Key { scancode, state } => (scancode, state),
+            event => panic!("not a key: {:?}", event),
+        })
+        .collect()
+}
+
+#[test]
+fn decodes_keyboard_packets() {
+    use ButtonState::*;
+    const BREAK: u16 = RI_KEY_BREAK;
+    const E0: u16 = RI_KEY_E0;
+    const E1: u16 = RI_KEY_E1;
+
+    type Case<'a> = (&'a str, &'a [(u16, u16)], &'a [(u16, ButtonState)]);
+    let cases: &[Case] = &[
+        (
+            "A",
+            &[(0x1E, 0), (0x1E, BREAK)],
+            &[(0x1E, Pressed), (0x1E, Released)],
+        ),
+        (
+            "left and right Ctrl",
+            &[(0x1D, 0), (0x1D, E0), (0x1D, E0 | BREAK), (0x1D, BREAK)],
+            &[
+                (0x1D, Pressed),
+                (0xE01D, Pressed),
+                (0xE01D, Released),
+                (0x1D, Released),
+            ],
+        ),
+        (
+            "numpad Enter",
+            &[(0x1C, E0), (0x1C, E0 | BREAK)],
+            &[(0xE01C, Pressed), (0xE01C, Released)],
+        ),
+        (
+            "arrow with Num Lock on, wrapped in fake shifts",
+            &[
+                (0x2A, E0),
+                (0x48, E0),
+                (0x48, E0 | BREAK),
+                (0x2A, E0 | BREAK),
+            ],
+            &[(0xE048, Pressed), (0xE048, Released)],
+        ),
+        (
+            "Insert with right Shift held, which the keyboard releases and presses again",
+            &[
+                (0x36, 0),
+                (0x36, E0 | BREAK),
+                (0x52, E0),
+                (0x52, E0 | BREAK),
+                (0x36, E0),
+                (0x36, BREAK),
+            ],
+            &[
+                (0x36, Pressed),
+                (0xE052, Pressed),
+                (0xE052, Released),
+                (0x36, Released),
+            ],
+        ),
+        (
+            "Print Screen",
+            &[
+                (0x2A, E0),
+                (0x37, E0),
+                (0x37, E0 | BREAK),
+                (0x2A, E0 | BREAK),
+            ],
+            &[(0xE037, Pressed), (0xE037, Released)],
+        ),
+        (
+            "Pause, released right after the press",
+            &[(0x1D, E1), (0x45, 0), (0x1D, E1 | BREAK), (0x45, BREAK)],
+            &[(0xE11D, Pressed), (0xE11D, Released)],
+        ),
+        (
+            "Pause followed by Num Lock",
+            &[(0x1D, E1), (0x45, 0), (0x45, 0), (0x45, BREAK)],
+            &[(0xE11D, Pressed), (0x45, Pressed), (0x45, Released)],
+        ),
+        (
+            "Num Lock with E0",
+            &[(0x45, E0), (0x45, E0 | BREAK)],
+            &[(0x45, Pressed), (0x45, Released)],
+        ),
+        (
+            "Ctrl+Pause, i.e. Break",
+            &[(0x1D, 0), (0x46, E0), (0x46, E0 | BREAK), (0x1D, BREAK)],
+            &[
+                (0x1D, Pressed),
+                (0xE046, Pressed),
+                (0xE046, Released),
+                (0x1D, Released),
+            ],
+        ),
+        ("overrun", &[(0xFF, 0), (0x00, 0)], &[]),
+        ("stray E1", &[(0x2A, E1)], &[]),
+    ];
+    for &(name, packets, expected) in cases {
+        assert_eq!(keyboard(packets), expected, "{}", name);
+    }
+}
@@ -0,0 +1,435 @@
+#![cfg(target_os = "linux")]
+
+use std::{
+    f32::consts::FRAC_1_SQRT_2,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use windows_experiments::{
+    backend::{evdev::EvdevBackend, Capture, InputBackend},
+    ButtonState, DeviceDetails, DeviceHandle, DeviceKind, Error, Event, GamepadState, Hat,
+    HidUsage, InputEvent, KeyCode, MouseButton, WheelAxis,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+const KEY_ENTER: u16 = 28;
+const KEY_A: u16 = 30;
+const KEY_Z: u16 = 44;
+const KEY_SPACE: u16 = 57;
+const KEY_POWER: u16 = 116;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_TRIGGER: u16 = 0x120;
+const BTN_SOUTH: u16 = 0x130;
+const BTN_EAST: u16 = 0x131;
+const BTN_START: u16 = 0x13b;
+
+/// A `/dev/input` and `/sys/class/input` pair in a fresh temporary directory.
+struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("evdev-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("dev")).unwrap();
+        Self { root }
+    }
+
+    fn backend(&self) -> EvdevBackend {
+        EvdevBackend::with_dirs(self.root.join("dev"), self.root.join("sys"))
+    }
+
+    /// Declares `eventN` with its sysfs attributes and the records its node reads.
+    fn node(
+        &self,
+        number: usize,
+        name: &str,
+        rel: &[u16],
+        key: &[u16],
+        records: &[(u16, u16, i32)],
+    ) {
+        let device = self.root.join(format!("sys/event{}/device", number));
+        fs::create_dir_all(device.join("capabilities")).unwrap();
+        fs::create_dir_all(device.join("id")).unwrap();
+        fs::write(device.join("name"), format!("{}\n", name)).unwrap();
+        fs::write(device.join("capabilities/rel"), bitmap(rel)).unwrap();
+        fs::write(device.join("capabilities/key"), bitmap(key)).unwrap();
+        fs::write(device.join("id/vendor"), "046d\n").unwrap();
+        fs::write(device.join("id/product"), "c08b\n").unwrap();
+
+        let mut bytes = vec![];
+        for (i, &(kind, code, value)) in records.iter().enumerate() {
+            bytes.extend_from_slice(&1usize.to_ne_bytes());
+            bytes.extend_from_slice(&(i * 1000).to_ne_bytes());
+            bytes.extend_from_slice(&kind.to_ne_bytes());
+            bytes.extend_from_slice(&code.to_ne_bytes());
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        fs::write(self.root.join(format!("dev/event{}", number)), bytes).unwrap();
+    }
+
+    /// Declares `eventN` with absolute axes as well.
+    fn joystick(
+        &self,
+        number: usize,
+        name: &str,
+        abs: &[u16],
+        key: &[u16],
+        records: &[(u16, u16, i32)],
+    ) {
+        self.node(number, name, &[], key, records);
+        let device = self.root.join(format!("sys/event{}/device", number));
+        fs::write(device.join("capabilities/abs"), bitmap(abs)).unwrap();
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Formats a capability bitmap like sysfs does, as hex longs with the most significant first.
+fn bitmap(bits: &[u16]) -> String {
+    let width = usize::BITS as usize;
+    let mut words = vec![
+        0usize;
+        bits.iter()
+            .map(|&bit| bit as usize / width + 1)
+            .max()
+            .unwrap_or(1)
+    ];
+    for &bit in bits {
+        words[bit as usize / width] |= 1 << (bit as usize % width);
+    }
+    let words: Vec<_> = words
+        .iter()
+        .rev()
+        .map(|word| format!("{:x}", word))
+        .collect();
+    format!("{}\n", words.join(" "))
+}
+
+fn mouse_keys() -> Vec<u16> {
+    vec![BTN_LEFT, BTN_RIGHT]
+}
+
+fn keyboard_keys() -> Vec<u16> {
+    vec![KEY_ENTER, KEY_A, KEY_Z, KEY_SPACE]
+}
+
+/// Captures until every node has been read to its end, which reports it as removed.
+fn capture_all(backend: &EvdevBackend, hid_usages: &[HidUsage], nodes: usize) -> Vec<Event> {
+    let mut capture = backend.start_capture(hid_usages).unwrap();
+    let mut events = vec![];
+    let start = Instant::now();
+    while events
+        .iter()
+        .filter(|event: &&Event| event.kind == InputEvent::DeviceRemoved)
+        .count()
+        < nodes
+    {
+        assert!(start.elapsed() < TIMEOUT, "nodes never ended: {:?}", events);
+        capture
+            .poll_events(Duration::from_millis(10), &mut events)
+            .unwrap();
+    }
+    events
+}
+
+fn kinds(events: &[Event], device: usize) -> Vec<InputEvent> {
+    events
+        .iter()
+        .filter(|event| event.device == DeviceHandle(device))
+        .map(|event| event.kind.clone())
+        .collect()
+}
+
+#[test]
+fn classifies_nodes_by_capabilities() {
+    let fixture = Fixture::new("classify");
+    fixture.node(0, "Mouse", &[REL_X, REL_Y, REL_WHEEL], &mouse_keys(), &[]);
+    fixture.node(1, "Keyboard", &[], &keyboard_keys(), &[]);
+    fixture.node(2, "Power Button", &[], &[KEY_POWER], &[]);
+    // relative axes without a left button, e.g. a multimedia knob
+    fixture.node(3, "Knob", &[REL_X, REL_Y], &[], &[]);
+    let sticks = [ABS_X, ABS_Y];
+    fixture.joystick(4, "Gamepad", &sticks, &[BTN_SOUTH, BTN_EAST], &[]);
+    fixture.joystick(5, "Flight Stick", &sticks, &[BTN_TRIGGER], &[]);
+    // absolute axes without joystick buttons, e.g. a touchpad
+    fixture.joystick(6, "Touchpad", &sticks, &[BTN_LEFT], &[]);
+
+    let devices = fixture.backend().enumerate().unwrap();
+    let found: Vec<_> = devices
+        .iter()
+        .map(|device| {
+            (
+                device.handle,
+                device.kind,
+                device.info.product_name.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (DeviceHandle(0), DeviceKind::Mouse, "Mouse"),
+            (DeviceHandle(1), DeviceKind::Keyboard, "Keyboard"),
+            (DeviceHandle(4), DeviceKind::Hid, "Gamepad"),
+            (DeviceHandle(5), DeviceKind::Hid, "Flight Stick"),
+        ]
+    );
+    assert_eq!(devices[0].info.vendor_id, 0x046D);
+    assert_eq!(devices[0].info.product_id, 0xC08B);
+    match devices[0].info.details {
+        DeviceDetails::Mouse(details) => {
+            assert_eq!(details.buttons, 2);
+            assert!(!details.has_horizontal_wheel);
+        }
+        ref details => panic!("not a mouse: {:?}", details),
+    }
+    match devices[1].info.details {
+        DeviceDetails::Keyboard(details) => assert_eq!(details.total_keys, 4),
+        ref details => panic!("not a keyboard: {:?}", details),
+    }
+    assert_eq!(
+        devices[2].info.details,
+        DeviceDetails::Hid(HidUsage::GAMEPAD)
+    );
+    assert_eq!(
+        devices[3].info.details,
+        DeviceDetails::Hid(HidUsage::JOYSTICK)
+    );
+}
+
+#[test]
+fn coalesces_motion_until_sync() {
+    let fixture = Fixture::new("motion");
+    fixture.node(
+        0,
+        "Mouse",
+        &[REL_X, REL_Y],
+        &mouse_keys(),
+        &[
+            (EV_REL, REL_X, 3),
+            (EV_REL, REL_Y, -1),
+            (EV_REL, REL_X, 2),
+            (EV_SYN, 0, 0),
+            (EV_KEY, BTN_LEFT, 1),
+            (EV_SYN, 0, 0),
+            (EV_REL, REL_Y, 4),
+            (EV_KEY, BTN_LEFT, 0),
+            (EV_SYN, 0, 0),
+            // never synced
+            (EV_REL, REL_X, 9),
+        ],
+    );
+
+    let events = capture_all(&fixture.backend(), &[], 1);
+    assert_eq!(
+        kinds(&events, 0),
+        [
+            InputEvent::MouseMotion { dx: 5, dy: -1 },
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Pressed
+            },
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                state: ButtonState::Released
+            },
+            InputEvent::MouseMotion { dx: 0, dy: 4 },
+            InputEvent::DeviceRemoved,
+        ]
+    );
+    // the kernel's timestamps are kept
+    assert_eq!(events[0].timestamp.0, Duration::new(1, 3_000_000));
+}
+
+#[test]
+fn prefers_high_resolution_wheels() {
+    let fixture = Fixture::new("wheel");
+    let wheel = [
+        (EV_REL, REL_WHEEL_HI_RES, 60),
+        (EV_SYN, 0, 0),
+        (EV_REL, REL_WHEEL_HI_RES, 60),
+        (EV_REL, REL_WHEEL, 1),
+        (EV_SYN, 0, 0),
+        (EV_REL, REL_WHEEL, 1000),
+        (EV_SYN, 0, 0),
+    ];
+    fixture.node(
+        0,
+        "Low Res",
+        &[REL_X, REL_Y, REL_WHEEL],
+        &mouse_keys(),
+        &wheel,
+    );
+    fixture.node(
+        1,
+        "High Res",
+        &[REL_X, REL_Y, REL_WHEEL, REL_WHEEL_HI_RES],
+        &mouse_keys(),
+        &wheel,
+    );
+
+    let events = capture_all(&fixture.backend(), &[], 2);
+    let vertical = |delta| InputEvent::MouseWheel {
+        axis: WheelAxis::Vertical,
+        delta,
+    };
+    // without the high resolution axis advertised, both are passed on,
+    // and far scrolls saturate rather than wrap
+    assert_eq!(
+        kinds(&events, 0),
+        [
+            vertical(60),
+            vertical(60),
+            vertical(120),
+            vertical(i16::MAX),
+            InputEvent::DeviceRemoved,
+        ]
+    );
+    assert_eq!(
+        kinds(&events, 1),
+        [vertical(60), vertical(60), InputEvent::DeviceRemoved]
+    );
+}
+
+#[test]
+fn translates_key_codes_to_scancodes() {
+    let fixture = Fixture::new("keys");
+    fixture.node(
+        0,
+        "Keyboard",
+        &[],
+        &keyboard_keys(),
+        &[
+            (EV_KEY, KEY_A, 1),
+            (EV_SYN, 0, 0),
+            // autorepeat
+            (EV_KEY, KEY_A, 2),
+            (EV_SYN, 0, 0),
+            (EV_KEY, KEY_A, 0),
+            (EV_KEY, KEY_ENTER, 1),
+            (EV_SYN, 0, 0),
+        ],
+    );
+
+    let events = capture_all(&fixture.backend(), &[], 1);
+    let key = |key: KeyCode, state| InputEvent::Key {
+        scancode: key.scancode().unwrap(),
+        state,
+    };
+    assert_eq!(KeyCode::KeyA.scancode(), Some(0x1E));
+    assert_eq!(
+        kinds(&events, 0),
+        [
+            key(KeyCode::KeyA, ButtonState::Pressed),
+            key(KeyCode::KeyA, ButtonState::Released),
+            key(KeyCode::Enter, ButtonState::Pressed),
+            InputEvent::DeviceRemoved,
+        ]
+    );
+}
+
+#[test]
+fn reports_gamepad_input() {
+    let fixture = Fixture::new("gamepad");
+    fixture.joystick(
+        0,
+        "Gamepad",
+        &[ABS_X, ABS_Y, ABS_RZ, ABS_HAT0X, ABS_HAT0Y],
+        &[BTN_SOUTH, BTN_EAST, BTN_START],
+        &[
+            (EV_ABS, ABS_X, i16::MAX.into()),
+            (EV_ABS, ABS_Y, i16::MIN.into()),
+            (EV_KEY, BTN_EAST, 1),
+            (EV_KEY, BTN_START, 1),
+            (EV_ABS, ABS_HAT0X, 1),
+            (EV_ABS, ABS_HAT0Y, -1),
+            (EV_SYN, 0, 0),
+            // nothing changed
+            (EV_SYN, 0, 0),
+            (EV_KEY, BTN_START, 0),
+            (EV_ABS, ABS_HAT0Y, 0),
+            (EV_ABS, ABS_RZ, 40000),
+            (EV_SYN, 0, 0),
+        ],
+    );
+    let backend = fixture.backend();
+
+    // only read when asked for
+    let mut capture = backend.start_capture(&[HidUsage::JOYSTICK]).unwrap();
+    let mut events = vec![];
+    capture
+        .poll_events(Duration::from_millis(200), &mut events)
+        .unwrap();
+    assert!(events.is_empty(), "{:?}", events);
+    drop(capture);
+
+    let events = capture_all(&backend, &[HidUsage::GAMEPAD], 1);
+    let reports: Vec<_> = events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            InputEvent::HidReport { report } => Some(report.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        reports,
+        [
+            vec![0x06, 0xFF, 0x7F, 0x00, 0x80, 0x00, 0x00, 0x01],
+            // the axis is clamped, as fixtures have no ranges to scale from
+            vec![0x02, 0xFF, 0x7F, 0x00, 0x80, 0xFF, 0x7F, 0x02],
+        ]
+    );
+
+    let descriptor = backend.parse_report_descriptor(DeviceHandle(0)).unwrap();
+    let mut state = GamepadState::default();
+    state.deadzone = 0.0;
+    state.update(&descriptor.decode_input(&reports[0]));
+    assert_eq!(state.left_stick(), (FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+    assert_eq!(state.hat(), Hat::UpRight);
+    assert_eq!(state.pressed_buttons().collect::<Vec<_>>(), [2, 3]);
+    state.update(&descriptor.decode_input(&reports[1]));
+    assert_eq!(state.hat(), Hat::Right);
+    assert_eq!(state.pressed_buttons().collect::<Vec<_>>(), [2]);
+    assert_eq!(state.right_stick().1, 1.0);
+}
+
+#[test]
+fn fails_when_no_node_can_be_opened() {
+    let fixture = Fixture::new("unreadable");
+    fixture.node(0, "Mouse", &[REL_X, REL_Y], &mouse_keys(), &[]);
+    let node = fixture.root.join("dev/event0");
+    fs::remove_file(&node).unwrap();
+    std::os::unix::fs::symlink(fixture.root.join("missing"), &node).unwrap();
+
+    match fixture.backend().start_capture(&[]) {
+        Err(err) => assert_eq!(err, Error::Registration(2)),
+        Ok(_) => panic!("capture started without any readable node"),
+    }
+
+    // nothing to read is not a failure, new nodes are picked up later
+    fs::remove_file(&node).unwrap();
+    assert!(fixture.backend().start_capture(&[]).is_ok());
+}
@@ -0,0 +1,785 @@
+use std::{f32::consts::FRAC_1_SQRT_2, sync::mpsc, time::Duration};
+
+use windows_experiments::{
+    backend::mock::MockBackend,
+    hid::{
+        Collection, CollectionKind, DescriptorError, DescriptorErrorKind, Field, FieldValue, Item,
+        ReportDescriptor, ReportKind, UsageRange,
+    },
+    DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Devices, Error, Gamepad, Hat, HidUsage,
+    InputEvent, Timestamp,
+};
+
+/// The boot protocol mouse from the HID specification, appendix E.10.
+const MOUSE: &[u8] = &[
+    0x05, 0x01, 0x09, 0x02, 0xA1, 0x01, 0x09, 0x01, 0xA1, 0x00, 0x05, 0x09, 0x19, 0x01, 0x29, 0x03,
+    0x15, 0x00, 0x25, 0x01, 0x95, 0x03, 0x75, 0x01, 0x81, 0x02, 0x95, 0x01, 0x75, 0x05, 0x81, 0x01,
+    0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x81, 0x25, 0x7F, 0x75, 0x08, 0x95, 0x02, 0x81, 0x06,
+    0xC0, 0xC0,
+];
+
+/// A gamepad with ten buttons, two axes and a hat switch in input report 1,
+/// and a vendor-defined output report 2.
+const GAMEPAD: &[u8] = &[
+    0x05, 0x01, 0x09, 0x05, 0xA1, 0x01, 0x85, 0x01, // application collection, report 1
+    0x05, 0x09, 0x19, 0x01, 0x29, 0x0A, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x0A, 0x81,
+    0x02, // buttons 1 to 10
+    0x75, 0x06, 0x95, 0x01, 0x81, 0x03, // padding
+    0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x75, 0x08, 0x95, 0x02, 0x81,
+    0x02, // X and Y
+    0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x35, 0x00, 0x46, 0x3B, 0x01, 0x65, 0x14, 0x75, 0x04, 0x95,
+    0x01, 0x81, 0x42, // hat switch in degrees
+    0x75, 0x04, 0x95, 0x01, 0x81, 0x03, // padding
+    0x85, 0x02, 0x06, 0x00, 0xFF, 0x09, 0x01, 0x15, 0x00, 0x25, 0xFF, 0x75, 0x08, 0x95, 0x04, 0x91,
+    0x02, // vendor output
+    0xC0,
+];
+
+#[test]
+fn parses_boot_mouse() {
+    let descriptor = ReportDescriptor::parse(MOUSE).unwrap();
+
+    let [Item::Collection(application)] = descriptor.items() else {
+        panic!("expected one collection: {:?}", descriptor.items());
+    };
+    assert_eq!(application.kind, CollectionKind::Application);
+    assert_eq!(application.usage, Some(HidUsage::MOUSE));
+    let [Item::Collection(pointer)] = application.items.as_slice() else {
+        panic!("expected the pointer collection: {:?}", application.items);
+    };
+    assert_eq!(pointer.kind, CollectionKind::Physical);
+    assert_eq!(pointer.usage, Some(HidUsage::new(0x01, 0x01)));
+
+    let fields: Vec<_> = descriptor.fields().collect();
+    assert_eq!(fields.len(), 3);
+    let (buttons, padding, axes) = (fields[0], fields[1], fields[2]);
+    assert_eq!((buttons.bit_offset, buttons.bit_len()), (0, 3));
+    assert!(buttons.is_variable() && !buttons.is_constant());
+    assert_eq!(
+        buttons.usages,
+        [UsageRange {
+            page: 0x09,
+            min: 1,
+            max: 3
+        }]
+    );
+    assert_eq!((padding.bit_offset, padding.bit_len()), (3, 5));
+    assert!(padding.is_constant());
+    assert_eq!(
+        (axes.bit_offset, axes.report_size, axes.report_count),
+        (8, 8, 2)
+    );
+    assert!(axes.is_relative());
+    assert_eq!((axes.logical_min, axes.logical_max), (-127, 127));
+    assert_eq!((axes.physical_min, axes.physical_max), (-127, 127));
+    assert_eq!(axes.usage(1), Some(HidUsage::new(0x01, 0x31)));
+    assert_eq!(axes.usage(2), None);
+
+    assert!(!descriptor.uses_report_ids());
+    assert_eq!(descriptor.report_len(ReportKind::Input, None), Some(3));
+    assert_eq!(descriptor.report_len(ReportKind::Output, None), None);
+}
+
+#[test]
+fn parses_report_ids_ranges_and_units() {
+    let descriptor = ReportDescriptor::parse(GAMEPAD).unwrap();
+
+    let [Item::Collection(application)] = descriptor.items() else {
+        panic!("expected one collection: {:?}", descriptor.items());
+    };
+    assert_eq!(application.usage, Some(HidUsage::GAMEPAD));
+    let fields: Vec<_> = descriptor.fields().collect();
+    let offsets: Vec<_> = fields
+        .iter()
+        .map(|field| (field.kind, field.report_id, field.bit_offset))
+        .collect();
+    assert_eq!(
+        offsets,
+        [
+            (ReportKind::Input, Some(1), 0),
+            (ReportKind::Input, Some(1), 10),
+            (ReportKind::Input, Some(1), 16),
+            (ReportKind::Input, Some(1), 32),
+            (ReportKind::Input, Some(1), 36),
+            (ReportKind::Output, Some(2), 0),
+        ]
+    );
+    assert_eq!(fields[0].usage(9), Some(HidUsage::new(0x09, 10)));
+    // a one byte maximum of FF is 255 as long as the minimum is not negative
+    assert_eq!((fields[2].logical_min, fields[2].logical_max), (0, 255));
+    assert_eq!((fields[5].logical_min, fields[5].logical_max), (0, 255));
+
+    let hat = fields[3];
+    assert!(hat.has_null_state());
+    assert_eq!(hat.usage(0), Some(HidUsage::new(0x01, 0x39)));
+    assert_eq!((hat.logical_min, hat.logical_max), (0, 7));
+    assert_eq!((hat.physical_min, hat.physical_max), (0, 315));
+    assert_eq!((hat.unit, hat.unit_exponent), (0x14, 0));
+
+    assert!(descriptor.uses_report_ids());
+    assert_eq!(descriptor.report_len(ReportKind::Input, Some(1)), Some(6));
+    assert_eq!(descriptor.report_len(ReportKind::Output, Some(2)), Some(5));
+    assert_eq!(descriptor.report_len(ReportKind::Input, Some(2)), None);
+}
+
+#[test]
+fn applies_push_pop_and_extended_usages() {
+    let descriptor = ReportDescriptor::parse(&[
+        0x05, 0x01, // usage page generic desktop
+        0xA4, // push
+        0x05, 0x09, 0x09, 0x01, // button 1
+        0x55, 0x0E, // unit exponent -2
+        0xB4, // pop
+        0x09, 0x30, // X
+        0x0B, 0x01, 0x00, 0x0C, 0x00, // consumer control
+        0x75, 0x08, 0x95, 0x03, 0x81, 0x02,
+    ])
+    .unwrap();
+
+    let field = descriptor.fields().next().unwrap();
+    let usages: Vec<_> = (0..4).map(|index| field.usage(index)).collect();
+    assert_eq!(
+        usages,
+        [
+            Some(HidUsage::new(0x09, 0x01)),
+            Some(HidUsage::new(0x01, 0x30)),
+            Some(HidUsage::CONSUMER_CONTROL),
+            None,
+        ]
+    );
+    assert_eq!(field.unit_exponent, 0);
+}
+
+#[test]
+fn rejects_malformed_descriptors() {
+    use DescriptorErrorKind::*;
+
+    let too_deep: Vec<u8> = [0xA1, 0x00].repeat(33);
+    let cases: [(&[u8], usize, DescriptorErrorKind); 8] = [
+        (&[0x05], 0, Truncated),
+        (&[0x05, 0x01, 0x27, 0x01, 0x00], 2, Truncated),
+        (&[0xFE, 0x05, 0x00, 0x01], 0, Truncated),
+        (&[0xA1, 0x01, 0xA1, 0x00, 0xC0], 5, UnclosedCollection),
+        (&[0x81, 0x02, 0xC0], 2, UnmatchedEndCollection),
+        (&[0xA4, 0xB4, 0xB4], 2, UnmatchedPop),
+        (&[0x85, 0x00], 0, InvalidReportId),
+        (
+            &[0x76, 0xFF, 0xFF, 0x96, 0xFF, 0xFF, 0x81, 0x02],
+            6,
+            ReportTooLong,
+        ),
+    ];
+    for (bytes, offset, kind) in cases {
+        assert_eq!(
+            ReportDescriptor::parse(bytes),
+            Err(DescriptorError { offset, kind }),
+            "{:02X?}",
+            bytes
+        );
+    }
+    assert_eq!(
+        ReportDescriptor::parse(&too_deep).unwrap_err(),
+        DescriptorError {
+            offset: 64,
+            kind: TooDeep
+        }
+    );
+}
+
+/// The usages and values decoded from a report.
+fn decode(descriptor: &ReportDescriptor, report: &[u8]) -> Vec<(u16, u16, i32)> {
+    descriptor
+        .decode_input(report)
+        .iter()
+        .map(|value| (value.usage.page, value.usage.id, value.value))
+        .collect()
+}
+
+#[test]
+fn decodes_variable_fields() {
+    let mouse = ReportDescriptor::parse(MOUSE).unwrap();
+    assert_eq!(
+        decode(&mouse, &[0x05, 0xFE, 0x03]),
+        [
+            (0x09, 1, 1),
+            (0x09, 2, 0),
+            (0x09, 3, 1),
+            (0x01, 0x30, -2),
+            (0x01, 0x31, 3)
+        ]
+    );
+
+    let gamepad = ReportDescriptor::parse(GAMEPAD).unwrap();
+    let report = [0x01, 0x01, 0xFE, 0x80, 0xFF, 0xF2];
+    let values = gamepad.decode_input(&report);
+    let pressed: Vec<_> = values[..10]
+        .iter()
+        .filter(|value| value.value == 1)
+        .map(|value| value.usage.id)
+        .collect();
+    // the padding bits set in the second byte are not buttons
+    assert_eq!(pressed, [1, 10]);
+    assert_eq!(
+        values[10..],
+        [
+            FieldValue {
+                usage: HidUsage::new(0x01, 0x30),
+                value: 128,
+                logical_min: 0,
+                logical_max: 255,
+                physical: Some(128.0),
+                unit: 0,
+            },
+            FieldValue {
+                usage: HidUsage::new(0x01, 0x31),
+                value: 255,
+                logical_min: 0,
+                logical_max: 255,
+                physical: Some(255.0),
+                unit: 0,
+            },
+            FieldValue {
+                usage: HidUsage::new(0x01, 0x39),
+                value: 2,
+                logical_min: 0,
+                logical_max: 7,
+                physical: Some(90.0),
+                unit: 0x14,
+            },
+        ]
+    );
+    // a hat switch in its null state points nowhere
+    let centered = gamepad.decode_input(&[0x01, 0x00, 0x00, 0x80, 0x80, 0x08]);
+    assert_eq!((centered[12].value, centered[12].physical), (8, None));
+    // short reports lose the fields that don't fit, other reports have no input fields
+    assert_eq!(gamepad.decode_input(&report[..4]).len(), 11);
+    assert!(gamepad
+        .decode_input(&[0x02, 0x01, 0x02, 0x03, 0x04])
+        .is_empty());
+    assert!(gamepad.decode_input(&[]).is_empty());
+}
+
+#[test]
+fn decodes_array_fields_and_units() {
+    // a keyboard's array of up to three held keys
+    let keys = ReportDescriptor::parse(&[
+        0x05, 0x07, 0x19, 0x00, 0x29, 0x65, 0x15, 0x00, 0x25, 0x65, 0x75, 0x08, 0x95, 0x03, 0x81,
+        0x00,
+    ])
+    .unwrap();
+    assert_eq!(
+        decode(&keys, &[0x04, 0x00, 0x1E]),
+        [(0x07, 0x04, 1), (0x07, 0x1E, 1)]
+    );
+    // out of the logical range
+    assert_eq!(decode(&keys, &[0x66, 0xFF, 0x00]), []);
+
+    // a throttle reporting 0 to 10 m/s in hundredths
+    let throttle = ReportDescriptor::parse(&[
+        0x05, 0x02, 0x09, 0xBB, 0x15, 0x00, 0x26, 0xFF, 0x00, 0x35, 0x00, 0x46, 0xE8, 0x03, 0x66,
+        0x11, 0xF0, 0x55, 0x0E, 0x75, 0x08, 0x95, 0x01, 0x81, 0x02,
+    ])
+    .unwrap();
+    let [value] = throttle.decode_input(&[0xFF])[..] else {
+        panic!("expected one value");
+    };
+    assert_eq!(
+        (value.usage, value.value, value.unit),
+        (HidUsage::new(0x02, 0xBB), 255, 0xF011)
+    );
+    assert!((value.physical.unwrap() - 10.0).abs() < 1e-9);
+}
+
+/// xorshift64, so that failures can be reproduced from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[test]
+fn survives_random_descriptors() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for round in 0..20_000 {
+        let bytes: Vec<u8> = if round % 2 == 0 {
+            (0..rng.below(96)).map(|_| rng.next() as u8).collect()
+        } else {
+            // mutations of valid descriptors get further into the parser
+            let mut bytes = [MOUSE, GAMEPAD][rng.below(2)].to_vec();
+            for _ in 0..1 + rng.below(4) {
+                let at = rng.below(bytes.len() + 1);
+                match rng.below(3) {
+                    0 if at < bytes.len() => bytes[at] = rng.next() as u8,
+                    1 if at < bytes.len() => {
+                        bytes.remove(at);
+                    }
+                    _ => bytes.insert(at, rng.next() as u8),
+                }
+            }
+            bytes
+        };
+
+        let Ok(descriptor) = ReportDescriptor::parse(&bytes) else {
+            continue;
+        };
+        for field in descriptor.fields() {
+            let len = descriptor
+                .report_len(field.kind, field.report_id)
+                .unwrap_or_else(|| panic!("no report for {:?} in {:02X?}", field, bytes));
+            let id_bits = field.report_id.map_or(0, |_| 8);
+            assert!(
+                field.bit_offset + field.bit_len() + id_bits <= len as u32 * 8,
+                "{:?} outside its report in {:02X?}",
+                field,
+                bytes
+            );
+        }
+        // reports of any length and content must decode without panicking
+        let report: Vec<u8> = (0..rng.below(16)).map(|_| rng.next() as u8).collect();
+        descriptor.decode_input(&report);
+    }
+}
+
+#[test]
+fn survives_random_preparsed_data() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    let valid = [gamepad_preparsed().1, keyboard_preparsed()];
+    for _ in 0..20_000 {
+        let mut data = valid[rng.below(2)].clone();
+        for _ in 0..1 + rng.below(4) {
+            if data.is_empty() {
+                break;
+            }
+            let at = rng.below(data.len());
+            match rng.below(3) {
+                0 => data[at] = rng.next() as u8,
+                // whole counts, offsets and usages break more than single bits
+                1 if at + 1 < data.len() => {
+                    data[at..at + 2].copy_from_slice(&(rng.next() as u16).to_le_bytes())
+                }
+                _ => data.truncate(at),
+            }
+        }
+
+        let Ok(descriptor) = ReportDescriptor::from_preparsed(&data) else {
+            continue;
+        };
+        for field in descriptor.fields() {
+            let len = descriptor
+                .report_len(field.kind, field.report_id)
+                .unwrap_or_else(|| panic!("no report for {:?}", field));
+            let id_bits = field.report_id.map_or(0, |_| 8);
+            assert!(
+                field.bit_offset + field.bit_len() + id_bits <= len as u32 * 8,
+                "{:?} outside its report",
+                field
+            );
+        }
+        // reports of any length and content must decode without panicking
+        let report: Vec<u8> = (0..rng.below(16)).map(|_| rng.next() as u8).collect();
+        descriptor.decode_input(&report);
+    }
+}
+
+fn write(bytes: &mut [u8], offset: usize, value: &[u8]) {
+    bytes[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// Lays out a cap for `field` the way hidapi documents Windows' preparsed data.
+/// One-bit variable fields and array fields become button caps, others value caps.
+fn cap(field: &Field, link_collection: u16, data_index: u16) -> Vec<u8> {
+    let mut cap = vec![0; 104];
+    let usage = field.usages[0];
+    let button = field.report_size == 1 || !field.is_variable();
+    write(&mut cap, 0, &usage.page.to_le_bytes());
+    cap[2] = field.report_id.unwrap_or(0);
+    cap[3] = (field.bit_offset % 8) as u8;
+    write(&mut cap, 4, &(field.report_size as u16).to_le_bytes());
+    write(&mut cap, 6, &(field.report_count as u16).to_le_bytes());
+    write(
+        &mut cap,
+        8,
+        &(field.bit_offset as u16 / 8 + 1).to_le_bytes(),
+    );
+    write(&mut cap, 10, &(field.bit_len() as u16).to_le_bytes());
+    write(&mut cap, 12, &field.flags.to_le_bytes());
+    write(&mut cap, 18, &link_collection.to_le_bytes());
+    cap[24] = if button { 0x04 } else { 0 } | if usage.min != usage.max { 0x10 } else { 0 };
+    write(&mut cap, 60, &usage.min.to_le_bytes());
+    write(&mut cap, 62, &usage.max.to_le_bytes());
+    write(&mut cap, 72, &data_index.to_le_bytes());
+    write(
+        &mut cap,
+        74,
+        &(data_index + usage.max - usage.min).to_le_bytes(),
+    );
+    if !button {
+        write(&mut cap, 80, &field.logical_min.to_le_bytes());
+        write(&mut cap, 84, &field.logical_max.to_le_bytes());
+        write(&mut cap, 88, &field.physical_min.to_le_bytes());
+        write(&mut cap, 92, &field.physical_max.to_le_bytes());
+    } else if !field.is_variable() {
+        // variable buttons leave their range out
+        write(&mut cap, 76, &field.logical_min.to_le_bytes());
+        write(&mut cap, 80, &field.logical_max.to_le_bytes());
+    }
+    write(&mut cap, 96, &field.unit.to_le_bytes());
+    write(
+        &mut cap,
+        100,
+        &(field.unit_exponent as u32 & 0x0F).to_le_bytes(),
+    );
+    cap
+}
+
+/// Lays out a link collection node, whose children are chained from `first_child` on.
+fn node(usage: HidUsage, kind: u8, children: u16, next_sibling: u16, first_child: u16) -> Vec<u8> {
+    let mut node = vec![0; 16];
+    write(&mut node, 0, &usage.id.to_le_bytes());
+    write(&mut node, 2, &usage.page.to_le_bytes());
+    write(&mut node, 6, &children.to_le_bytes());
+    write(&mut node, 8, &next_sibling.to_le_bytes());
+    write(&mut node, 10, &first_child.to_le_bytes());
+    node[12] = kind;
+    node
+}
+
+/// Puts the input, output and feature caps and the link collection nodes behind the
+/// preparsed data header.
+///
+/// No preparsed data captured from a device was at hand, so the tests build their own
+/// from the layout hidapi worked out.
+fn preparsed(caps: [Vec<Vec<u8>>; 3], nodes: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = b"HidP KDR".to_vec();
+    data.resize(44, 0);
+    let mut first = 0;
+    for (index, caps) in caps.iter().enumerate() {
+        let last = first + caps.len() as u16;
+        write(&mut data, 16 + 8 * index, &first.to_le_bytes());
+        write(
+            &mut data,
+            18 + 8 * index,
+            &(caps.len() as u16).to_le_bytes(),
+        );
+        write(&mut data, 20 + 8 * index, &last.to_le_bytes());
+        first = last;
+    }
+    write(&mut data, 40, &(first * 104).to_le_bytes());
+    write(&mut data, 42, &(nodes.len() as u16).to_le_bytes());
+    data.extend(caps.concat().concat());
+    data.extend(nodes.concat());
+    data
+}
+
+/// The preparsed data of [`GAMEPAD`], with the fields it declares.
+fn gamepad_preparsed() -> (Vec<Field>, Vec<u8>) {
+    let parsed = ReportDescriptor::parse(GAMEPAD).unwrap();
+    // Windows leaves padding out and splits X and Y into a cap each
+    let mut fields = vec![];
+    for field in parsed.fields().filter(|field| !field.is_constant()) {
+        for (index, &usage) in field.usages.iter().enumerate() {
+            fields.push(Field {
+                bit_offset: field.bit_offset + index as u32 * field.report_size,
+                report_count: field.report_count / field.usages.len() as u32,
+                usages: vec![usage],
+                ..field.clone()
+            });
+        }
+    }
+    let mut caps = [vec![], vec![], vec![]];
+    let mut data_index = [0; 3];
+    for field in &fields {
+        let kind = field.kind as usize;
+        caps[kind].push(cap(field, 0, data_index[kind]));
+        data_index[kind] += field.usages[0].max - field.usages[0].min + 1;
+    }
+    let data = preparsed(caps, &[node(HidUsage::GAMEPAD, 0x01, 0, 0, 0)]);
+    (fields, data)
+}
+
+#[test]
+fn rebuilds_descriptors_from_preparsed_data() {
+    let parsed = ReportDescriptor::parse(GAMEPAD).unwrap();
+    let (fields, data) = gamepad_preparsed();
+    let rebuilt = ReportDescriptor::from_preparsed(&data).unwrap();
+    let [Item::Collection(application)] = rebuilt.items() else {
+        panic!("expected one collection: {:?}", rebuilt.items());
+    };
+    assert_eq!(application.kind, CollectionKind::Application);
+    assert_eq!(application.usage, Some(HidUsage::GAMEPAD));
+    assert_eq!(rebuilt.fields().cloned().collect::<Vec<_>>(), fields);
+    assert!(rebuilt.uses_report_ids());
+    for (kind, id) in [(ReportKind::Input, 1), (ReportKind::Output, 2)] {
+        assert_eq!(
+            rebuilt.report_len(kind, Some(id)),
+            parsed.report_len(kind, Some(id))
+        );
+    }
+    let report = [0x01, 0x05, 0x02, 0xFF, 0x00, 0x02];
+    assert_eq!(rebuilt.decode_input(&report), parsed.decode_input(&report));
+}
+
+/// Removes the padding Windows doesn't keep.
+fn without_padding(items: &[Item]) -> Vec<Item> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Field(field) if field.is_constant() => None,
+            Item::Field(_) => Some(item.clone()),
+            Item::Collection(collection) => Some(Item::Collection(Collection {
+                items: without_padding(&collection.items),
+                ..collection.clone()
+            })),
+        })
+        .collect()
+}
+
+/// A keyboard whose key array and LEDs are in collections of their own.
+const KEYBOARD: &[u8] = &[
+    0x05, 0x01, 0x09, 0x06, 0xA1, 0x01, // application collection
+    0x05, 0x07, 0x19, 0xE0, 0x29, 0xE7, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x08, 0x81,
+    0x02, // modifiers
+    0x09, 0x01, 0xA1, 0x02, 0x19, 0x04, 0x29, 0x1D, 0x19, 0xE0, 0x29, 0xE7, 0x25, 0x21, 0x75, 0x08,
+    0x95, 0x02, 0x81, 0x00, 0xC0, // two keys, letters or modifiers
+    0x05, 0x08, 0x09, 0x4B, 0xA1, 0x02, 0x19, 0x01, 0x29, 0x05, 0x25, 0x01, 0x75, 0x01, 0x95, 0x05,
+    0x91, 0x02, 0x95, 0x03, 0x91, 0x03, 0xC0, // LEDs and padding
+    0xC0,
+];
+
+/// The preparsed data of [`KEYBOARD`].
+fn keyboard_preparsed() -> Vec<u8> {
+    let parsed = ReportDescriptor::parse(KEYBOARD).unwrap();
+    let fields: Vec<_> = parsed.fields().collect();
+    let (modifiers, keys, leds) = (fields[0], fields[1], fields[2]);
+    let letters = Field {
+        usages: vec![keys.usages[0]],
+        ..keys.clone()
+    };
+    let other_modifiers = Field {
+        usages: vec![keys.usages[1]],
+        ..keys.clone()
+    };
+    preparsed(
+        [
+            // the caps of an array come last declared first
+            vec![
+                cap(modifiers, 0, 0),
+                cap(&other_modifiers, 1, 34),
+                cap(&letters, 1, 8),
+            ],
+            vec![cap(leds, 2, 0)],
+            vec![],
+        ],
+        &[
+            node(HidUsage::new(0x01, 0x06), 0x01, 2, 0, 2),
+            node(HidUsage::new(0x07, 0x01), 0x02, 0, 0, 0),
+            // chained first as it was declared last
+            node(HidUsage::new(0x08, 0x4B), 0x02, 0, 1, 0),
+        ],
+    )
+}
+
+#[test]
+fn merges_array_caps_and_orders_collections() {
+    let parsed = ReportDescriptor::parse(KEYBOARD).unwrap();
+    let rebuilt = ReportDescriptor::from_preparsed(&keyboard_preparsed()).unwrap();
+    assert_eq!(rebuilt.items(), without_padding(parsed.items()));
+    assert!(!rebuilt.uses_report_ids());
+    assert_eq!(rebuilt.report_len(ReportKind::Input, None), Some(3));
+    assert_eq!(rebuilt.report_len(ReportKind::Output, None), Some(1));
+    assert_eq!(
+        decode(&rebuilt, &[0x02, 0x00, 0x1B]),
+        [
+            (0x07, 0xE0, 0),
+            (0x07, 0xE1, 1),
+            (0x07, 0xE2, 0),
+            (0x07, 0xE3, 0),
+            (0x07, 0xE4, 0),
+            (0x07, 0xE5, 0),
+            (0x07, 0xE6, 0),
+            (0x07, 0xE7, 0),
+            (0x07, 0x04, 1),
+            (0x07, 0xE1, 1),
+        ]
+    );
+}
+
+#[test]
+fn rejects_malformed_preparsed_data() {
+    use DescriptorErrorKind::*;
+
+    let data = keyboard_preparsed();
+    let nodes = data.len() - 3 * 16;
+    let error = |offset, kind| Err(DescriptorError { offset, kind });
+    assert_eq!(
+        ReportDescriptor::from_preparsed(KEYBOARD),
+        error(0, NotPreparsedData)
+    );
+    assert_eq!(
+        ReportDescriptor::from_preparsed(&data[..100]),
+        error(100, Truncated)
+    );
+
+    // a cap in a collection that doesn't exist
+    let mut stray = data.clone();
+    stray[44 + 18] = 3;
+    assert_eq!(
+        ReportDescriptor::from_preparsed(&stray),
+        error(44, NotPreparsedData)
+    );
+    // a collection that is its own sibling
+    let mut looped = data.clone();
+    looped[nodes + 2 * 16 + 8] = 2;
+    assert_eq!(
+        ReportDescriptor::from_preparsed(&looped),
+        error(nodes + 2 * 16, NotPreparsedData)
+    );
+    // a usage range running backwards
+    let mut backwards = data.clone();
+    backwards[44 + 60..44 + 62].copy_from_slice(&0xE8u16.to_le_bytes());
+    assert_eq!(
+        ReportDescriptor::from_preparsed(&backwards),
+        error(44, NotPreparsedData)
+    );
+    // a cap ending far beyond any report
+    let mut long = data;
+    long[44 + 6..44 + 8].copy_from_slice(&u16::MAX.to_le_bytes());
+    long[44 + 4..44 + 6].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert_eq!(
+        ReportDescriptor::from_preparsed(&long),
+        error(44, ReportTooLong)
+    );
+}
+
+#[test]
+fn reads_descriptors_from_the_backend() {
+    let backend = MockBackend::new();
+    backend.add_hid("Gamepad", DeviceHandle(1), HidUsage::GAMEPAD);
+    backend.add_hid("Broken", DeviceHandle(2), HidUsage::GAMEPAD);
+    backend.set_report_descriptor(DeviceHandle(1), GAMEPAD);
+    backend.set_report_descriptor(DeviceHandle(2), &GAMEPAD[..GAMEPAD.len() - 1]);
+
+    let devices = Devices::with_backend(backend);
+    let descriptor = devices.report_descriptor(DeviceHandle(1)).unwrap();
+    assert_eq!(descriptor, ReportDescriptor::parse(GAMEPAD).unwrap());
+    assert_eq!(
+        devices.report_descriptor(DeviceHandle(2)).unwrap_err(),
+        Error::Descriptor(DescriptorError {
+            offset: GAMEPAD.len() - 1,
+            kind: DescriptorErrorKind::UnclosedCollection,
+        })
+    );
+    assert_eq!(
+        devices.report_descriptor(DeviceHandle(3)).unwrap_err(),
+        Error::Unsupported
+    );
+}
+
+#[test]
+fn delivers_decoded_reports() {
+    let backend = MockBackend::new();
+    backend.add_hid("Gamepad", DeviceHandle(1), HidUsage::GAMEPAD);
+    backend.add_hid("Unknown Gamepad", DeviceHandle(2), HidUsage::GAMEPAD);
+    backend.set_report_descriptor(DeviceHandle(1), GAMEPAD);
+
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_hid_devices(HidUsage::GAMEPAD).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    devices.on_hid_input(None, move |id, values| {
+        sender.send((id.clone(), values.to_vec())).unwrap()
+    });
+    let listener = devices.start_listening_with(|_| {}).unwrap();
+
+    // the device without a descriptor is skipped
+    for handle in [2, 1] {
+        backend.push_event(
+            DeviceHandle(handle),
+            Timestamp::default(),
+            InputEvent::HidReport {
+                report: vec![0x01, 0x00, 0x00, 0x80, 0x40, 0x06],
+            },
+        );
+    }
+    let (id, values) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(id, devices.hid_devices()[0].id);
+    assert_eq!(values.len(), 13);
+    assert_eq!(values[11].value, 0x40);
+    assert_eq!(values[12].physical, Some(270.0));
+    listener.stop();
+    listener.join().unwrap();
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn tracks_gamepad_state() {
+    let backend = MockBackend::new();
+    backend.add_hid("Mouse", DeviceHandle(1), HidUsage::MOUSE);
+    let info = DeviceInfo {
+        product_name: "Controller".to_string(),
+        vendor_id: 0x045E,
+        product_id: 0x0B12,
+        details: DeviceDetails::Hid(HidUsage::GAMEPAD),
+        ..Default::default()
+    };
+    backend.add_device(DeviceKind::Hid, DeviceHandle(2), info);
+    backend.add_hid("Joystick", DeviceHandle(3), HidUsage::JOYSTICK);
+    backend.set_report_descriptor(DeviceHandle(2), GAMEPAD);
+
+    let gamepads = windows_experiments::get_devices_from::<Gamepad, _>(&backend).unwrap();
+    assert_eq!(gamepads.len(), 2);
+    assert_eq!(gamepads[0].layout().name, "Xbox Series X|S Controller");
+    assert_eq!(gamepads[1].layout().name, "Generic Gamepad");
+
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_gamepads().unwrap();
+    let mut handles: Vec<_> = devices
+        .gamepads()
+        .iter()
+        .map(|gamepad| gamepad.handle)
+        .collect();
+    // added by usage, joysticks first
+    handles.sort();
+    assert_eq!(handles, [DeviceHandle(2), DeviceHandle(3)]);
+    assert!(devices.modify_gamepad_state(DeviceHandle(2), |state| state.deadzone = 0.0));
+    assert!(!devices.modify_gamepad_state(DeviceHandle(1), |state| state.deadzone = 0.0));
+
+    let (sender, receiver) = mpsc::channel();
+    devices.on_hid_input(None, move |_, _| sender.send(()).unwrap());
+    let listener = devices.start_listening_with(|_| {}).unwrap();
+    assert_eq!(
+        backend.hid_usages(),
+        [HidUsage::JOYSTICK, HidUsage::GAMEPAD]
+    );
+    backend.push_event(
+        DeviceHandle(2),
+        Timestamp::default(),
+        InputEvent::HidReport {
+            report: vec![0x01, 0x05, 0x02, 0xFF, 0x00, 0x02],
+        },
+    );
+    // handlers run once the state is updated
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    let state = devices.gamepad_state(DeviceHandle(2)).unwrap();
+    assert_eq!(state.left_stick(), (FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+    assert_eq!(state.hat(), Hat::Right);
+    assert_eq!(state.pressed_buttons().collect::<Vec<_>>(), [1, 3, 10]);
+    assert_eq!(state.layout().button_name(3), Some("X"));
+    // axes are numbered as the descriptor declares them
+    assert_eq!(state.raw_axis(1), Some(-1.0));
+    assert_eq!(state.raw_axis(2), None);
+    assert_eq!(
+        devices.gamepad_state(DeviceHandle(3)),
+        Some(Default::default())
+    );
+    listener.stop();
+    listener.join().unwrap();
+}
@@ -0,0 +1,181 @@
+use windows_experiments::{
+    backend::{mock::MockBackend, RawDevice},
+    get_devices_from, Anchor, DeviceHandle, DeviceId, DeviceInfo, DeviceKind, Devices, Mouse,
+};
+
+fn mouse(path: &str, serial: Option<&str>, location: Option<&str>) -> RawDevice {
+    RawDevice {
+        handle: DeviceHandle(0),
+        kind: DeviceKind::Mouse,
+        info: DeviceInfo {
+            product_name: "Mouse".to_string(),
+            vendor_id: 0x046d,
+            product_id: 0xc08b,
+            serial_number: serial.map(str::to_string),
+            location: location.map(str::to_string),
+            path: path.to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+#[test]
+fn prefers_serial_over_location() {
+    let ids = DeviceId::assign(&[
+        mouse("a", Some("1A2B"), Some("usb-1")),
+        mouse("b", None, Some("usb-2")),
+        mouse("c", None, None),
+    ]);
+    let anchors: Vec<_> = ids.iter().map(|id| id.anchor.clone()).collect();
+    assert_eq!(
+        anchors,
+        [
+            Anchor::Serial("1A2B".to_string()),
+            Anchor::Location("usb-2".to_string()),
+            Anchor::None,
+        ]
+    );
+}
+
+#[test]
+fn numbers_identical_devices_by_path() {
+    let ids = DeviceId::assign(&[
+        mouse("c", None, None),
+        mouse("a", None, None),
+        mouse("b", None, Some("usb-1")),
+        mouse("b", None, None),
+    ]);
+    let instances: Vec<_> = ids.iter().map(|id| id.instance).collect();
+    assert_eq!(instances, [2, 0, 0, 1]);
+}
+
+#[test]
+fn round_trips_through_strings() {
+    let ids = DeviceId::assign(&[
+        mouse("a", Some("odd#serial%"), None),
+        mouse("b", None, Some("usb-0000:00:14.0-2/input0")),
+        mouse("c", None, None),
+        mouse("d", None, None),
+    ]);
+    assert_eq!(ids[0].to_string(), "mouse:046d:c08b:serial=odd%23serial%25");
+    assert_eq!(ids[3].to_string(), "mouse:046d:c08b#1");
+    for id in ids {
+        assert_eq!(id.to_string().parse::<DeviceId>(), Ok(id));
+    }
+    for invalid in [
+        "",
+        "pen:046d:c08b",
+        "mouse:046d",
+        "mouse:046d:c08b:x=1",
+        "mouse:1:2#x",
+    ] {
+        assert!(invalid.parse::<DeviceId>().is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn matches_saved_ids() {
+    let saved = DeviceId::assign(&[
+        mouse("a", None, Some("usb-1")),
+        mouse("b", None, Some("usb-2")),
+    ]);
+    let connected = DeviceId::assign(&[
+        mouse("x", None, Some("usb-2")),
+        mouse("y", None, Some("usb-1")),
+    ]);
+    assert_eq!(saved[0].best_match(&connected), Some(1));
+    assert_eq!(saved[1].best_match(&connected), Some(0));
+
+    // a device that moved ports is found as long as it is the only one of its model
+    let moved = DeviceId::assign(&[mouse("z", None, Some("usb-3"))]);
+    assert_eq!(saved[0].best_match(&moved), Some(0));
+    let mut ambiguous = connected.clone();
+    ambiguous.extend(moved);
+    let gone = DeviceId::new(DeviceKind::Mouse, &mouse("", None, Some("usb-9")).info);
+    assert_eq!(gone.best_match(&ambiguous), None);
+}
+
+#[test]
+fn devices_carry_their_id() {
+    let backend = MockBackend::new();
+    backend.add_device(
+        DeviceKind::Mouse,
+        DeviceHandle(1),
+        mouse("a", None, None).info,
+    );
+    backend.add_device(
+        DeviceKind::Mouse,
+        DeviceHandle(2),
+        mouse("b", None, None).info,
+    );
+    let mice = get_devices_from::<Mouse, _>(&backend).unwrap();
+    let ids: Vec<_> = mice.iter().map(|mouse| mouse.id.to_string()).collect();
+    assert_eq!(ids, ["mouse:046d:c08b", "mouse:046d:c08b#1"]);
+}
+
+#[test]
+fn added_devices_are_numbered_by_path() {
+    let backend = MockBackend::new();
+    let devices = Devices::with_backend(backend.clone());
+    // added out of path order
+    for (handle, path) in [(1, "b"), (2, "a")] {
+        let mouse = mouse(path, None, None);
+        backend.add_device(DeviceKind::Mouse, DeviceHandle(handle), mouse.info.clone());
+        devices.add_device(RawDevice {
+            handle: DeviceHandle(handle),
+            ..mouse
+        });
+    }
+    let ids = |mice: Vec<Mouse>| {
+        let mut ids: Vec<_> = mice
+            .into_iter()
+            .map(|mouse| (mouse.handle, mouse.id.to_string()))
+            .collect();
+        ids.sort();
+        ids
+    };
+    let enumerated = ids(get_devices_from::<Mouse, _>(&backend).unwrap());
+    assert_eq!(
+        enumerated,
+        [
+            (DeviceHandle(1), "mouse:046d:c08b#1".to_string()),
+            (DeviceHandle(2), "mouse:046d:c08b".to_string()),
+        ]
+    );
+    assert_eq!(ids(devices.mice()), enumerated);
+
+    // the remaining one takes the first number, as it would when enumerated
+    devices.remove_device(DeviceHandle(2));
+    assert_eq!(
+        ids(devices.mice()),
+        [(DeviceHandle(1), "mouse:046d:c08b".to_string())]
+    );
+}
+
+#[test]
+fn all_devices_are_numbered_like_get_devices() {
+    let backend = MockBackend::new();
+    // enumerated out of path order
+    for (handle, path) in [(1, "c"), (2, "a"), (3, "b")] {
+        let info = mouse(path, None, None).info;
+        backend.add_device(DeviceKind::Mouse, DeviceHandle(handle), info);
+    }
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+
+    let ids = |mice: Vec<Mouse>| -> Vec<_> {
+        mice.into_iter()
+            .map(|mouse| (mouse.handle, mouse.id.instance))
+            .collect()
+    };
+    let enumerated = ids(get_devices_from::<Mouse, _>(&backend).unwrap());
+    assert_eq!(
+        enumerated,
+        [
+            (DeviceHandle(1), 2),
+            (DeviceHandle(2), 0),
+            (DeviceHandle(3), 1)
+        ]
+    );
+    assert_eq!(ids(devices.mice()), enumerated);
+}
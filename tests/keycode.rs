@@ -0,0 +1,78 @@
+use windows_experiments::KeyCode;
+
+#[test]
+fn converts_between_code_sets() {
+    // key, HID usage, set 1 scancode, evdev code
+    let cases = [
+        (KeyCode::KeyA, 0x04, Some(0x1E), Some(30)),
+        (KeyCode::Digit0, 0x27, Some(0x0B), Some(11)),
+        (KeyCode::F12, 0x45, Some(0x58), Some(88)),
+        (KeyCode::PrintScreen, 0x46, Some(0xE037), Some(99)),
+        (KeyCode::Pause, 0x48, Some(0xE11D), Some(119)),
+        (KeyCode::NumLock, 0x53, Some(0x45), Some(69)),
+        (KeyCode::NumpadEnter, 0x58, Some(0xE01C), Some(96)),
+        (KeyCode::IntlBackslash, 0x64, Some(0x56), Some(86)),
+        (KeyCode::F24, 0x73, Some(0x76), Some(194)),
+        (KeyCode::IntlRo, 0x87, Some(0x73), Some(89)),
+        (KeyCode::Execute, 0x74, None, Some(134)),
+        (KeyCode::NumpadHexadecimal, 0xDD, None, None),
+        (KeyCode::SuperLeft, 0xE3, Some(0xE05B), Some(125)),
+        (KeyCode::AltRight, 0xE6, Some(0xE038), Some(100)),
+    ];
+    for (key, usage, scancode, evdev) in cases {
+        assert_eq!(key.hid_usage(), usage, "{key:?}");
+        assert_eq!(KeyCode::from_hid_usage(usage), Some(key));
+        assert_eq!(key.scancode(), scancode, "{key:?}");
+        assert_eq!(key.evdev_code(), evdev, "{key:?}");
+        if let Some(scancode) = scancode {
+            assert_eq!(KeyCode::from_scancode(scancode), Some(key));
+        }
+        if let Some(evdev) = evdev {
+            assert_eq!(KeyCode::from_evdev(evdev), Some(key));
+        }
+    }
+
+    // shared codes convert to the key with the lower usage
+    assert_eq!(KeyCode::NonUsHash.scancode(), Some(0x2B));
+    assert_eq!(KeyCode::from_scancode(0x2B), Some(KeyCode::Backslash));
+    assert_eq!(KeyCode::from_scancode(0x76), Some(KeyCode::F24));
+
+    assert_eq!(KeyCode::from_hid_usage(0x03), None);
+    assert_eq!(KeyCode::from_hid_usage(0xE8), None);
+    assert_eq!(KeyCode::from_scancode(0), None);
+    assert_eq!(KeyCode::from_evdev(0), None);
+}
+
+#[test]
+fn round_trips_every_key() {
+    let keys: Vec<_> = (0..=0xFF).filter_map(KeyCode::from_hid_usage).collect();
+    assert_eq!(keys.len(), 0xA4 - 0x04 + 1 + 0xDD - 0xB0 + 1 + 8);
+    for key in keys {
+        if let Some(scancode) = key.scancode() {
+            let back = KeyCode::from_scancode(scancode).unwrap();
+            assert_eq!(back.scancode(), Some(scancode));
+        }
+        if let Some(evdev) = key.evdev_code() {
+            let back = KeyCode::from_evdev(evdev).unwrap();
+            assert_eq!(back.evdev_code(), Some(evdev));
+        }
+    }
+}
+
+#[cfg(feature = "winit")]
+#[test]
+fn converts_to_winit() {
+    use winit::keyboard::{KeyCode as Winit, NativeKeyCode, PhysicalKey};
+
+    assert_eq!(KeyCode::KeyQ.to_winit(), Some(Winit::KeyQ));
+    assert_eq!(KeyCode::Mute.to_winit(), Some(Winit::AudioVolumeMute));
+    assert_eq!(
+        KeyCode::from_winit(Winit::Backslash),
+        Some(KeyCode::Backslash)
+    );
+    assert_eq!(KeyCode::from_winit(Winit::F35), None);
+    assert_eq!(
+        PhysicalKey::from(KeyCode::Numpad000),
+        PhysicalKey::Unidentified(NativeKeyCode::Unidentified)
+    );
+}
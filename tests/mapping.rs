@@ -0,0 +1,346 @@
+use windows_experiments::{
+    backend::mock::MockBackend,
+    hid::{FieldValue, ReportDescriptor},
+    mapping::{Axis, Binding, Button, Guid, Half, Mapping, MappingDatabase, Source, Target},
+    DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Devices, Gamepad, GamepadState, HidUsage,
+};
+
+const PS4: &str = "030000004c050000c405000000010000,PS4 Controller,a:b1,b:b2,back:b8,\
+    dpdown:h0.4,dpleft:h0.8,dpright:h0.2,dpup:h0.1,guide:b12,leftshoulder:b4,leftstick:b10,\
+    lefttrigger:a3,leftx:a0,lefty:a1,rightshoulder:b5,rightstick:b11,righttrigger:a4,\
+    rightx:a2,righty:a5,start:b9,x:b0,y:b3,touchpad:b13,crc:1234,platform:Linux,";
+
+fn info(vendor_id: u16, product_id: u16, version: u16) -> DeviceInfo {
+    DeviceInfo {
+        product_name: "Controller".to_string(),
+        vendor_id,
+        product_id,
+        version,
+        details: DeviceDetails::Hid(HidUsage::GAMEPAD),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_mapping_lines() {
+    let mapping: Mapping = PS4.parse().unwrap();
+    assert_eq!(mapping.name, "PS4 Controller");
+    assert_eq!(mapping.platform.as_deref(), Some("Linux"));
+    assert_eq!(mapping.guid.vendor_id(), Some(0x054C));
+    assert_eq!(mapping.guid.product_id(), Some(0x05C4));
+    assert_eq!(mapping.guid.version(), Some(0x0100));
+    assert_eq!(mapping.button(Button::A), Some(Source::Button(1)));
+    assert_eq!(
+        mapping.button(Button::DpadLeft),
+        Some(Source::Hat { index: 0, mask: 8 })
+    );
+    assert_eq!(mapping.button(Button::Misc1), None);
+    assert_eq!(
+        mapping.axis(Axis::RightY).collect::<Vec<_>>(),
+        [(
+            None,
+            Source::Axis {
+                index: 5,
+                half: None,
+                inverted: false
+            }
+        )]
+    );
+    // crc is not a binding
+    assert_eq!(mapping.bindings.len(), 22);
+    assert_eq!(mapping.to_string().parse::<Mapping>().unwrap(), mapping);
+
+    let mapping: Mapping = "03000000790000000600000010010000,Generic,a:b2,-leftx:h0.8,\
+        +leftx:h0.2,lefty:a1~,lefttrigger:+a2,righttrigger:-a2,hint:!SDL_HINT:=1,misc9:b9,"
+        .parse()
+        .unwrap();
+    assert_eq!(mapping.platform, None);
+    assert_eq!(
+        mapping.bindings[1..],
+        [
+            Binding {
+                target: Target::Axis {
+                    axis: Axis::LeftX,
+                    half: Some(Half::Negative)
+                },
+                source: Source::Hat { index: 0, mask: 8 }
+            },
+            Binding {
+                target: Target::Axis {
+                    axis: Axis::LeftX,
+                    half: Some(Half::Positive)
+                },
+                source: Source::Hat { index: 0, mask: 2 }
+            },
+            Binding {
+                target: Target::Axis {
+                    axis: Axis::LeftY,
+                    half: None
+                },
+                source: Source::Axis {
+                    index: 1,
+                    half: None,
+                    inverted: true
+                }
+            },
+            Binding {
+                target: Target::Axis {
+                    axis: Axis::LeftTrigger,
+                    half: None
+                },
+                source: Source::Axis {
+                    index: 2,
+                    half: Some(Half::Positive),
+                    inverted: false
+                }
+            },
+            Binding {
+                target: Target::Axis {
+                    axis: Axis::RightTrigger,
+                    half: None
+                },
+                source: Source::Axis {
+                    index: 2,
+                    half: Some(Half::Negative),
+                    inverted: false
+                }
+            },
+        ]
+    );
+    assert_eq!(mapping.to_string().parse::<Mapping>().unwrap(), mapping);
+
+    for line in [
+        "",
+        "xinput,XInput Controller,a:b0,",
+        "030000004c050000c40500000001000,Short GUID,a:b0,",
+        "030000004c050000c405000000010000",
+        "030000004c050000c405000000010000,No Colon,a",
+        "030000004c050000c405000000010000,Bad Source,a:c0,",
+        "030000004c050000c405000000010000,Half Button,a:+b0,",
+        "030000004c050000c405000000010000,Bad Hat,dpup:h0,",
+    ] {
+        assert!(line.parse::<Mapping>().is_err(), "{}", line);
+    }
+}
+
+#[test]
+fn reads_vendor_and_product_from_guids() {
+    let guid = Guid::from_info(&info(0x045E, 0x02EA, 0x0408));
+    assert_eq!(guid.to_string(), "030000005e040000ea02000008040000");
+    assert_eq!(guid.to_string().parse(), Ok(guid));
+    assert_eq!(
+        (guid.vendor_id(), guid.product_id(), guid.version()),
+        (Some(0x045E), Some(0x02EA), Some(0x0408))
+    );
+
+    // DirectInput
+    let guid: Guid = "4c056802000000000000504944564944".parse().unwrap();
+    assert_eq!(
+        (guid.vendor_id(), guid.product_id(), guid.version()),
+        (Some(0x054C), Some(0x0268), None)
+    );
+    // Bluetooth
+    let guid: Guid = "05000000c82d00000161000000010000".parse().unwrap();
+    assert_eq!(guid.vendor_id(), Some(0x2DC8));
+    // made up from the name
+    let guid: Guid = "050000004e696d6275732b0000000000".parse().unwrap();
+    assert_eq!(guid.vendor_id(), None);
+}
+
+#[test]
+fn looks_up_mappings_by_device() {
+    let mut database = MappingDatabase::new();
+    let text = format!(
+        "# a comment\n\n{}\r\n\
+        030000004c050000c405000000000000,Any Version,a:b0,\n\
+        030000004c050000c405000000020000,Other Version,a:b0,\n\
+        this line is broken\n\
+        030000004c050000cc09000000000000,Android Only,a:b0,platform:Android,\n\
+        4c05cc09000000000000504944564944,DirectInput,a:b0,\n",
+        PS4.replace("platform:Linux,", "")
+    );
+    assert_eq!(database.add_mappings(&text), 5);
+    let name = |database: &MappingDatabase, info| {
+        database
+            .lookup(&info)
+            .map(|mapping: &Mapping| mapping.name.clone())
+    };
+    assert_eq!(
+        name(&database, info(0x054C, 0x05C4, 0x0100)).as_deref(),
+        Some("PS4 Controller")
+    );
+    assert_eq!(
+        name(&database, info(0x054C, 0x05C4, 0x0200)).as_deref(),
+        Some("Other Version")
+    );
+    assert_eq!(
+        name(&database, info(0x054C, 0x05C4, 0x0300)).as_deref(),
+        Some("Any Version")
+    );
+    assert_eq!(
+        name(&database, info(0x054C, 0x09CC, 0x0100)).as_deref(),
+        Some("DirectInput")
+    );
+    assert_eq!(name(&database, info(0x054C, 0x0000, 0x0100)), None);
+
+    // overrides replace the mapping with the same GUID
+    let mut mapping: Mapping = PS4.replace("platform:Linux,", "").parse().unwrap();
+    mapping.name = "Override".to_string();
+    database.add_mapping(mapping);
+    assert_eq!(database.mappings().len(), 5);
+    assert_eq!(
+        name(&database, info(0x054C, 0x05C4, 0x0100)).as_deref(),
+        Some("Override")
+    );
+}
+
+/// A gamepad with ten buttons, the X, Y, Z, Rz and dial axes and a hat switch.
+const DESCRIPTOR: &[u8] = &[
+    0x05, 0x01, 0x09, 0x05, 0xA1, 0x01, // application collection
+    0x05, 0x09, 0x19, 0x01, 0x29, 0x0A, 0x15, 0x00, 0x25, 0x01, 0x75, 0x01, 0x95, 0x0A, 0x81,
+    0x02, // buttons 1 to 10
+    0x75, 0x06, 0x95, 0x01, 0x81, 0x03, // padding
+    0x05, 0x01, 0x09, 0x30, 0x09, 0x31, 0x09, 0x32, 0x09, 0x35, 0x09, 0x37, 0x15, 0x00, 0x26, 0xFE,
+    0x00, 0x75, 0x08, 0x95, 0x05, 0x81, 0x02, // axes
+    0x09, 0x39, 0x15, 0x00, 0x25, 0x07, 0x75, 0x08, 0x95, 0x01, 0x81, 0x42, // hat switch
+    0xC0,
+];
+
+fn value(page: u16, id: u16, value: i32) -> FieldValue {
+    FieldValue {
+        usage: HidUsage::new(page, id),
+        value,
+        logical_min: 0,
+        logical_max: 254,
+        physical: None,
+        unit: 0,
+    }
+}
+
+fn hat(position: i32) -> FieldValue {
+    FieldValue {
+        logical_max: 7,
+        ..value(0x01, 0x39, position)
+    }
+}
+
+#[test]
+fn reads_gamepads_through_mappings() {
+    let mapping: Mapping = "03000000790000000600000000000000,Generic,a:b2,b:b1,\
+        dpup:h0.1,-leftx:h0.8,+leftx:h0.2,lefty:a1~,rightx:a2,righty:a3,\
+        lefttrigger:+a4,righttrigger:b6,"
+        .parse()
+        .unwrap();
+    let mut gamepad = GamepadState::default();
+    gamepad.set_mapping(Some(mapping));
+    gamepad.set_descriptor(&ReportDescriptor::parse(DESCRIPTOR).unwrap());
+    gamepad.deadzone = 0.0;
+    gamepad.update(&[
+        value(0x09, 3, 1),
+        value(0x09, 7, 1),
+        // there is no slider, so the dial is axis 4
+        value(0x01, 0x30, 127),
+        value(0x01, 0x31, 127),
+        value(0x01, 0x32, 254),
+        value(0x01, 0x35, 127),
+        value(0x01, 0x37, 190),
+        hat(6),
+    ]);
+    assert!(gamepad.button(Button::A));
+    assert!(!gamepad.button(Button::B));
+    assert!(!gamepad.button(Button::DpadUp));
+    assert!(!gamepad.button(Button::Guide));
+    assert_eq!(gamepad.raw_axis(2), Some(1.0));
+    assert_eq!(gamepad.raw_axis(5), None);
+    assert_eq!(gamepad.left_stick(), (-1.0, 0.0));
+    assert_eq!(gamepad.right_stick(), (1.0, 0.0));
+    assert!((gamepad.left_trigger() - 0.4961).abs() < 1e-3);
+    assert_eq!(gamepad.right_trigger(), 1.0);
+
+    gamepad.update(&[value(0x01, 0x31, 0), hat(0)]);
+    assert!(gamepad.button(Button::DpadUp));
+    // lefty is inverted
+    assert_eq!(gamepad.left_stick(), (0.0, 1.0));
+
+    // without the mapping, the generic layout reads the same values
+    gamepad.set_mapping(None);
+    assert!(!gamepad.button(Button::A));
+    gamepad.update(&[value(0x01, 0x32, 254)]);
+    assert_eq!(gamepad.right_stick(), (1.0, 0.0));
+}
+
+#[test]
+fn numbers_axes_by_the_descriptor() {
+    let mapping: Mapping = "03000000790000000600000000000000,Generic,rightx:a2,"
+        .parse()
+        .unwrap();
+    let mut gamepad = GamepadState::default();
+    gamepad.set_mapping(Some(mapping));
+    gamepad.deadzone = 0.0;
+
+    // axis 2 reports before axis 0, and nothing is numbered without a descriptor
+    gamepad.update(&[value(0x01, 0x32, 254)]);
+    assert_eq!(gamepad.raw_axis(0), None);
+    assert_eq!(gamepad.right_stick(), (0.0, 0.0));
+
+    gamepad.set_descriptor(&ReportDescriptor::parse(DESCRIPTOR).unwrap());
+    assert_eq!(gamepad.raw_axis(2), Some(1.0));
+    assert_eq!(gamepad.raw_axis(0), None);
+    assert_eq!(gamepad.right_stick(), (1.0, 0.0));
+    gamepad.update(&[value(0x01, 0x30, 0)]);
+    assert_eq!(gamepad.raw_axis(0), Some(-1.0));
+
+    // the slider isn't declared, so the dial is axis 4 and there is no axis 5
+    gamepad.update(&[value(0x01, 0x36, 254)]);
+    assert_eq!(gamepad.raw_axis(4), None);
+    assert_eq!(gamepad.raw_axis(5), None);
+    gamepad.update(&[value(0x01, 0x37, 0)]);
+    assert_eq!(gamepad.raw_axis(4), Some(-1.0));
+}
+
+#[test]
+fn applies_mappings_to_added_gamepads() {
+    let backend = MockBackend::new();
+    backend.add_device(
+        DeviceKind::Hid,
+        DeviceHandle(1),
+        info(0x0079, 0x0006, 0x0100),
+    );
+    backend.add_device(DeviceKind::Hid, DeviceHandle(2), info(0x0001, 0x0002, 0));
+
+    let mut database = MappingDatabase::new();
+    database.add_mappings("03000000790000000600000000000000,Generic,a:b2,");
+    let gamepads = windows_experiments::get_devices_from::<Gamepad, _>(&backend).unwrap();
+    assert_eq!(
+        gamepads[0].guid().to_string(),
+        "03000000790000000600000000010000"
+    );
+    assert_eq!(gamepads[0].mapping(&database).unwrap().name, "Generic");
+    assert_eq!(gamepads[1].mapping(&database), None);
+
+    let devices = Devices::with_backend(backend);
+    devices.set_mappings(database);
+    devices.add_gamepads().unwrap();
+    let mapping_name = |handle| {
+        let state = devices.gamepad_state(DeviceHandle(handle)).unwrap();
+        state.mapping().map(|mapping| mapping.name.clone())
+    };
+    assert_eq!(mapping_name(1).as_deref(), Some("Generic"));
+    assert_eq!(mapping_name(2), None);
+
+    // overrides reach the added gamepads
+    devices.add_mapping(
+        "03000000010000000200000000000000,Custom,a:b0,"
+            .parse()
+            .unwrap(),
+    );
+    devices.add_mapping(
+        "03000000790000000600000000010000,Exact,a:b0,"
+            .parse()
+            .unwrap(),
+    );
+    assert_eq!(mapping_name(1).as_deref(), Some("Exact"));
+    assert_eq!(mapping_name(2).as_deref(), Some("Custom"));
+    devices.set_mappings(MappingDatabase::new());
+    assert_eq!(mapping_name(1), None);
+}
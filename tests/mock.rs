@@ -0,0 +1,438 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc,
+    time::Duration,
+};
+
+use windows_experiments::{
+    backend::mock::MockBackend, get_devices_from, ButtonState, ChannelConfig, DeviceHandle,
+    DeviceId, DeviceInfo, DeviceKind, Devices, Error, Event, HidDevice, HidUsage, InputEvent,
+    KeyCode, Keyboard, Mouse, Timestamp,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn enumerates_declared_devices() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Left Mouse", DeviceHandle(1));
+    backend.add_mouse("Right Mouse", DeviceHandle(2));
+    backend.add_keyboard("Keyboard", DeviceHandle(3));
+
+    let mice = get_devices_from::<Mouse, _>(&backend).unwrap();
+    let keyboards = get_devices_from::<Keyboard, _>(&backend).unwrap();
+
+    let names: Vec<_> = mice
+        .iter()
+        .map(|mouse| mouse.info.product_name.as_str())
+        .collect();
+    assert_eq!(names, ["Left Mouse", "Right Mouse"]);
+    assert_eq!(keyboards.len(), 1);
+    assert_eq!(keyboards[0].handle, DeviceHandle(3));
+}
+
+#[test]
+fn delivers_scripted_events_in_order() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    backend.add_keyboard("Keyboard", DeviceHandle(2));
+
+    let script = [
+        Event {
+            device: DeviceHandle(1),
+            timestamp: Timestamp(Duration::from_millis(1)),
+            kind: InputEvent::MouseMotion { dx: 4, dy: -2 },
+        },
+        Event {
+            device: DeviceHandle(2),
+            timestamp: Timestamp(Duration::from_millis(2)),
+            kind: InputEvent::Key {
+                scancode: 0x1E,
+                state: ButtonState::Pressed,
+            },
+        },
+    ];
+    // queued before listening starts
+    backend.push_event(
+        script[0].device,
+        script[0].timestamp,
+        script[0].kind.clone(),
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event).unwrap())
+        .unwrap();
+
+    backend.push_event(
+        script[1].device,
+        script[1].timestamp,
+        script[1].kind.clone(),
+    );
+
+    for expected in script {
+        assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), expected);
+    }
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn tracks_connected_devices() {
+    let backend = MockBackend::new();
+    let (sender, receiver) = mpsc::channel();
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event).unwrap())
+        .unwrap();
+
+    let info = DeviceInfo {
+        product_name: "Pad".to_string(),
+        vendor_id: 0x045e,
+        product_id: 0x0800,
+        ..Default::default()
+    };
+    for handle in [5, 6] {
+        backend.connect(
+            DeviceKind::Keyboard,
+            DeviceHandle(handle),
+            info.clone(),
+            Timestamp::default(),
+        );
+        let event = receiver.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(event.device, DeviceHandle(handle));
+        assert!(matches!(event.kind, InputEvent::DeviceAdded { .. }));
+    }
+    let keyboards = devices.keyboards();
+    let ids: Vec<_> = keyboards.iter().map(|k| k.id.to_string()).collect();
+    assert_eq!(ids, ["keyboard:045e:0800", "keyboard:045e:0800#1"]);
+    assert!(devices.mice().is_empty());
+
+    backend.disconnect(DeviceHandle(5), Timestamp::default());
+    let event = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(event.kind, InputEvent::DeviceRemoved);
+    let handles: Vec<_> = devices.keyboards().iter().map(|k| k.handle).collect();
+    assert_eq!(handles, [DeviceHandle(6)]);
+    assert_eq!(get_devices_from::<Keyboard, _>(&backend).unwrap().len(), 1);
+
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn delivers_only_added_devices() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    backend.add_keyboard("Keyboard", DeviceHandle(2));
+    backend.add_keyboard("Macro Pad", DeviceHandle(3));
+
+    let devices = Devices::with_backend(backend.clone());
+    devices
+        .add_devices_where(|info| info.product_name.starts_with("Macro"))
+        .unwrap();
+    let mouse = get_devices_from::<Mouse, _>(&backend).unwrap().remove(0);
+    devices.add_device(&mouse);
+    let names: Vec<_> = devices
+        .keyboards()
+        .into_iter()
+        .map(|keyboard| keyboard.info.product_name)
+        .collect();
+    assert_eq!(names, ["Macro Pad"]);
+
+    let (sender, receiver) = mpsc::channel();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event.device).unwrap())
+        .unwrap();
+    let motion = InputEvent::MouseMotion { dx: 1, dy: 1 };
+    let delivered = |count| {
+        (0..count)
+            .map(|_| receiver.recv_timeout(TIMEOUT).unwrap().0)
+            .collect::<Vec<_>>()
+    };
+    for handle in [1, 2, 3] {
+        backend.push_event(DeviceHandle(handle), Timestamp::default(), motion.clone());
+    }
+    assert_eq!(delivered(2), [1, 3]);
+
+    // the filter also applies to devices connected while listening
+    for (handle, name) in [(4, "Keyboard"), (5, "Macro Pad 2")] {
+        let info = DeviceInfo {
+            product_name: name.to_string(),
+            ..Default::default()
+        };
+        backend.connect(
+            DeviceKind::Keyboard,
+            DeviceHandle(handle),
+            info,
+            Timestamp::default(),
+        );
+        backend.push_event(DeviceHandle(handle), Timestamp::default(), motion.clone());
+    }
+    // the addition and the motion
+    assert_eq!(delivered(2), [5, 5]);
+
+    assert!(devices.remove_device(DeviceHandle(1)));
+    assert!(!devices.remove_device(DeviceHandle(2)));
+    backend.push_event(DeviceHandle(1), Timestamp::default(), motion.clone());
+    backend.push_event(DeviceHandle(3), Timestamp::default(), motion);
+    assert_eq!(delivered(1), [3]);
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn delivers_reports_of_registered_hid_usages() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    backend.add_hid("Gamepad", DeviceHandle(2), HidUsage::GAMEPAD);
+    backend.add_hid("Joystick", DeviceHandle(3), HidUsage::JOYSTICK);
+
+    let hid = get_devices_from::<HidDevice, _>(&backend).unwrap();
+    let usages: Vec<_> = hid.iter().map(|device| device.usage()).collect();
+    assert_eq!(usages, [Some(HidUsage::GAMEPAD), Some(HidUsage::JOYSTICK)]);
+
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    assert!(devices.hid_devices().is_empty());
+    devices.add_hid_devices(HidUsage::GAMEPAD).unwrap();
+    devices.add_hid_devices(HidUsage::GAMEPAD).unwrap();
+    let added = devices.hid_devices();
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0].handle, DeviceHandle(2));
+    assert_eq!(added[0].id.to_string(), "hid:0000:0000");
+
+    let (sender, receiver) = mpsc::channel();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event).unwrap())
+        .unwrap();
+    assert_eq!(backend.hid_usages(), [HidUsage::GAMEPAD]);
+    for handle in [3, 2] {
+        backend.push_event(
+            DeviceHandle(handle),
+            Timestamp::default(),
+            InputEvent::HidReport {
+                report: vec![handle as u8, 0x80],
+            },
+        );
+    }
+    let event = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(event.device, DeviceHandle(2));
+    assert_eq!(
+        event.kind,
+        InputEvent::HidReport {
+            report: vec![2, 0x80]
+        }
+    );
+    listener.stop();
+    listener.join().unwrap();
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn releases_keys_of_removed_keyboards() {
+    let backend = MockBackend::new();
+    backend.add_keyboard("Keyboard", DeviceHandle(1));
+    let (sender, receiver) = mpsc::channel();
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event).unwrap())
+        .unwrap();
+
+    let key = |scancode, state| InputEvent::Key { scancode, state };
+    for scancode in [0x2A, 0x1E] {
+        backend.push_event(
+            DeviceHandle(1),
+            Timestamp::default(),
+            key(scancode, ButtonState::Pressed),
+        );
+        receiver.recv_timeout(TIMEOUT).unwrap();
+    }
+    let state = devices.keyboard_state(DeviceHandle(1)).unwrap();
+    assert!(state.is_pressed(KeyCode::KeyA));
+    assert!(state.modifiers().left_shift);
+
+    let removed = Timestamp(Duration::from_secs(1));
+    backend.disconnect(DeviceHandle(1), removed);
+    let kinds: Vec<_> = (0..3)
+        .map(|_| {
+            let event = receiver.recv_timeout(TIMEOUT).unwrap();
+            assert_eq!(event.timestamp, removed);
+            event.kind
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            key(0x1E, ButtonState::Released),
+            key(0x2A, ButtonState::Released),
+            InputEvent::DeviceRemoved,
+        ]
+    );
+    assert!(devices.keyboard_state(DeviceHandle(1)).is_none());
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn calls_subscribed_handlers() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    backend.add_keyboard("Keyboard", DeviceHandle(2));
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+    let keyboard = devices.keyboards().remove(0).id;
+
+    let (sender, calls) = mpsc::channel();
+    let subscribe = |name| {
+        let sender = sender.clone();
+        move |id: &DeviceId, event: &Event| {
+            sender.send((name, id.to_string(), event.device)).unwrap()
+        }
+    };
+    devices.on_key(Some(keyboard.clone()), subscribe("key"));
+    let moves = devices.on_mouse_move(None, subscribe("move"));
+    devices.on_device_change(None, subscribe("change"));
+    // a handler for a device that isn't connected
+    let mut unknown = keyboard.clone();
+    unknown.instance = 1;
+    devices.on_event(Some(unknown), subscribe("unknown"));
+
+    let (listener, receiver) = devices.start_listening(ChannelConfig::default()).unwrap();
+    let key = InputEvent::Key {
+        scancode: 0x1E,
+        state: ButtonState::Pressed,
+    };
+    let motion = InputEvent::MouseMotion { dx: 1, dy: 0 };
+    backend.push_event(DeviceHandle(2), Timestamp::default(), key.clone());
+    backend.push_event(DeviceHandle(1), Timestamp::default(), motion.clone());
+    backend.disconnect(DeviceHandle(1), Timestamp::default());
+    for _ in 0..3 {
+        receiver.recv_timeout(TIMEOUT).unwrap();
+    }
+    let received: Vec<_> = calls.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            ("key", "keyboard:0000:0000".to_string(), DeviceHandle(2)),
+            ("move", "mouse:0000:0000".to_string(), DeviceHandle(1)),
+            ("change", "mouse:0000:0000".to_string(), DeviceHandle(1)),
+        ]
+    );
+
+    assert!(devices.unsubscribe(moves));
+    assert!(!devices.unsubscribe(moves));
+    backend.connect(
+        DeviceKind::Mouse,
+        DeviceHandle(3),
+        DeviceInfo::default(),
+        Timestamp::default(),
+    );
+    backend.push_event(DeviceHandle(3), Timestamp::default(), motion);
+    for _ in 0..2 {
+        receiver.recv_timeout(TIMEOUT).unwrap();
+    }
+    let received: Vec<_> = calls.try_iter().map(|(name, ..)| name).collect();
+    assert_eq!(received, ["change"]);
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn allows_one_listener_at_a_time() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    let listener = devices
+        .start_listening_with(move |event| sender.send(event.kind).unwrap())
+        .unwrap();
+    assert_eq!(
+        devices.start_listening_with(|_| {}).err(),
+        Some(Error::AlreadyListening)
+    );
+    assert!(devices.start_listening(ChannelConfig::default()).is_err());
+
+    // the motion is applied once, by the one listener
+    let motion = InputEvent::MouseMotion { dx: 3, dy: 4 };
+    backend.push_event(DeviceHandle(1), Timestamp::default(), motion.clone());
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), motion);
+    let state = devices.mouse_state(DeviceHandle(1)).unwrap();
+    assert_eq!(state.position(), (3, 4));
+
+    listener.stop();
+    listener.join().unwrap();
+    let listener = devices.start_listening_with(|_| {}).unwrap();
+    drop(listener);
+    assert!(devices.start_listening_with(|_| {}).is_ok());
+}
+
+#[test]
+fn keeps_calling_handlers_after_one_panics() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+
+    let (sender, calls) = mpsc::channel();
+    let before = sender.clone();
+    devices.on_event(None, move |_, event| {
+        before.send(("before", event.kind.clone())).unwrap()
+    });
+    devices.on_event(None, |_, _| panic!("handler failed"));
+    devices.on_event(None, move |_, event| {
+        sender.send(("after", event.kind.clone())).unwrap()
+    });
+
+    let (listener, receiver) = devices.start_listening(ChannelConfig::default()).unwrap();
+    let motions = [
+        InputEvent::MouseMotion { dx: 1, dy: 0 },
+        InputEvent::MouseMotion { dx: 0, dy: 1 },
+    ];
+    for motion in &motions {
+        backend.push_event(DeviceHandle(1), Timestamp::default(), motion.clone());
+        assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap().2, *motion);
+    }
+    let received: Vec<_> = calls.try_iter().collect();
+    assert_eq!(
+        received,
+        [
+            ("before", motions[0].clone()),
+            ("after", motions[0].clone()),
+            ("before", motions[1].clone()),
+            ("after", motions[1].clone()),
+        ]
+    );
+    assert!(!listener.is_finished());
+    listener.stop();
+    listener.join().unwrap();
+}
+
+#[test]
+fn keeps_listening_after_a_state_change_panics() {
+    let backend = MockBackend::new();
+    backend.add_mouse("Mouse", DeviceHandle(1));
+    let devices = Devices::with_backend(backend.clone());
+    devices.add_all_devices().unwrap();
+
+    let (listener, receiver) = devices.start_listening(ChannelConfig::default()).unwrap();
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+        devices.modify_mouse_state(DeviceHandle(1), |_| panic!("modification failed"))
+    }));
+    assert!(panicked.is_err());
+
+    let motion = InputEvent::MouseMotion { dx: 3, dy: 4 };
+    backend.push_event(DeviceHandle(1), Timestamp::default(), motion.clone());
+    assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap().2, motion);
+    let state = devices.mouse_state(DeviceHandle(1)).unwrap();
+    assert_eq!(state.position(), (3, 4));
+    assert_eq!(devices.mice().len(), 1);
+    assert!(!listener.is_finished());
+    listener.stop();
+    listener.join().unwrap();
+}
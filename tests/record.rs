@@ -0,0 +1,299 @@
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use windows_experiments::{
+    backend::{
+        replay::{ReplayBackend, ReplaySpeed},
+        RawDevice,
+    },
+    get_devices_from,
+    record::{Recorder, SessionReader},
+    ButtonState, DeviceDetails, DeviceHandle, DeviceInfo, DeviceKind, Devices, Event, HidUsage,
+    InputEvent, KeyboardDetails, Mouse, MouseButton, MouseDetails, Timestamp, WheelAxis,
+};
+
+fn event(device: usize, micros: u64, kind: InputEvent) -> Event {
+    Event {
+        device: DeviceHandle(device),
+        timestamp: Timestamp(Duration::from_micros(micros)),
+        kind,
+    }
+}
+
+#[test]
+fn round_trips_devices_and_events() {
+    let devices = vec![
+        RawDevice {
+            handle: DeviceHandle(0x1234),
+            kind: DeviceKind::Mouse,
+            info: DeviceInfo {
+                product_name: "Gaming Mouse".to_string(),
+                manufacturer: Some("ACME".to_string()),
+                vendor_id: 0x046d,
+                product_id: 0xc08b,
+                version: 0x2701,
+                path: "/dev/input/event4".to_string(),
+                location: Some("usb-0000:00:14.0-2/input0".to_string()),
+                details: DeviceDetails::Mouse(MouseDetails {
+                    id: 256,
+                    buttons: 5,
+                    sample_rate: 0,
+                    has_horizontal_wheel: true,
+                }),
+                ..Default::default()
+            },
+        },
+        RawDevice {
+            handle: DeviceHandle(0x5678),
+            kind: DeviceKind::Keyboard,
+            info: DeviceInfo {
+                product_name: "Tastatur ÄÖÜ".to_string(),
+                serial_number: Some("0001".to_string()),
+                details: DeviceDetails::Keyboard(KeyboardDetails {
+                    keyboard_type: 4,
+                    function_keys: 12,
+                    indicators: 3,
+                    total_keys: 104,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        },
+        RawDevice {
+            handle: DeviceHandle(0x9ABC),
+            kind: DeviceKind::Hid,
+            info: DeviceInfo {
+                product_name: "Gamepad".to_string(),
+                vendor_id: 0x045e,
+                product_id: 0x028e,
+                details: DeviceDetails::Hid(HidUsage::GAMEPAD),
+                ..Default::default()
+            },
+        },
+    ];
+    let events = vec![
+        event(0x1234, 10, InputEvent::MouseMotion { dx: -3, dy: 7 }),
+        event(
+            0x1234,
+            11,
+            InputEvent::MouseButton {
+                button: MouseButton::Middle,
+                state: ButtonState::Pressed,
+            },
+        ),
+        event(
+            0x1234,
+            12,
+            InputEvent::MouseWheel {
+                axis: WheelAxis::Vertical,
+                delta: -240,
+            },
+        ),
+        event(
+            0x1234,
+            13,
+            InputEvent::MouseWheel {
+                axis: WheelAxis::Horizontal,
+                delta: 30,
+            },
+        ),
+        event(
+            0x1234,
+            14,
+            InputEvent::MouseButton {
+                button: MouseButton::Forward,
+                state: ButtonState::Released,
+            },
+        ),
+        event(0x1234, 15, InputEvent::MousePosition { x: -1920, y: 1079 }),
+        event(
+            0x5678,
+            2_000_000,
+            InputEvent::Key {
+                scancode: 0xE01D,
+                state: ButtonState::Released,
+            },
+        ),
+        event(
+            0x9ABC,
+            2_000_001,
+            InputEvent::HidReport {
+                report: vec![1, 2, 3, 255],
+            },
+        ),
+        event(
+            0x5679,
+            3_000_000,
+            InputEvent::DeviceAdded {
+                kind: DeviceKind::Keyboard,
+                info: devices[1].info.clone(),
+            },
+        ),
+        event(0x1234, 3_000_001, InputEvent::DeviceRemoved),
+    ];
+
+    let mut recorder = Recorder::new(vec![], devices.clone()).unwrap();
+    for event in &events {
+        recorder.record(event).unwrap();
+    }
+    let log = recorder.finish().unwrap();
+
+    let reader = SessionReader::new(log.as_slice()).unwrap();
+    assert_eq!(reader.devices(), devices.as_slice());
+    let read: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert_eq!(read, events);
+}
+
+#[test]
+fn rejects_truncated_and_foreign_logs() {
+    assert!(SessionReader::new(&b"PNG\x89rest"[..]).is_err());
+
+    let mut recorder = Recorder::new(vec![], []).unwrap();
+    recorder
+        .record(&event(1, 1, InputEvent::MouseMotion { dx: 1, dy: 1 }))
+        .unwrap();
+    let log = recorder.finish().unwrap();
+
+    let mut reader = SessionReader::new(&log[..log.len() - 1]).unwrap();
+    assert!(reader.next().unwrap().is_err());
+
+    for version in [0u16, 2] {
+        let mut other = log.clone();
+        other[4..6].copy_from_slice(&version.to_le_bytes());
+        assert!(SessionReader::new(other.as_slice()).is_err());
+    }
+}
+
+#[test]
+fn reads_logs_written_byte_by_byte() {
+    let info = DeviceInfo {
+        product_name: "Mouse".to_string(),
+        vendor_id: 0x046d,
+        path: "/dev/input/event3".to_string(),
+        location: Some("usb-1".to_string()),
+        details: DeviceDetails::Mouse(MouseDetails {
+            buttons: 3,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut log = b"WXIN".to_vec();
+    log.extend(1u16.to_le_bytes());
+    log.extend(0u32.to_le_bytes());
+    // device added
+    log.push(5);
+    log.extend(3u64.to_le_bytes());
+    log.extend(1000u64.to_le_bytes());
+    log.push(0);
+    log.extend(5u32.to_le_bytes());
+    log.extend(b"Mouse");
+    log.extend([0, 0]);
+    log.extend([0x6d, 0x04, 0, 0, 0, 0]);
+    log.extend(17u32.to_le_bytes());
+    log.extend(b"/dev/input/event3");
+    log.push(1);
+    log.extend(5u32.to_le_bytes());
+    log.extend(b"usb-1");
+    log.push(1);
+    for field in [0u32, 3, 0, 0] {
+        log.extend(field.to_le_bytes());
+    }
+    // side button
+    log.push(1);
+    log.extend(3u64.to_le_bytes());
+    log.extend(2000u64.to_le_bytes());
+    log.extend([3, 1]);
+
+    let read: Vec<_> = SessionReader::new(&log[..])
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        read,
+        [
+            event(
+                3,
+                1,
+                InputEvent::DeviceAdded {
+                    kind: DeviceKind::Mouse,
+                    info
+                }
+            ),
+            event(
+                3,
+                2,
+                InputEvent::MouseButton {
+                    button: MouseButton::Back,
+                    state: ButtonState::Pressed
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn replays_recorded_sessions() {
+    let devices = [RawDevice {
+        handle: DeviceHandle(7),
+        kind: DeviceKind::Mouse,
+        info: DeviceInfo {
+            product_name: "Mouse".to_string(),
+            ..Default::default()
+        },
+    }];
+    let events: Vec<_> = (0..3)
+        .map(|i| {
+            event(
+                7,
+                i * 20_000,
+                InputEvent::MouseMotion {
+                    dx: i as i32,
+                    dy: 0,
+                },
+            )
+        })
+        .collect();
+    let mut recorder = Recorder::new(vec![], devices).unwrap();
+    for event in &events {
+        recorder.record(event).unwrap();
+    }
+    let log = recorder.finish().unwrap();
+
+    for speed in [
+        ReplaySpeed::RealTime,
+        ReplaySpeed::Scaled(4.0),
+        ReplaySpeed::Instant,
+    ] {
+        let backend = ReplayBackend::from_reader(log.as_slice(), speed).unwrap();
+        let mice = get_devices_from::<Mouse, _>(&backend).unwrap();
+        assert_eq!(mice.len(), 1);
+        assert_eq!(mice[0].handle, DeviceHandle(7));
+
+        let (sender, receiver) = mpsc::channel();
+        let start = Instant::now();
+        let devices = Devices::with_backend(backend);
+        devices.add_all_devices().unwrap();
+        let _listener = devices
+            .start_listening_with(move |event| sender.send(event).unwrap())
+            .unwrap();
+        let replayed: Vec<_> = receiver.iter().take(events.len()).collect();
+        assert_eq!(replayed, events);
+        if speed == ReplaySpeed::RealTime {
+            assert!(start.elapsed() >= Duration::from_millis(40));
+        }
+    }
+}
+
+#[test]
+fn rejects_invalid_replay_speeds() {
+    let log = Recorder::new(vec![], []).unwrap().finish().unwrap();
+    for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let err = ReplayBackend::from_reader(log.as_slice(), ReplaySpeed::Scaled(factor))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", factor);
+    }
+    assert!(ReplayBackend::from_reader(log.as_slice(), ReplaySpeed::Scaled(1e-300)).is_ok());
+}
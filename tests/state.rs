@@ -0,0 +1,212 @@
+use std::{f32::consts::FRAC_1_SQRT_2, time::Duration};
+
+use windows_experiments::{
+    hid::FieldValue, ButtonState, DeviceHandle, Event, GamepadFamily, GamepadLayout, GamepadState,
+    Hat, HidUsage, InputEvent, KeyCode, KeyboardState, MouseButton, MouseState, Rect, Timestamp,
+    WheelAxis,
+};
+
+fn key(scancode: u16, state: ButtonState) -> InputEvent {
+    InputEvent::Key { scancode, state }
+}
+
+#[test]
+fn tracks_held_keys_and_locks() {
+    use ButtonState::*;
+
+    let mut keyboard = KeyboardState::new();
+    for event in [
+        key(0xE01D, Pressed),
+        key(0x2A, Pressed),
+        key(0x1E, Pressed),
+        key(0x1E, Pressed),
+        key(0x3A, Pressed),
+        // autorepeat doesn't toggle again
+        key(0x3A, Pressed),
+        key(0x3A, Released),
+        key(0xE11D, Pressed),
+        key(0x7F, Pressed),
+        InputEvent::MouseMotion { dx: 1, dy: 1 },
+    ] {
+        keyboard.update(&event);
+    }
+    let keys: Vec<_> = keyboard.pressed_keys().collect();
+    assert_eq!(
+        keys,
+        [
+            KeyCode::KeyA,
+            KeyCode::Pause,
+            KeyCode::ShiftLeft,
+            KeyCode::ControlRight
+        ]
+    );
+    assert!(keyboard.is_pressed(KeyCode::KeyA));
+    assert!(keyboard.is_pressed(KeyCode::Pause));
+    keyboard.update(&key(0xE11D, Released));
+    assert!(!keyboard.is_pressed(KeyCode::Pause));
+    assert!(keyboard.caps_lock);
+    assert!(!keyboard.num_lock);
+
+    let modifiers = keyboard.modifiers();
+    assert!(modifiers.ctrl() && modifiers.right_ctrl && !modifiers.left_ctrl);
+    assert!(modifiers.shift() && !modifiers.alt() && !modifiers.meta());
+
+    keyboard.update(&key(0x1E, Released));
+    keyboard.update(&key(0x3A, Pressed));
+    assert!(!keyboard.caps_lock);
+    assert_eq!(
+        keyboard.release_all(),
+        [KeyCode::CapsLock, KeyCode::ShiftLeft, KeyCode::ControlRight]
+    );
+    assert_eq!(keyboard.pressed_keys().count(), 0);
+}
+
+fn event(millis: u64, kind: InputEvent) -> Event {
+    Event {
+        device: DeviceHandle(1),
+        timestamp: Timestamp(Duration::from_millis(millis)),
+        kind,
+    }
+}
+
+#[test]
+fn integrates_mouse_input() {
+    let button = |button, state| InputEvent::MouseButton { button, state };
+    let wheel = |delta| InputEvent::MouseWheel {
+        axis: WheelAxis::Vertical,
+        delta,
+    };
+    let mut mouse = MouseState::new();
+    for (millis, kind) in [
+        (1, InputEvent::MouseMotion { dx: 30, dy: -5 }),
+        (2, InputEvent::MouseMotion { dx: -10, dy: 2 }),
+        (3, button(MouseButton::Right, ButtonState::Pressed)),
+        (4, button(MouseButton::Left, ButtonState::Pressed)),
+        (5, button(MouseButton::Right, ButtonState::Released)),
+        (6, wheel(120)),
+        (6, wheel(120)),
+        (6, wheel(-60)),
+        (7, InputEvent::DeviceRemoved),
+    ] {
+        mouse.update(&event(millis, kind));
+    }
+
+    assert_eq!(mouse.position(), (20, -3));
+    assert!(mouse.is_pressed(MouseButton::Left));
+    assert_eq!(
+        mouse.pressed_buttons().collect::<Vec<_>>(),
+        [MouseButton::Left]
+    );
+    assert_eq!(mouse.wheel(WheelAxis::Vertical), 180);
+    assert_eq!(mouse.wheel(WheelAxis::Horizontal), 0);
+    assert_eq!(
+        mouse.last_activity(),
+        Some(Timestamp(Duration::from_millis(6)))
+    );
+
+    mouse.set_bounds(Some(Rect {
+        left: 0,
+        top: 0,
+        width: 640,
+        height: 480,
+    }));
+    assert_eq!(mouse.position(), (20, 0));
+    mouse.update(&event(8, InputEvent::MouseMotion { dx: 1000, dy: 100 }));
+    assert_eq!(mouse.position(), (639, 100));
+    mouse.update(&event(8, InputEvent::MousePosition { x: -1920, y: 200 }));
+    assert_eq!(mouse.position(), (0, 200));
+}
+
+fn value(page: u16, id: u16, value: i32, logical_max: i32) -> FieldValue {
+    FieldValue {
+        usage: HidUsage::new(page, id),
+        value,
+        logical_min: 0,
+        logical_max,
+        physical: None,
+        unit: 0,
+    }
+}
+
+#[test]
+fn normalizes_gamepad_input() {
+    let mut gamepad = GamepadState::default();
+    assert_eq!(gamepad.layout().family, GamepadFamily::Generic);
+    gamepad.update(&[
+        value(0x09, 1, 1, 1),
+        value(0x09, 4, 1, 1),
+        value(0x01, 0x30, 254, 254),
+        value(0x01, 0x31, 127, 254),
+        value(0x01, 0x32, 0, 255),
+        value(0x01, 0x35, 0, 255),
+        value(0x01, 0x33, 255, 255),
+        value(0x01, 0x34, 20, 255),
+        value(0x01, 0x39, 3, 7),
+        // unknown usages are ignored
+        value(0x02, 0xC4, 255, 255),
+    ]);
+    assert_eq!(gamepad.left_stick(), (1.0, 0.0));
+    let (x, y) = gamepad.right_stick();
+    // diagonals are kept within the unit circle
+    assert!((x + FRAC_1_SQRT_2).abs() < 1e-3 && (y + FRAC_1_SQRT_2).abs() < 1e-3);
+    assert_eq!(gamepad.left_trigger(), 1.0);
+    // within the deadzone
+    assert_eq!(gamepad.right_trigger(), 0.0);
+    assert_eq!(gamepad.hat(), Hat::DownRight);
+    assert_eq!(gamepad.pressed_buttons().collect::<Vec<_>>(), [1, 4]);
+
+    gamepad.update(&[
+        value(0x09, 1, 0, 1),
+        value(0x01, 0x30, 153, 255),
+        value(0x01, 0x34, 128, 255),
+        // the null state of the hat switch
+        value(0x01, 0x39, 8, 7),
+    ]);
+    assert!(!gamepad.is_pressed(1));
+    assert!(gamepad.is_pressed(4));
+    let (x, y) = gamepad.left_stick();
+    assert!((x - 0.1111).abs() < 1e-3 && y == 0.0);
+    assert!((gamepad.right_trigger() - 0.4458).abs() < 1e-3);
+    assert_eq!(gamepad.hat(), Hat::Centered);
+
+    gamepad.deadzone = 0.25;
+    assert_eq!(gamepad.left_stick(), (0.0, 0.0));
+    gamepad.deadzone = 0.0;
+    assert!((gamepad.left_stick().0 - 0.2).abs() < 1e-3);
+
+    // a four-way hat switch
+    gamepad.update(&[value(0x01, 0x39, 3, 3)]);
+    assert_eq!(gamepad.hat(), Hat::Left);
+}
+
+#[test]
+fn looks_up_gamepad_layouts() {
+    let xbox = GamepadLayout::lookup(0x045E, 0x0B12);
+    assert_eq!(xbox.name, "Xbox Series X|S Controller");
+    assert_eq!(xbox.family, GamepadFamily::Xbox);
+    assert_eq!(xbox.button_name(1), Some("A"));
+    assert_eq!(xbox.button_name(0), None);
+    assert_eq!(xbox.button_name(11), None);
+    assert_eq!(
+        GamepadLayout::lookup(0x054C, 0x0CE6).button_name(15),
+        Some("Mute")
+    );
+    // unknown models of known vendors
+    assert_eq!(
+        GamepadLayout::lookup(0x045E, 0xFFFF).name,
+        "Xbox Controller"
+    );
+    assert_eq!(
+        GamepadLayout::lookup(0x057E, 0x0000).family,
+        GamepadFamily::Nintendo
+    );
+    let generic = GamepadLayout::lookup(0x1234, 0x5678);
+    assert_eq!(generic.family, GamepadFamily::Generic);
+    assert_eq!(generic.button_name(1), None);
+
+    // the right stick of an Xbox controller is Rx and Ry
+    let mut gamepad = GamepadState::new(xbox);
+    gamepad.update(&[value(0x01, 0x33, 0, 65535), value(0x01, 0x32, 0, 65535)]);
+    assert_eq!(gamepad.right_stick(), (-1.0, 0.0));
+    assert_eq!(gamepad.left_trigger(), 0.0);
+}